@@ -1,6 +1,6 @@
 attribute vec4 coordinates;
+attribute vec4 vertex_color;
 
-uniform vec4 color;
 uniform mat4 camera;
 uniform mat4 transform;
 
@@ -8,5 +8,5 @@ varying lowp vec4 v_color;
 
 void main(void) {
     gl_Position = camera * transform * coordinates;
-    v_color = color;
+    v_color = vertex_color;
 }
@@ -6,8 +6,8 @@ varying float v_water_y_level;
 varying vec3 v_world_position;
 
 const float water_height = 0.1;
-const vec4 blue_1 = vec4(1.0, 1.0, 1.0, 0.3);
-const vec4 blue_2 = vec4(0.26, 0.47, 0.9, 0.5);
+// the surface fades from white into the fill of the water
+const vec3 foam = vec3(1.0, 1.0, 1.0);
 
 void main(void) {
     float water_start = v_water_y_level + sin(sin(v_world_position.x * 0.3) * 0.3 + v_time * 2.0) * water_height + water_height;
@@ -16,6 +16,6 @@ void main(void) {
     } else {
         float a = clamp((v_world_position.y - water_start) * 1.0, 0.0, 1.0);
 
-        gl_FragColor = vec4(mix(blue_1, blue_2, a).xyz, 0.3);
+        gl_FragColor = vec4(mix(foam, v_color.rgb, a), v_color.a);
     }
 }
@@ -1,9 +1,10 @@
 attribute vec4 coordinates;
+attribute vec4 vertex_color;
+// the surface of the water volume, before the transform
+attribute float water_y_level;
 
-uniform vec4 color;
 uniform mat4 camera;
 uniform mat4 transform;
-uniform float water_y_level;
 uniform float time;
 
 varying lowp vec4 v_color;
@@ -12,9 +13,10 @@ varying lowp float v_water_y_level;
 varying lowp vec3 v_world_position;
 
 void main(void) {
-    gl_Position = camera * transform * coordinates;
-    v_color = color;
+    vec4 world_position = transform * coordinates;
+    gl_Position = camera * world_position;
+    v_color = vertex_color;
     v_time = time;
-    v_water_y_level = water_y_level;
-    v_world_position = coordinates.xyz;
+    v_water_y_level = water_y_level + transform[3].y;
+    v_world_position = world_position.xyz;
 }
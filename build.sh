@@ -1 +1,2 @@
+cargo run --bin svg_to_level -- assets/map.svg assets/map.ron
 wasm-pack build --target web
@@ -0,0 +1,44 @@
+//! Writes a generated lake level, as SVG to open in Inkscape or as RON for the game.
+//!
+//! ```sh
+//! cargo run --bin generate_level -- 42 assets/lake-42.svg
+//! ```
+//!
+//! The format follows the extension of the output.
+
+use std::error::Error;
+use std::path::Path;
+use std::{env, fs, process};
+
+use rust_wasm_webgl::generator::{generate, generate_svg, LakeSettings};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (seed, output) = match &args[..] {
+        [seed, output] => match seed.parse::<u64>() {
+            Ok(seed) => (seed, Path::new(output)),
+            Err(_) => usage(),
+        },
+        _ => usage(),
+    };
+
+    if let Err(e) = write(seed, output) {
+        eprintln!("{}: {e}", output.display());
+        process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: generate_level <seed> <level.svg|level.ron>");
+    process::exit(2);
+}
+
+fn write(seed: u64, output: &Path) -> Result<(), Box<dyn Error>> {
+    let settings = LakeSettings::default();
+    let content = match output.extension().and_then(|extension| extension.to_str()) {
+        Some("ron") => generate(seed, &settings)?.to_ron()?,
+        _ => generate_svg(seed, &settings).to_string(),
+    };
+    fs::write(output, content)?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! Reports problems with levels before they are shipped, exits with 1 if there are any.
+//! Only SVG levels can be checked, RON files do not keep the warnings found while loading.
+//!
+//! ```sh
+//! cargo run --bin lint_level -- assets/map.svg
+//! ```
+
+use std::{env, fs, process};
+
+use rust_wasm_webgl::level::Level;
+use rust_wasm_webgl::lint::lint;
+
+fn main() {
+    let paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: lint_level <level.svg>...");
+        process::exit(2);
+    }
+
+    let mut failed = false;
+    for path in paths {
+        let problems = match fs::read_to_string(&path) {
+            Ok(content) if !content.trim_start().starts_with('<') => {
+                vec!["not an SVG level, lint the SVG it was converted from".to_string()]
+            }
+            Ok(content) => match Level::load_from_svg_str(&content) {
+                Ok(level) => lint(&level).iter().map(|p| p.to_string()).collect(),
+                Err(e) => vec![e.to_string()],
+            },
+            Err(e) => vec![e.to_string()],
+        };
+        for problem in &problems {
+            println!("{path}: {problem}");
+        }
+        failed |= !problems.is_empty();
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
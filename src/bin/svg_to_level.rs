@@ -0,0 +1,37 @@
+//! Converts a level SVG into the pre-triangulated RON format that loads much faster.
+//!
+//! ```sh
+//! cargo run --bin svg_to_level -- assets/map.svg assets/map.ron
+//! ```
+//!
+//! The output defaults to the input with a `.ron` extension.
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+use rust_wasm_webgl::level::Level;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (input, output) = match &args[..] {
+        [input] => (PathBuf::from(input), Path::new(input).with_extension("ron")),
+        [input, output] => (PathBuf::from(input), PathBuf::from(output)),
+        _ => {
+            eprintln!("usage: svg_to_level <level.svg> [<level.ron>]");
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = convert(&input, &output) {
+        eprintln!("{}: {e}", input.display());
+        process::exit(1);
+    }
+}
+
+fn convert(input: &Path, output: &Path) -> Result<(), Box<dyn Error>> {
+    let content = fs::read_to_string(input)?;
+    let level = Level::load_from_svg_str(&content)?;
+    fs::write(output, level.to_ron()?)?;
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! What part of the world is on screen. The camera follows an entity, the player by default,
+//! once it leaves a dead zone around the center of the screen, and stays inside the level's
+//! camera bounds.
+
+use crate::drawing::Mat4;
+use crate::metadata::CameraFraming;
+use crate::svg_path::Point;
+
+pub struct Camera {
+    /// The id of the entity to follow, `None` to stay put.
+    pub target: Option<String>,
+    /// The point in the middle of the screen.
+    pub center: Point,
+    /// The visible width at a zoom of `1`.
+    pub width: f32,
+    /// `2` shows half the width.
+    pub zoom: f32,
+    /// How far the target can be from the center before the camera follows it, in world
+    /// units horizontally and vertically.
+    pub dead_zone: (f32, f32),
+    /// The fraction of the way to the target that is left after a second, `0` to stay on top
+    /// of it.
+    pub smoothing: f32,
+    /// `(x, y, width, height)` of the area the camera stays in, it shows the center of the
+    /// bounds if it is bigger than them.
+    pub bounds: Option<(f32, f32, f32, f32)>,
+    /// The size of the canvas in pixels.
+    pub screen_size: (f32, f32),
+}
+
+impl Camera {
+    pub fn new(framing: &CameraFraming, bounds: Option<(f32, f32, f32, f32)>) -> Camera {
+        Camera {
+            target: Some("player".to_string()),
+            center: framing.center,
+            width: framing.width,
+            zoom: 1.0,
+            dead_zone: (framing.width / 8.0, framing.width / 16.0),
+            smoothing: 0.05,
+            bounds,
+            screen_size: (1.0, 1.0),
+        }
+    }
+
+    /// The visible width and height in world units.
+    pub fn visible_size(&self) -> (f32, f32) {
+        let width = self.width / self.zoom;
+        let (screen_width, screen_height) = self.screen_size;
+        (width, width * screen_height / screen_width)
+    }
+
+    /// Moves toward the position of the target, `time_passed` seconds after the last update.
+    pub fn update(&mut self, target_position: Option<Point>, time_passed: f32) {
+        if let Some((x, y)) = target_position {
+            let goal = (
+                into_dead_zone(self.center.0, x, self.dead_zone.0),
+                into_dead_zone(self.center.1, y, self.dead_zone.1),
+            );
+            let left = self.smoothing.powf(time_passed);
+            self.center = (
+                goal.0 + (self.center.0 - goal.0) * left,
+                goal.1 + (self.center.1 - goal.1) * left,
+            );
+        }
+
+        if let Some((x, y, width, height)) = self.bounds {
+            let (visible_width, visible_height) = self.visible_size();
+            self.center = (
+                clamp_center(self.center.0, x, width, visible_width),
+                clamp_center(self.center.1, y, height, visible_height),
+            );
+        }
+    }
+
+    /// Maps the world to clip space.
+    pub fn matrix(&self) -> Mat4 {
+        // clip space is 2 units wide
+        let (width, height) = self.visible_size();
+        let scale = (2.0 / width, 2.0 / height);
+
+        let mut tmp1 = mat4::new_identity();
+        let mut tmp2 = mat4::new_identity();
+        mat4::scale(&mut tmp1, &tmp2, &[scale.0, -scale.1, 1.0]);
+        mat4::translate(&mut tmp2, &tmp1, &[-self.center.0, -self.center.1, 0.0]);
+        tmp2
+    }
+
+    /// The point in the world under `screen`, in pixels from the top left of the canvas.
+    pub fn screen_to_world(&self, screen: Point) -> Point {
+        let (width, height) = self.visible_size();
+        (
+            self.center.0 + (screen.0 / self.screen_size.0 - 0.5) * width,
+            self.center.1 + (screen.1 / self.screen_size.1 - 0.5) * height,
+        )
+    }
+}
+
+// where the center has to be for `target` to be at the edge of the dead zone, if it is outside
+fn into_dead_zone(center: f32, target: f32, dead_zone: f32) -> f32 {
+    center.clamp(target - dead_zone, target + dead_zone)
+}
+
+fn clamp_center(center: f32, start: f32, size: f32, visible: f32) -> f32 {
+    if visible >= size {
+        start + size / 2.0
+    } else {
+        center.clamp(start + visible / 2.0, start + size - visible / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Camera;
+    use crate::metadata::CameraFraming;
+
+    fn camera() -> Camera {
+        let framing = CameraFraming {
+            center: (0.0, 0.0),
+            width: 20.0,
+        };
+        let mut camera = Camera::new(&framing, None);
+        camera.screen_size = (200.0, 100.0);
+        camera.dead_zone = (2.0, 1.0);
+        camera
+    }
+
+    #[test]
+    fn follow() {
+        let mut camera = camera();
+        camera.update(Some((1.0, -0.5)), 1.0);
+        assert_eq!(camera.center, (0.0, 0.0));
+
+        camera.smoothing = 0.0;
+        camera.update(Some((5.0, -3.0)), 1.0);
+        assert_eq!(camera.center, (3.0, -2.0));
+
+        // half of the way after a second
+        camera.smoothing = 0.5;
+        camera.update(Some((7.0, -2.0)), 1.0);
+        assert_eq!(camera.center, (4.0, -2.0));
+        camera.update(Some((7.0, -2.0)), 2.0);
+        assert_eq!(camera.center, (4.75, -2.0));
+    }
+
+    #[test]
+    fn bounds() {
+        let mut camera = camera();
+        camera.smoothing = 0.0;
+        camera.bounds = Some((0.0, 0.0, 100.0, 5.0));
+        camera.update(Some((-50.0, 20.0)), 1.0);
+        // the left edge of the screen is at the left edge of the bounds, the bounds are not as
+        // high as the screen
+        assert_eq!(camera.center, (10.0, 2.5));
+
+        camera.zoom = 2.0;
+        camera.update(Some((-50.0, 20.0)), 1.0);
+        assert_eq!(camera.center, (5.0, 2.5));
+    }
+
+    #[test]
+    fn screen_to_world() {
+        let mut camera = camera();
+        camera.center = (10.0, 5.0);
+        assert_eq!(camera.screen_to_world((100.0, 50.0)), (10.0, 5.0));
+        assert_eq!(camera.screen_to_world((0.0, 100.0)), (0.0, 10.0));
+        camera.zoom = 2.0;
+        assert_eq!(camera.screen_to_world((200.0, 0.0)), (15.0, 2.5));
+    }
+}
@@ -0,0 +1,361 @@
+use crate::types::Color;
+
+/// The value of a `fill` or `stroke` property.
+///
+/// See https://www.w3.org/TR/SVG/painting.html#SpecifyingPaint
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    None,
+    Color(Color),
+    CurrentColor,
+    /// A reference to a paint server like a gradient, by id.
+    Url(String),
+}
+
+impl Paint {
+    pub fn parse(value: &str) -> Option<Paint> {
+        match value.trim() {
+            "none" => Some(Paint::None),
+            "currentColor" | "currentcolor" => Some(Paint::CurrentColor),
+            value if value.starts_with("url(") => {
+                // a fallback color after the reference is ignored
+                let (reference, _fallback) = value.split_once(')')?;
+                let id = reference
+                    .strip_prefix("url(")?
+                    .trim()
+                    .trim_matches(|c| c == '"' || c == '\'');
+                Some(Paint::Url(id.strip_prefix('#')?.to_string()))
+            }
+            value => parse_color(value).map(Paint::Color),
+        }
+    }
+}
+
+/// Parses an `opacity`, `fill-opacity` or `stroke-opacity` value, clamped to `0..=1`.
+pub fn parse_opacity(value: &str) -> Option<f32> {
+    parse_number_or_percentage(value.trim(), 1.0).map(|v| v.clamp(0.0, 1.0))
+}
+
+/// Parses a CSS color: `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb()`, `rgba()`, `hsl()`,
+/// `hsla()` and the named colors.
+///
+/// See https://www.w3.org/TR/css-color-4/
+pub fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+
+    if let Some((function, args)) = value.split_once('(') {
+        let args = parse_arguments(args.strip_suffix(')')?)?;
+        return match function.trim().to_ascii_lowercase().as_str() {
+            "rgb" | "rgba" => parse_rgb(&args),
+            "hsl" | "hsla" => parse_hsl(&args),
+            _ => None,
+        };
+    }
+
+    let value = value.to_ascii_lowercase();
+    if value == "transparent" {
+        return Some([0.0, 0.0, 0.0, 0.0]);
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, rgb)| {
+            let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32 / 255.0;
+            [channel(16), channel(8), channel(0), 1.0]
+        })
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    let channels: Vec<u8> = match hex.len() {
+        3 | 4 => (0..hex.len())
+            .map(|i| digit(i).map(|d| d * 17))
+            .collect::<Option<_>>()?,
+        6 | 8 => (0..hex.len()).step_by(2).map(pair).collect::<Option<_>>()?,
+        _ => return None,
+    };
+
+    let mut color = [1.0; 4];
+    for (out, channel) in color.iter_mut().zip(channels) {
+        *out = channel as f32 / 255.0;
+    }
+    Some(color)
+}
+
+// splits `10, 20, 30` as well as `10 20 30 / 50%`
+fn parse_arguments(args: &str) -> Option<Vec<&str>> {
+    let args: Vec<&str> = args
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|arg| !arg.is_empty())
+        .collect();
+
+    if args.len() == 3 || args.len() == 4 {
+        Some(args)
+    } else {
+        None
+    }
+}
+
+fn parse_number_or_percentage(value: &str, percent_of: f32) -> Option<f32> {
+    match value.strip_suffix('%') {
+        Some(percentage) => percentage
+            .parse::<f32>()
+            .ok()
+            .map(|p| p / 100.0 * percent_of),
+        None => value.parse::<f32>().ok(),
+    }
+}
+
+fn parse_alpha(args: &[&str]) -> Option<f32> {
+    match args.get(3) {
+        Some(alpha) => parse_opacity(alpha),
+        None => Some(1.0),
+    }
+}
+
+fn parse_rgb(args: &[&str]) -> Option<Color> {
+    let channel =
+        |i: usize| parse_number_or_percentage(args[i], 255.0).map(|v| v.clamp(0.0, 255.0) / 255.0);
+    Some([channel(0)?, channel(1)?, channel(2)?, parse_alpha(args)?])
+}
+
+fn parse_hsl(args: &[&str]) -> Option<Color> {
+    let hue = args[0].trim_end_matches("deg").parse::<f32>().ok()?;
+    let saturation = parse_number_or_percentage(args[1], 1.0)?.clamp(0.0, 1.0);
+    let lightness = parse_number_or_percentage(args[2], 1.0)?.clamp(0.0, 1.0);
+
+    // https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+    let channel = |n: f32| {
+        let k = (n + hue / 30.0).rem_euclid(12.0);
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    Some([channel(0.0), channel(8.0), channel(4.0), parse_alpha(args)?])
+}
+
+// https://www.w3.org/TR/css-color-4/#named-colors
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_color, parse_opacity, Paint};
+
+    #[test]
+    fn hex() {
+        assert_eq!(parse_color("#ff0000"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(parse_color("#FF0000"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(parse_color("#f00"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(parse_color("#f008"), Some([1.0, 0.0, 0.0, 136.0 / 255.0]));
+        assert_eq!(
+            parse_color("#00ff0080"),
+            Some([0.0, 1.0, 0.0, 128.0 / 255.0])
+        );
+        assert_eq!(parse_color("#00ff0"), None);
+        assert_eq!(parse_color("#zzzzzz"), None);
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(parse_color("rgb(255, 0, 0)"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(parse_color("rgb(100%,0%,0%)"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(
+            parse_color("rgba(0, 0, 255, 0.5)"),
+            Some([0.0, 0.0, 1.0, 0.5])
+        );
+        assert_eq!(
+            parse_color("rgb(0 0 255 / 50%)"),
+            Some([0.0, 0.0, 1.0, 0.5])
+        );
+        assert_eq!(
+            parse_color("hsl(120, 100%, 50%)"),
+            Some([0.0, 1.0, 0.0, 1.0])
+        );
+        assert_eq!(
+            parse_color("hsla(240deg, 100%, 50%, 0.25)"),
+            Some([0.0, 0.0, 1.0, 0.25])
+        );
+        assert_eq!(parse_color("rgb(1, 2)"), None);
+        assert_eq!(parse_color("cmyk(1, 2, 3, 4)"), None);
+    }
+
+    #[test]
+    fn named() {
+        assert_eq!(parse_color("red"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(parse_color("SeaGreen"), parse_color("#2e8b57"));
+        assert_eq!(parse_color("transparent"), Some([0.0, 0.0, 0.0, 0.0]));
+        assert_eq!(parse_color("blurple"), None);
+    }
+
+    #[test]
+    fn paint_and_opacity() {
+        assert_eq!(Paint::parse("none"), Some(Paint::None));
+        assert_eq!(Paint::parse("currentColor"), Some(Paint::CurrentColor));
+        assert_eq!(
+            Paint::parse(" black "),
+            Some(Paint::Color([0.0, 0.0, 0.0, 1.0]))
+        );
+        assert_eq!(parse_opacity("0.49"), Some(0.49));
+        assert_eq!(parse_opacity("50%"), Some(0.5));
+        assert_eq!(parse_opacity("2"), Some(1.0));
+        assert_eq!(parse_opacity("half"), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! Just enough CSS for the `<style>` sheets that Inkscape and other editors write.
+//!
+//! Only simple selectors are supported: `*`, `rect`, `.class`, `#id` and combinations like
+//! `path.water`, in comma separated lists. Rules with any other selector and at-rules like
+//! `@media` are ignored.
+
+/// A parsed `<style>` sheet, the rules in document order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StyleSheet {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    selectors: Vec<Selector>,
+    declarations: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Selector {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl Selector {
+    fn parse(value: &str) -> Option<Selector> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+
+        let mut selector = Selector::default();
+        // every class and id starts at its `.` or `#`, what comes before them is the tag name
+        let mut starts: Vec<usize> = value.match_indices(['.', '#']).map(|(i, _)| i).collect();
+        starts.push(value.len());
+
+        match &value[..starts[0]] {
+            "" | "*" => {}
+            tag if is_name(tag) => selector.tag = Some(tag.to_string()),
+            _ => return None,
+        }
+        for part in starts.windows(2) {
+            let name = &value[part[0] + 1..part[1]];
+            if !is_name(name) {
+                return None;
+            }
+            if value[part[0]..].starts_with('.') {
+                selector.classes.push(name.to_string());
+            } else if selector.id.replace(name.to_string()).is_some() {
+                return None;
+            }
+        }
+        Some(selector)
+    }
+
+    fn matches(&self, tag: &str, id: Option<&str>, classes: &[&str]) -> bool {
+        self.tag.iter().all(|t| t == tag)
+            && self.id.iter().all(|i| Some(i.as_str()) == id)
+            && self.classes.iter().all(|c| classes.contains(&c.as_str()))
+    }
+
+    // https://www.w3.org/TR/selectors-3/#specificity
+    fn specificity(&self) -> (usize, usize, usize) {
+        (
+            self.id.iter().count(),
+            self.classes.len(),
+            self.tag.iter().count(),
+        )
+    }
+}
+
+impl StyleSheet {
+    pub fn parse(content: &str) -> StyleSheet {
+        let content = strip_comments(content);
+        let mut rules = vec![];
+        let mut rest = content.as_str();
+
+        while let Some(open) = rest.find('{') {
+            let prelude = rest[..open].trim();
+            let close = match block_end(&rest[open..]) {
+                Some(close) => open + close,
+                None => break,
+            };
+            let block = &rest[open + 1..close];
+            rest = &rest[close + 1..];
+
+            if prelude.starts_with('@') {
+                continue;
+            }
+            let selectors: Option<Vec<Selector>> =
+                prelude.split(',').map(Selector::parse).collect();
+            if let Some(selectors) = selectors {
+                rules.push(Rule {
+                    selectors,
+                    declarations: parse_declarations(block),
+                });
+            }
+        }
+
+        StyleSheet { rules }
+    }
+
+    /// The declarations that apply to an element, the ones that win come last.
+    pub fn declarations<'a>(
+        &'a self,
+        tag: &str,
+        id: Option<&str>,
+        class: Option<&str>,
+    ) -> Vec<(&'a str, &'a str)> {
+        if self.rules.is_empty() {
+            return vec![];
+        }
+        let classes: Vec<&str> = class.map_or(vec![], |c| c.split_whitespace().collect());
+
+        let mut matching: Vec<((usize, usize, usize), &Rule)> = self
+            .rules
+            .iter()
+            .filter_map(|rule| {
+                rule.selectors
+                    .iter()
+                    .filter(|selector| selector.matches(tag, id, &classes))
+                    .map(|selector| selector.specificity())
+                    .max()
+                    .map(|specificity| (specificity, rule))
+            })
+            .collect();
+        // stable, so later rules still win over earlier ones with the same specificity
+        matching.sort_by_key(|(specificity, _)| *specificity);
+
+        matching
+            .into_iter()
+            .flat_map(|(_, rule)| rule.declarations.iter())
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+}
+
+fn is_name(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+fn strip_comments(content: &str) -> String {
+    let mut result = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("/*") {
+        result.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    result.push_str(rest);
+    result
+}
+
+// the index of the `}` that closes the block starting at the beginning of `content`, blocks of
+// at-rules can be nested
+fn block_end(content: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in content.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_declarations(block: &str) -> Vec<(String, String)> {
+    block
+        .split(';')
+        .filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            let value = value.trim();
+            let value = value.strip_suffix("!important").unwrap_or(value).trim();
+            Some((name.trim().to_string(), value.to_string()))
+        })
+        .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StyleSheet;
+
+    #[test]
+    fn declarations() {
+        let sheet = StyleSheet::parse(
+            r"
+            /* generated */
+            .water { fill: blue; opacity: 0.5 }
+            rect, .ground { fill: #fff; stroke: black !important; }
+            @media print { .water { fill: gray } }
+            #lake.water { fill: teal }
+            g > .water { fill: red }
+            .water { fill: navy; }
+            ",
+        );
+
+        assert_eq!(
+            sheet.declarations("rect", None, Some("water")),
+            vec![
+                ("fill", "#fff"),
+                ("stroke", "black"),
+                ("fill", "blue"),
+                ("opacity", "0.5"),
+                ("fill", "navy"),
+            ]
+        );
+        assert_eq!(
+            sheet
+                .declarations("path", Some("lake"), Some("big water"))
+                .last(),
+            Some(&("fill", "teal"))
+        );
+        assert_eq!(sheet.declarations("path", None, Some("wat")), vec![]);
+        assert_eq!(sheet.declarations("circle", None, None), vec![]);
+    }
+}
@@ -1,136 +1,351 @@
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
 use wasm_bindgen::JsValue;
-use web_sys::{WebGlBuffer, WebGlProgram, WebGlRenderingContext, WebGlUniformLocation};
+use web_sys::WebGlRenderingContext;
 
 use crate::{
-    types::Color,
-    types::Triangle,
-    utils::{float_32_array, uint_16_array},
+    camera::Camera,
+    gl::Gl,
+    log,
+    mesh::{DrawRange, Mesh, Primitive, Program, StaticMesh},
+    types::{Color, Entity},
 };
 
-type Mat4 = [f32; 16];
+pub type Mat4 = [f32; 16];
 
-#[derive(Debug, Clone)]
-pub struct Shader {
-    pub program: WebGlProgram,
+// how many frames the render stats are averaged over
+const STATS_FRAMES: usize = 100;
+
+pub struct Shader<G: Gl = WebGlRenderingContext> {
+    pub program: G::Program,
     pub coordinate_index: u32,
-    pub camera_index: WebGlUniformLocation,
-    pub transform_index: WebGlUniformLocation,
-    pub color_index: WebGlUniformLocation,
+    pub camera_index: G::UniformLocation,
+    pub transform_index: G::UniformLocation,
 }
 
-pub struct WaterShader {
-    pub base: Shader,
-    pub time_index: WebGlUniformLocation,
-    pub water_y_level_index: WebGlUniformLocation,
+pub struct DefaultShader<G: Gl = WebGlRenderingContext> {
+    pub base: Shader<G>,
+    pub vertex_color_index: u32,
 }
 
-pub struct Renderer {
-    pub coordinate_buffer: WebGlBuffer,
-    pub index_buffer: WebGlBuffer,
-    pub gl: WebGlRenderingContext,
-    pub camera: Mat4,
-    pub time: f32,
+pub struct WaterShader<G: Gl = WebGlRenderingContext> {
+    pub base: Shader<G>,
+    pub time_index: G::UniformLocation,
+    pub vertex_color_index: u32,
+    pub water_y_level_index: u32,
+}
+
+/// What drawing cost, summed up over `frames`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub frames: usize,
+    pub draw_calls: usize,
+    /// Calls to `buffer_data`.
+    pub uploads: usize,
+    pub uploaded_bytes: usize,
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let frames = self.frames.max(1) as f32;
+        write!(
+            f,
+            "{:.1} draw calls, {:.1} uploads ({:.1} KiB) per frame",
+            self.draw_calls as f32 / frames,
+            self.uploads as f32 / frames,
+            self.uploaded_bytes as f32 / frames / 1024.0
+        )
+    }
+}
 
-    pub shader: Shader,
-    pub water_shader: WaterShader,
+// a mesh on the GPU
+struct MeshBuffers<G: Gl> {
+    positions: G::Buffer,
+    colors: G::Buffer,
+    water_levels: G::Buffer,
+    indices: G::Buffer,
+    draws: Vec<DrawRange>,
 }
 
-impl Renderer {
-    pub fn use_shader(
-        &self,
-        tri: &Triangle,
-        transform_offset: (f32, f32),
-        transform_rotation: f32,
+impl<G: Gl> MeshBuffers<G> {
+    fn new(gl: &G) -> Result<MeshBuffers<G>, JsValue> {
+        let buffer = || gl.create_buffer().ok_or("failed to create buffer");
+        Ok(MeshBuffers {
+            positions: buffer()?,
+            colors: buffer()?,
+            water_levels: buffer()?,
+            indices: buffer()?,
+            draws: vec![],
+        })
+    }
+
+    fn upload(
+        &mut self,
+        gl: &G,
+        mesh: &Mesh,
+        usage: u32,
+        stats: &mut RenderStats,
     ) -> Result<(), JsValue> {
-        let shader = &self.shader;
-
-        self.gl.use_program(Some(&shader.program));
-        self.gl
-            .uniform_matrix4fv_with_f32_array(Some(&shader.camera_index), false, &self.camera);
-        self.gl.uniform_matrix4fv_with_f32_array(
-            Some(&shader.transform_index),
-            false,
-            &make_transform(transform_offset, transform_rotation),
-        );
-        self.gl
-            .uniform4fv_with_f32_array(Some(&shader.color_index), &tri.color);
+        use WebGlRenderingContext as GL;
+
+        for (buffer, data) in [
+            (&self.positions, &mesh.positions),
+            (&self.colors, &mesh.colors),
+            (&self.water_levels, &mesh.water_levels),
+        ] {
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(buffer));
+            gl.buffer_data_f32(GL::ARRAY_BUFFER, data, usage)?;
+            stats.uploaded_bytes += data.len() * 4;
+        }
+        gl.bind_buffer(GL::ARRAY_BUFFER, None);
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.indices));
+        gl.buffer_data_u32(GL::ELEMENT_ARRAY_BUFFER, &mesh.indices, usage)?;
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, None);
+        stats.uploaded_bytes += mesh.indices.len() * 4;
+        stats.uploads += 4;
+
+        self.draws = mesh.draws.clone();
         Ok(())
     }
+}
 
-    pub fn use_water_shader(&self, color: &Color, water_y_level: f32) -> Result<(), JsValue> {
-        let shader = &self.water_shader.base;
+/// Where frames are drawn, [`WebGlBackend`] in the browser and [`crate::raster::Rasterizer`]
+/// in tests. See [`draw_frame`].
+pub trait Backend {
+    type Error;
 
-        self.gl.use_program(Some(&shader.program));
-        self.gl
-            .uniform_matrix4fv_with_f32_array(Some(&shader.camera_index), false, &self.camera);
-        self.gl.uniform_matrix4fv_with_f32_array(
-            Some(&shader.transform_index),
-            false,
-            &mat4::new_identity(),
-        );
-        self.gl
-            .uniform4fv_with_f32_array(Some(&shader.color_index), color);
+    /// The width and height of the frame in pixels.
+    fn size(&self) -> (u32, u32);
+    /// Clears the frame. `camera` maps world coordinates to clip space for everything drawn
+    /// until the next frame, `time` in seconds animates the water.
+    fn begin_frame(
+        &mut self,
+        clear_color: Color,
+        camera: Mat4,
+        time: f32,
+    ) -> Result<(), Self::Error>;
+    /// Draws the `key.1`th static mesh of the entity `key.0`. Backends can keep what they
+    /// prepared for the mesh until its version changes.
+    fn draw_static(&mut self, key: (&str, usize), mesh: &StaticMesh) -> Result<(), Self::Error>;
+    /// Draws triangles that change every frame, in world coordinates.
+    fn draw_mesh(&mut self, mesh: &Mesh) -> Result<(), Self::Error>;
+    fn finish_frame(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
 
-        self.gl
-            .uniform1f(Some(&self.water_shader.water_y_level_index), water_y_level);
-        self.gl
-            .uniform1f(Some(&self.water_shader.time_index), self.time);
+/// Draws the entities by z-index. The triangles of entities without static meshes are batched
+/// per z-index and drawn after the static meshes with the same z-index, with one draw call per
+/// run of the same shader.
+pub fn draw_frame<'a, B: Backend>(
+    backend: &mut B,
+    clear_color: Color,
+    camera: &Camera,
+    entities: impl IntoIterator<Item = &'a dyn Entity>,
+    time: f32,
+) -> Result<(), B::Error> {
+    backend.begin_frame(clear_color, camera.matrix(), time)?;
 
-        Ok(())
+    let mut entities: Vec<&dyn Entity> = entities.into_iter().collect();
+    entities.sort_by(|a, b| (a.z_index(), a.id()).cmp(&(b.z_index(), b.id())));
+
+    let mut static_meshes = vec![];
+    let mut batches: BTreeMap<i32, Mesh> = BTreeMap::new();
+    for entity in entities {
+        match entity.static_meshes() {
+            Some(meshes) => {
+                let keyed = meshes.into_iter().enumerate();
+                static_meshes.extend(keyed.map(|(i, mesh)| ((entity.id(), i), mesh)));
+            }
+            None => batches.entry(entity.z_index()).or_default().push(
+                entity.triangles(),
+                entity.position(),
+                entity.rotation(),
+            ),
+        }
     }
+    // stable, meshes with the same z-index stay in entity order
+    static_meshes.sort_by_key(|(_, mesh)| mesh.z_index);
 
-    pub fn triangle(
-        &self,
-        triangle: &Triangle,
-        transform_offset: (f32, f32),
-        transform_rotation: f32,
-    ) -> Result<(), JsValue> {
-        const NUM_COORDINATES: i32 = 3;
+    let mut batches = batches.into_iter().peekable();
+    for (key, mesh) in &static_meshes {
+        while let Some((_, batch)) = batches.next_if(|(z_index, _)| *z_index < mesh.z_index) {
+            backend.draw_mesh(&batch)?;
+        }
+        backend.draw_static((key.0, key.1), mesh)?;
+    }
+    for (_, batch) in batches {
+        backend.draw_mesh(&batch)?;
+    }
+
+    backend.finish_frame()
+}
+
+/// Draws static meshes from buffers that are only uploaded when they change, and other
+/// triangles from buffers that are uploaded once per frame. Consecutive triangles with the same
+/// shader are drawn with one draw call, and the program is only switched when it changes.
+pub struct WebGlBackend<G: Gl = WebGlRenderingContext> {
+    pub gl: G,
+    pub camera: Mat4,
+    pub time: f32,
+    pub stats: RenderStats,
+
+    pub shader: DefaultShader<G>,
+    pub water_shader: WaterShader<G>,
+
+    // by entity id and index of the static mesh, with the version that was uploaded
+    static_meshes: HashMap<(String, usize), (u64, MeshBuffers<G>)>,
+    dynamic_buffers: MeshBuffers<G>,
+    // the program in use, unknown at the start of a frame
+    program: Cell<Option<Program>>,
+}
+
+impl<G: Gl> WebGlBackend<G> {
+    pub fn new(
+        gl: G,
+        shader: DefaultShader<G>,
+        water_shader: WaterShader<G>,
+    ) -> Result<WebGlBackend<G>, JsValue> {
+        // meshes can have more than 65536 vertices
+        if !gl.enable_extension("OES_element_index_uint")? {
+            return Err("OES_element_index_uint is not supported".into());
+        }
+
+        Ok(WebGlBackend {
+            dynamic_buffers: MeshBuffers::new(&gl)?,
+            gl,
+            camera: mat4::new_identity(),
+            time: 0.0,
+            stats: RenderStats::default(),
+            shader,
+            water_shader,
+            static_meshes: HashMap::new(),
+            program: Cell::new(None),
+        })
+    }
+
+    // returns the number of draw calls
+    fn draw_buffers(&self, buffers: &MeshBuffers<G>, offset: (f32, f32)) -> usize {
         use WebGlRenderingContext as GL;
         let gl = &self.gl;
+        let transform = make_transform(offset, 0.0);
 
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.coordinate_buffer));
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&buffers.indices));
+        for draw in &buffers.draws {
+            let (shader, color_index, water_level_index) = match draw.program {
+                Program::Default => (&self.shader.base, self.shader.vertex_color_index, None),
+                Program::Water => (
+                    &self.water_shader.base,
+                    self.water_shader.vertex_color_index,
+                    Some(self.water_shader.water_y_level_index),
+                ),
+            };
 
-        gl.buffer_data_with_array_buffer_view(
-            GL::ARRAY_BUFFER,
-            &float_32_array(&triangle.coords)?.into(),
-            GL::STATIC_DRAW,
-        );
-        gl.bind_buffer(GL::ARRAY_BUFFER, None);
+            if self.program.replace(Some(draw.program)) != Some(draw.program) {
+                gl.use_program(&shader.program);
+            }
+            gl.uniform_matrix4fv(&shader.camera_index, &self.camera);
+            gl.uniform_matrix4fv(&shader.transform_index, &transform);
+            if draw.program == Program::Water {
+                gl.uniform1f(&self.water_shader.time_index, self.time);
+            }
 
-        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.index_buffer));
-        gl.buffer_data_with_array_buffer_view(
-            GL::ELEMENT_ARRAY_BUFFER,
-            &uint_16_array(&[0, 1, 2])?.into(),
-            GL::STATIC_DRAW,
-        );
-        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, None);
+            gl.bind_attribute(&buffers.positions, shader.coordinate_index, 3);
+            gl.bind_attribute(&buffers.colors, color_index, 4);
+            if let Some(index) = water_level_index {
+                gl.bind_attribute(&buffers.water_levels, index, 1);
+            }
+
+            let mode = match draw.primitive {
+                Primitive::Triangles => GL::TRIANGLES,
+                Primitive::Lines => GL::LINES,
+            };
+            gl.draw_elements(
+                mode,
+                draw.count as i32,
+                GL::UNSIGNED_INT,
+                draw.start as i32 * 4,
+            );
 
-        // bind buffer
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.coordinate_buffer));
-        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.index_buffer));
-        gl.vertex_attrib_pointer_with_i32(self.shader.coordinate_index, 3, GL::FLOAT, false, 0, 0);
-        gl.enable_vertex_attrib_array(self.shader.coordinate_index);
-
-        // use shader
-        if triangle.color[0] < 0.0001 && triangle.color[1] < triangle.color[2] {
-            // spaghetti is served 🍝
-            let water_y_level = triangle.coords[1]
-                .min(triangle.coords[4])
-                .min(triangle.coords[7]);
-
-            self.use_water_shader(&triangle.color, water_y_level)?;
-        } else {
-            self.use_shader(&triangle, transform_offset, transform_rotation)?;
+            gl.disable_vertex_attrib_array(color_index);
+            if let Some(index) = water_level_index {
+                gl.disable_vertex_attrib_array(index);
+            }
         }
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, None);
+
+        buffers.draws.len()
+    }
+}
+
+impl<G: Gl> Backend for WebGlBackend<G> {
+    type Error = JsValue;
 
-        // draw
-        if triangle.wireframe {
-            gl.draw_elements_with_i32(GL::LINE_LOOP, NUM_COORDINATES, GL::UNSIGNED_SHORT, 0);
-        } else {
-            gl.draw_elements_with_i32(GL::TRIANGLES, NUM_COORDINATES, GL::UNSIGNED_SHORT, 0);
+    fn size(&self) -> (u32, u32) {
+        self.gl.drawing_buffer_size()
+    }
+
+    fn begin_frame(&mut self, clear_color: Color, camera: Mat4, time: f32) -> Result<(), JsValue> {
+        use WebGlRenderingContext as GL;
+        let gl = &self.gl;
+        self.camera = camera;
+        self.time = time;
+        self.program.set(None);
+
+        gl.enable(GL::BLEND);
+        gl.blend_func(GL::SRC_ALPHA, GL::ONE_MINUS_SRC_ALPHA);
+
+        gl.clear(clear_color);
+
+        let (width, height) = gl.drawing_buffer_size();
+        gl.viewport(0, 0, width as i32, height as i32);
+        Ok(())
+    }
+
+    fn draw_static(&mut self, key: (&str, usize), mesh: &StaticMesh) -> Result<(), JsValue> {
+        use WebGlRenderingContext as GL;
+
+        let key = (key.0.to_string(), key.1);
+        let uploaded =
+            matches!(self.static_meshes.get(&key), Some((version, _)) if *version == mesh.version);
+        if !uploaded {
+            let mut vertices = Mesh::default();
+            vertices.push(mesh.triangles, (0.0, 0.0), 0.0);
+            let mut buffers = match self.static_meshes.remove(&key) {
+                Some((_, buffers)) => buffers,
+                None => MeshBuffers::new(&self.gl)?,
+            };
+            buffers.upload(&self.gl, &vertices, GL::STATIC_DRAW, &mut self.stats)?;
+            self.static_meshes
+                .insert(key.clone(), (mesh.version, buffers));
         }
 
+        let (_, buffers) = &self.static_meshes[&key];
+        self.stats.draw_calls += self.draw_buffers(buffers, mesh.offset);
+        Ok(())
+    }
+
+    fn draw_mesh(&mut self, mesh: &Mesh) -> Result<(), JsValue> {
+        use WebGlRenderingContext as GL;
+
+        self.dynamic_buffers
+            .upload(&self.gl, mesh, GL::DYNAMIC_DRAW, &mut self.stats)?;
+        self.stats.draw_calls += self.draw_buffers(&self.dynamic_buffers, (0.0, 0.0));
+        Ok(())
+    }
+
+    /// Logs the render stats every `STATS_FRAMES` frames.
+    fn finish_frame(&mut self) -> Result<(), JsValue> {
+        self.stats.frames += 1;
+        if self.stats.frames == STATS_FRAMES {
+            log!("{}", self.stats);
+            self.stats = RenderStats::default();
+        }
         Ok(())
     }
 }
@@ -146,3 +361,164 @@ fn make_transform(transform_offset: (f32, f32), transform_rotation: f32) -> Mat4
     mat4::rotate_z(&mut tmp1, &tmp2, &transform_rotation);
     tmp1
 }
+
+#[cfg(test)]
+mod tests {
+    use web_sys::WebGlRenderingContext as GL;
+
+    use super::{draw_frame, WebGlBackend};
+    use crate::camera::Camera;
+    use crate::level::Level;
+    use crate::metadata::CameraFraming;
+    use crate::recording_gl::{GlCall, RecordingGl};
+    use crate::types::{Entity, Triangle};
+
+    struct Thing {
+        id: String,
+        triangles: Vec<Triangle>,
+        z_index: i32,
+    }
+
+    impl Entity for Thing {
+        fn id(&self) -> &String {
+            &self.id
+        }
+        fn triangles(&self) -> &[Triangle] {
+            &self.triangles
+        }
+        fn z_index(&self) -> i32 {
+            self.z_index
+        }
+    }
+
+    fn thing(id: &str, color: [f32; 4], z_index: i32) -> Thing {
+        Thing {
+            id: id.to_string(),
+            triangles: vec![Triangle::from_points(
+                (0.0, 0.0),
+                (1.0, 0.0),
+                (0.0, 1.0),
+                color,
+            )],
+            z_index,
+        }
+    }
+
+    const CLEAR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(&CameraFraming::default(), None);
+        camera.screen_size = (320.0, 180.0);
+        camera
+    }
+
+    fn backend() -> WebGlBackend<RecordingGl> {
+        let (shader, water_shader) = RecordingGl::shaders();
+        WebGlBackend::new(RecordingGl::new(320, 180), shader, water_shader).unwrap()
+    }
+
+    fn programs(calls: &[GlCall]) -> Vec<&'static str> {
+        let programs = calls.iter().filter_map(|call| match call {
+            GlCall::UseProgram(program) => Some(*program),
+            _ => None,
+        });
+        programs.collect()
+    }
+
+    fn uploads(calls: &[GlCall], usage: u32) -> usize {
+        let uploads = calls.iter().filter(|call| match call {
+            GlCall::BufferData { usage: u, .. } => *u == usage,
+            _ => false,
+        });
+        uploads.count()
+    }
+
+    fn draws(calls: &[GlCall]) -> usize {
+        let draws = calls
+            .iter()
+            .filter(|call| matches!(call, GlCall::DrawElements { .. }));
+        draws.count()
+    }
+
+    #[test]
+    fn static_meshes() {
+        let level = Level::load_from_svg_str(
+            r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+                <rect data-water="" x="0" y="50" width="10" height="10" fill="teal"/>
+                <rect data-water="" x="20" y="50" width="10" height="10" fill="teal"/>
+                <rect x="40" y="50" width="10" height="10" fill="gray"/>
+                <rect data-water="" x="60" y="50" width="10" height="10" fill="teal"/>
+            </svg>"#,
+        )
+        .unwrap();
+        let mut backend = backend();
+
+        draw_frame(&mut backend, CLEAR, &camera(), [&level as &dyn Entity], 0.0).unwrap();
+        let calls = backend.gl.take_calls();
+        // positions, colors, water levels and indices of the only layer
+        assert_eq!(uploads(&calls, GL::STATIC_DRAW), 4);
+        // the first two lakes are one batch
+        assert_eq!(programs(&calls), ["water", "default", "water"]);
+        assert_eq!(draws(&calls), 3);
+        assert!(calls.contains(&GlCall::Uniform("time", vec![0.0])));
+
+        draw_frame(&mut backend, CLEAR, &camera(), [&level as &dyn Entity], 1.0).unwrap();
+        let calls = backend.gl.take_calls();
+        assert_eq!(uploads(&calls, GL::STATIC_DRAW), 0);
+        assert_eq!(programs(&calls), ["water", "default", "water"]);
+        assert!(calls.contains(&GlCall::Uniform("time", vec![1.0])));
+    }
+
+    #[test]
+    fn dynamic_entities() {
+        let (a, b) = (thing("a", [1.0; 4], 0), thing("b", [0.5; 4], 0));
+        let mut backend = backend();
+
+        draw_frame(&mut backend, CLEAR, &camera(), [&a as &dyn Entity, &b], 0.0).unwrap();
+        let calls = backend.gl.take_calls();
+        assert_eq!(uploads(&calls, GL::DYNAMIC_DRAW), 4);
+        assert_eq!(programs(&calls), ["default"]);
+        assert_eq!(
+            calls.last(),
+            Some(&GlCall::DrawElements {
+                mode: GL::TRIANGLES,
+                count: 6,
+                offset: 0
+            })
+        );
+        assert_eq!(backend.stats.draw_calls, 1);
+    }
+
+    #[test]
+    fn z_index() {
+        let level = Level::load_from_svg_str(
+            r#"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">
+                <g inkscape:groupmode="layer" inkscape:label="Background z-index=-1">
+                    <rect x="0" y="0" width="10" height="10" fill="teal"/>
+                </g>
+                <rect data-water="" x="0" y="5" width="10" height="5" fill="teal"/>
+                <g inkscape:groupmode="layer" inkscape:label="Foreground z-index=50">
+                    <rect x="0" y="0" width="10" height="10" fill="teal"/>
+                </g>
+            </svg>"#,
+        )
+        .unwrap();
+        let (fish, player) = (thing("fish", [1.0; 4], 10), thing("player", [1.0; 4], 30));
+        let (a, b) = (thing("a", [1.0; 4], 10), thing("b", [1.0; 4], 10));
+        let mut backend = backend();
+
+        let entities = [&player as &dyn Entity, &b, &level, &fish, &a];
+        draw_frame(&mut backend, CLEAR, &camera(), entities, 0.0).unwrap();
+        let calls = backend.gl.take_calls();
+        let counts: Vec<i32> = calls
+            .iter()
+            .filter_map(|call| match call {
+                GlCall::DrawElements { count, .. } => Some(*count),
+                _ => None,
+            })
+            .collect();
+        // background, water, a, b and the fish in one batch, the player, foreground
+        assert_eq!(counts, [6, 6, 9, 3, 6]);
+        assert_eq!(programs(&calls), ["default", "water", "default"]);
+    }
+}
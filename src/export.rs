@@ -0,0 +1,640 @@
+//! Writes the running world back to SVG, for bug reports and for editing a level in Inkscape.
+//!
+//! The level comes out as the Inkscape layers it was loaded from, with a group for each element
+//! its triangles were made from, and its markers in a `Markers` layer, so that the document
+//! loads as the same level again. All other entities are drawn in a hidden `Entities` layer,
+//! where each one is a group moved to its position with a marker at its origin.
+
+use svg::node::element::path::Data;
+use svg::node::element::{Circle, Definitions, Group, LinearGradient, Path, Rectangle, Stop};
+use svg::Document;
+
+use crate::level::{Level, SourceElement};
+use crate::svg_path::Point;
+use crate::transform::Transform;
+use crate::triangulation::{signed_area, simple_rings};
+use crate::types::{Color, Entities, Entity, ShaderId, Triangle};
+
+const INKSCAPE_NAMESPACE: &str = "http://www.inkscape.org/namespaces/inkscape";
+// the radius of entity position markers, in world units
+const MARKER_RADIUS: f32 = 0.25;
+
+/// The parts of an SVG export, each entity adds itself with [`Entity::export`].
+#[derive(Default)]
+pub struct Snapshot {
+    attributes: Vec<(&'static str, String)>,
+    layers: Vec<Group>,
+    entities: Vec<Group>,
+    // the gradients of triangles with colors at their corners
+    gradients: Vec<LinearGradient>,
+    // the bounding box of everything added, as min and max corners
+    bounds: Option<(Point, Point)>,
+}
+
+impl Snapshot {
+    /// Sets an attribute of the root `<svg>` element.
+    pub fn set_attribute(&mut self, name: &'static str, value: String) {
+        self.attributes.push((name, value));
+    }
+
+    /// Adds a top level layer that contains `points`.
+    pub fn add_layer(&mut self, layer: Group, points: impl IntoIterator<Item = Point>) {
+        self.layers.push(layer);
+        self.include(points);
+    }
+
+    /// Adds the triangles of `entity` to the `Entities` layer, where it is in the world.
+    pub fn add_entity<E: Entity + ?Sized>(&mut self, entity: &E) {
+        let (x, y) = entity.position();
+        let rotation = entity.rotation().to_degrees();
+        let transform = Transform::translate(x, y).then(&Transform::rotate(rotation));
+
+        let mut group = Group::new().set("data-entity", entity.id().as_str()).set(
+            "transform",
+            format!("translate({x},{y}) rotate({rotation})"),
+        );
+        for path in self.triangle_paths(entity.triangles()) {
+            group = group.add(path);
+        }
+        let marker = Circle::new()
+            .set("r", MARKER_RADIUS)
+            .set("fill", "none")
+            .set("stroke", "#ff00ff")
+            .set("stroke-width", MARKER_RADIUS / 5.0);
+        self.entities.push(group.add(marker));
+
+        self.include(
+            corners(entity.triangles())
+                .chain([(0.0, 0.0)])
+                .map(|point| transform.apply(point)),
+        );
+    }
+
+    // the triangles as paths, one for each run of triangles with the same fill. Wireframes and
+    // slivers that draw nothing are left out.
+    fn triangle_paths(&mut self, triangles: &[Triangle]) -> Vec<Path> {
+        let mut runs: Vec<(Fill, Data)> = vec![];
+        for triangle in triangles.iter().filter(|triangle| !triangle.wireframe) {
+            let mut points: Vec<Point> = triangle.coords.chunks(3).map(|c| (c[0], c[1])).collect();
+            if simple_rings(&points).is_empty() {
+                continue;
+            }
+            let mut colors = triangle.vertex_colors();
+            // the same winding everywhere, so that overlaps are filled
+            if signed_area(&points) < 0.0 {
+                points.reverse();
+                colors.reverse();
+            }
+
+            let fill = match colors.iter().all(|color| *color == colors[0]) {
+                true => Fill::Color(triangle.color),
+                false => Fill::gradient(&points, colors),
+            };
+            match runs.last_mut() {
+                Some((last, data)) if *last == fill => *data = polygon(data.clone(), &points),
+                _ => runs.push((fill, polygon(Data::new(), &points))),
+            }
+        }
+        runs.into_iter()
+            .map(|(fill, data)| self.fill(Path::new().set("d", data), fill))
+            .collect()
+    }
+
+    fn fill(&mut self, path: Path, fill: Fill) -> Path {
+        let (from, to, [start, end]) = match fill {
+            Fill::Color(color) => return paint(path, color),
+            Fill::Gradient { from, to, colors } => (from, to, colors),
+        };
+        let id = format!("gradient-{}", self.gradients.len());
+        self.gradients.push(
+            LinearGradient::new()
+                .set("id", id.as_str())
+                .set("gradientUnits", "userSpaceOnUse")
+                .set("x1", from.0)
+                .set("y1", from.1)
+                .set("x2", to.0)
+                .set("y2", to.1)
+                .add(stop(0.0, start))
+                .add(stop(1.0, end)),
+        );
+        path.set("fill", format!("url(#{id})"))
+    }
+
+    fn include(&mut self, points: impl IntoIterator<Item = Point>) {
+        for (x, y) in points {
+            let (min, max) = self.bounds.get_or_insert(((x, y), (x, y)));
+            *min = (min.0.min(x), min.1.min(y));
+            *max = (max.0.max(x), max.1.max(y));
+        }
+    }
+
+    pub fn into_document(self) -> Document {
+        let mut document = Document::new().set("xmlns:inkscape", INKSCAPE_NAMESPACE);
+        if let Some(((x, y), (max_x, max_y))) = self.bounds {
+            document = document.set("viewBox", (x, y, max_x - x, max_y - y));
+        }
+        for (name, value) in self.attributes {
+            document = document.set(name, value);
+        }
+        if !self.gradients.is_empty() {
+            let mut definitions = Definitions::new();
+            for gradient in self.gradients {
+                definitions = definitions.add(gradient);
+            }
+            document = document.add(definitions);
+        }
+        for layer in self.layers {
+            document = document.add(layer);
+        }
+        if !self.entities.is_empty() {
+            // hidden, so that the document still loads as just the level
+            let mut layer = layer("Entities").set("style", "display:none");
+            for entity in self.entities {
+                layer = layer.add(entity);
+            }
+            document = document.add(layer);
+        }
+        document
+    }
+}
+
+#[derive(PartialEq)]
+enum Fill {
+    Color(Color),
+    /// A linear gradient in user space, from the first color at `from` to the second at `to`.
+    Gradient {
+        from: Point,
+        to: Point,
+        colors: [Color; 2],
+    },
+}
+
+impl Fill {
+    // the linear gradient that has `colors` at `points`. Colors that change in more than one
+    // direction, which gradients of the level do not make, are only matched at the two corners
+    // that differ the most.
+    fn gradient(points: &[Point], colors: [Color; 3]) -> Fill {
+        let distance =
+            |a: Color, b: Color| -> f32 { a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum() };
+        let (i, j) = [(0, 1), (1, 2), (2, 0)]
+            .into_iter()
+            .max_by(|(a, b), (c, d)| {
+                distance(colors[*a], colors[*b]).total_cmp(&distance(colors[*c], colors[*d]))
+            })
+            .unwrap();
+        let k = 3 - i - j;
+
+        // where the third corner is between the other two, and the gradient vector that has
+        // that position at each corner
+        let along: f32 = (0..4)
+            .map(|n| (colors[k][n] - colors[i][n]) * (colors[j][n] - colors[i][n]))
+            .sum::<f32>()
+            / distance(colors[i], colors[j]);
+        let (a, b, c) = (points[i], points[j], points[k]);
+        let (d1, d2) = ((b.0 - a.0, b.1 - a.1), (c.0 - a.0, c.1 - a.1));
+        let det = d1.0 * d2.1 - d1.1 * d2.0;
+        let gradient = ((d2.1 - along * d1.1) / det, (along * d1.0 - d2.0) / det);
+        let length = gradient.0 * gradient.0 + gradient.1 * gradient.1;
+
+        Fill::Gradient {
+            from: a,
+            to: (a.0 + gradient.0 / length, a.1 + gradient.1 / length),
+            colors: [colors[i], colors[j]],
+        }
+    }
+}
+
+/// The world as an SVG document, entities are exported in the order of their ids.
+pub fn world_to_svg(entities: &Entities) -> Document {
+    let mut entities: Vec<&mut dyn Entity> = entities.iter().collect();
+    entities.sort_by(|a, b| (a.z_index(), a.id()).cmp(&(b.z_index(), b.id())));
+
+    let mut snapshot = Snapshot::default();
+    for entity in entities {
+        entity.export(&mut snapshot);
+    }
+    snapshot.into_document()
+}
+
+/// Adds the render layers, markers and metadata of `level`.
+pub fn add_level(snapshot: &mut Snapshot, level: &Level) {
+    let metadata = level.metadata();
+    if let Some(name) = &metadata.name {
+        snapshot.set_attribute("data-name", name.clone());
+    }
+    if let Some(author) = &metadata.author {
+        snapshot.set_attribute("data-author", author.clone());
+    }
+    let (x, y) = metadata.gravity;
+    snapshot.set_attribute("data-gravity", format!("{x} {y}"));
+    snapshot.set_attribute("data-clear-color", hex(metadata.clear_color));
+    if let Some(color) = metadata.water_color {
+        snapshot.set_attribute("data-water-color", hex(color));
+    }
+    let camera = metadata.camera;
+    let (x, y) = camera.center;
+    snapshot.set_attribute("data-camera", format!("{x} {y} {}", camera.width));
+
+    let volumes = level.water_volumes();
+    let mut exported_volumes = vec![false; volumes.len()];
+    // layers of nothing but markers come back as the markers layer
+    let drawn_layers = level
+        .layers()
+        .iter()
+        .filter(|layer| layer.triangles.iter().any(|triangle| !triangle.wireframe));
+    for render_layer in drawn_layers {
+        let mut group = layer(render_layer.name.as_deref().unwrap_or_default())
+            .set("data-parallax", render_layer.parallax)
+            .set("data-z-index", render_layer.z_index);
+
+        let mut triangles = render_layer.triangles.as_slice();
+        for source in &render_layer.elements {
+            let (from_source, rest) = triangles.split_at(source.triangles);
+            triangles = rest;
+            if let Some(element) = source_group(snapshot, source, from_source, volumes) {
+                group = group.add(element);
+                if let Some(i) = source.water_volume {
+                    exported_volumes[i] = true;
+                }
+            }
+        }
+        // levels stored before the triangles knew where they came from
+        if !triangles.is_empty() {
+            for path in snapshot.triangle_paths(triangles) {
+                group = group.add(path);
+            }
+        }
+
+        snapshot.add_layer(group, corners(&render_layer.triangles));
+    }
+
+    let mut markers = layer("Markers");
+    let mut points: Vec<Point> = vec![];
+    if let Some((x, y, width, height)) = level.player_bounds() {
+        markers = markers.add(rectangle((x, y, width, height)).set("id", "player"));
+        points.extend([(x, y), (x + width, y + height)]);
+    }
+    for collider in level.colliders() {
+        let mut data = Data::new();
+        for triangle in &collider.triangles {
+            data = polygon(data, triangle);
+        }
+        for line in &collider.polylines {
+            data = polyline(data, line);
+        }
+        markers = markers.add(
+            with_id(Path::new(), &collider.id)
+                .set("data-marker", "hitbox")
+                .set("d", data),
+        );
+        points.extend(collider.triangles.iter().flatten());
+        points.extend(collider.polylines.iter().flatten());
+    }
+    // water volumes that are not drawn
+    for (volume, _) in volumes
+        .iter()
+        .zip(exported_volumes)
+        .filter(|(_, done)| !done)
+    {
+        markers = markers.add(
+            with_id(Path::new(), &volume.id)
+                .set("data-marker", "water")
+                .set("d", polygon(Data::new(), &volume.polygon)),
+        );
+        points.extend(&volume.polygon);
+    }
+    for spawn in level.fish_spawns() {
+        markers = markers.add(
+            with_id(Path::new(), &spawn.id)
+                .set("data-marker", "fish-spawn")
+                .set("data-race", spawn.race.name())
+                .set("data-density", spawn.density)
+                .set("d", polygon(Data::new(), &spawn.area)),
+        );
+        points.extend(&spawn.area);
+    }
+    for spawn in level.item_spawns() {
+        let (x, y) = spawn.position;
+        let size = MARKER_RADIUS * 2.0;
+        let mut rect = with_id(
+            rectangle((x - size / 2.0, y - size / 2.0, size, size)),
+            &spawn.id,
+        )
+        .set("data-marker", "item-spawn");
+        if let Some(item) = &spawn.item {
+            rect = rect.set("data-item", item.as_str());
+        }
+        markers = markers.add(rect);
+        points.push(spawn.position);
+    }
+    if let Some((x, y, width, height)) = level.camera_bounds() {
+        markers = markers.add(rectangle((x, y, width, height)).set("data-marker", "camera-bounds"));
+        points.extend([(x, y), (x + width, y + height)]);
+    }
+    for trigger in level.triggers() {
+        let mut path = with_id(Path::new(), &trigger.id)
+            .set("data-marker", "trigger")
+            .set("d", polygon(Data::new(), &trigger.area));
+        if let Some(event) = &trigger.event {
+            path = path.set("data-event", event.as_str());
+        }
+        markers = markers.add(path);
+        points.extend(&trigger.area);
+    }
+    for exit in level.exits() {
+        let mut path = with_id(Path::new(), &exit.id)
+            .set("data-marker", "exit")
+            .set("d", polygon(Data::new(), &exit.area));
+        if let Some(target) = &exit.target {
+            path = path.set("data-target", target.as_str());
+        }
+        markers = markers.add(path);
+        points.extend(&exit.area);
+    }
+    snapshot.add_layer(markers, points);
+}
+
+// the triangles of one element, or `None` if they are all markers, which are exported from
+// the level data instead
+fn source_group(
+    snapshot: &mut Snapshot,
+    source: &SourceElement,
+    triangles: &[Triangle],
+    volumes: &[crate::level::WaterVolume],
+) -> Option<Group> {
+    let label = source.id.as_deref().unwrap_or(&source.element);
+    let mut group = Group::new().set("inkscape:label", label);
+    let mut empty = true;
+
+    // water is drawn by its volume, its outline is drawn on top
+    let water = source.water_volume.and_then(|i| volumes.get(i));
+    let (water_triangles, rest): (Vec<Triangle>, Vec<Triangle>) = triangles
+        .iter()
+        .cloned()
+        .partition(|triangle| water.is_some() && triangle.shader_id != ShaderId::Default);
+    if let (Some(volume), Some(triangle)) = (water, water_triangles.first()) {
+        let path = with_id(Path::new(), &volume.id)
+            .set("data-water", "")
+            .set("d", polygon(Data::new(), &volume.polygon));
+        group = group.add(paint(path, triangle.color));
+        empty = false;
+    }
+    for path in snapshot.triangle_paths(&rest) {
+        group = group.add(path);
+        empty = false;
+    }
+
+    (!empty).then_some(group)
+}
+
+fn corners(triangles: &[Triangle]) -> impl Iterator<Item = Point> + '_ {
+    triangles
+        .iter()
+        .flat_map(|triangle| triangle.coords.chunks(3).map(|c| (c[0], c[1])))
+}
+
+fn layer(name: &str) -> Group {
+    Group::new()
+        .set("inkscape:groupmode", "layer")
+        .set("inkscape:label", name)
+}
+
+fn rectangle((x, y, width, height): (f32, f32, f32, f32)) -> Rectangle {
+    Rectangle::new()
+        .set("x", x)
+        .set("y", y)
+        .set("width", width)
+        .set("height", height)
+}
+
+fn with_id<T: svg::Node>(mut element: T, id: &Option<String>) -> T {
+    if let Some(id) = id {
+        element.assign("id", id.as_str());
+    }
+    element
+}
+
+fn paint(path: Path, color: Color) -> Path {
+    let path = path.set("fill", hex(color));
+    if color[3] < 1.0 {
+        path.set("fill-opacity", color[3])
+    } else {
+        path
+    }
+}
+
+fn stop(offset: f32, color: Color) -> Stop {
+    let stop = Stop::new()
+        .set("offset", offset)
+        .set("stop-color", hex(color));
+    if color[3] < 1.0 {
+        stop.set("stop-opacity", color[3])
+    } else {
+        stop
+    }
+}
+
+fn polygon(data: Data, points: &[Point]) -> Data {
+    polyline(data, points).close()
+}
+
+fn polyline(mut data: Data, points: &[Point]) -> Data {
+    for (i, point) in points.iter().enumerate() {
+        data = match i {
+            0 => data.move_to(*point),
+            _ => data.line_to(*point),
+        };
+    }
+    data
+}
+
+fn hex(color: Color) -> String {
+    let [r, g, b] = [color[0], color[1], color[2]].map(|c| (c.clamp(0.0, 1.0) * 255.0).round());
+    format!("#{:02x}{:02x}{:02x}", r as u8, g as u8, b as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{add_level, world_to_svg, Snapshot};
+    use crate::level::Level;
+    use crate::sick_physics::Physics;
+    use crate::triangulation::signed_area;
+    use crate::types::{Entities, Entity, EntityOps, Triangle};
+
+    const LEVEL: &str = r##"<svg viewBox="0 0 40 20" xmlns="http://www.w3.org/2000/svg"
+        xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"
+        data-name="Pond" data-gravity="0 5" data-camera="20 10 30" data-clear-color="#66b3e6">
+    <linearGradient id="dusk" x1="0" y1="0" x2="0" y2="1">
+        <stop offset="0" stop-color="#36c"/>
+        <stop offset="0.7" stop-color="#fc9"/>
+        <stop offset="1" stop-color="#fc9" stop-opacity="0.5"/>
+    </linearGradient>
+    <g inkscape:groupmode="layer" inkscape:label="Sky" data-parallax="0.5" data-z-index="-1">
+        <rect x="0" y="0" width="40" height="20" fill="url(#dusk)"/>
+    </g>
+    <g inkscape:groupmode="layer" inkscape:label="Ground">
+        <path id="hitbox-shore" d="M0,10 L10,10 L10,20 L0,20 Z" fill="green"/>
+        <path id="lake" data-water="" d="M10,12 L30,12 L30,20 L10,20 Z"
+            fill="blue" fill-opacity="0.5" stroke="navy" stroke-width="0.5"/>
+        <path id="bridge" data-solid="" d="M8,10 H32" fill="none" stroke="brown"/>
+        <rect id="player" x="4" y="8" width="1" height="2"/>
+        <rect inkscape:label="fish-spawn race=eel density=0.5" x="12" y="14" width="16" height="4"/>
+        <circle data-marker="item-spawn" data-item="boot" cx="20" cy="19" r="1"/>
+        <rect data-marker="camera-bounds" x="0" y="0" width="40" height="20"/>
+        <rect id="exit-east" data-target="cave" x="38" y="0" width="2" height="10"/>
+    </g>
+</svg>"##;
+
+    struct Bobber {
+        id: String,
+        triangles: Vec<Triangle>,
+    }
+
+    impl Entity for Bobber {
+        fn id(&self) -> &String {
+            &self.id
+        }
+        fn triangles(&self) -> &[Triangle] {
+            &self.triangles
+        }
+        fn position(&self) -> (f32, f32) {
+            (15.0, 12.0)
+        }
+        fn rotation(&self) -> f32 {
+            std::f32::consts::FRAC_PI_2
+        }
+    }
+
+    fn export_level(level: &Level) -> String {
+        let mut snapshot = Snapshot::default();
+        add_level(&mut snapshot, level);
+        snapshot.into_document().to_string()
+    }
+
+    #[test]
+    fn world() {
+        let mut entity_ops = EntityOps::new();
+        entity_ops.insert(Level::load_from_svg_str(LEVEL).unwrap());
+        entity_ops.insert(Bobber {
+            id: "bobber".to_string(),
+            triangles: vec![Triangle::from_points(
+                (0.0, 0.0),
+                (1.0, 0.0),
+                (0.0, 1.0),
+                [1.0, 0.0, 0.0, 1.0],
+            )],
+        });
+        let mut entities = Entities::new();
+        let physics = Rc::new(RefCell::new(Physics::new((0.0, 5.0))));
+        entities.apply_ops(&mut entity_ops, &mut physics.borrow_mut());
+
+        insta::assert_snapshot!(world_to_svg(&entities).to_string());
+    }
+
+    #[test]
+    fn round_trip() {
+        let level = Level::load_from_svg_str(LEVEL).unwrap();
+        let exported = Level::load_from_svg_str(&export_level(&level)).unwrap();
+
+        assert_eq!(exported.warnings(), level.warnings());
+        assert_eq!(exported.metadata(), level.metadata());
+        assert_eq!(exported.player_bounds(), level.player_bounds());
+        assert_eq!(exported.water_volumes(), level.water_volumes());
+        assert_eq!(exported.fish_spawns(), level.fish_spawns());
+        assert_eq!(exported.item_spawns(), level.item_spawns());
+        assert_eq!(exported.camera_bounds(), level.camera_bounds());
+        assert_eq!(exported.triggers(), level.triggers());
+        assert_eq!(exported.exits(), level.exits());
+
+        let colliders = |level: &Level| {
+            level
+                .colliders()
+                .iter()
+                .map(|collider| {
+                    let area: f32 = collider.triangles.iter().map(|t| signed_area(t)).sum();
+                    (collider.id.clone(), area / 2.0, collider.polylines.clone())
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(colliders(&exported), colliders(&level));
+
+        // the same drawing, plus a layer for the markers
+        let layers = |level: &Level| {
+            level
+                .layers()
+                .iter()
+                .map(|layer| {
+                    let area: f32 = layer
+                        .triangles
+                        .iter()
+                        .filter(|t| !t.wireframe)
+                        .map(|t| {
+                            let c = t.coords;
+                            signed_area(&[(c[0], c[1]), (c[3], c[4]), (c[6], c[7])]).abs() / 2.0
+                        })
+                        .sum();
+                    // the corners of strokes overlap, they come back as one shape
+                    (
+                        layer.name.clone(),
+                        layer.parallax,
+                        layer.z_index,
+                        area.round(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut expected = layers(&level);
+        expected.push((Some("Markers".to_string()), 1.0, 0, 0.0));
+        assert_eq!(layers(&exported), expected);
+
+        // the color of the topmost triangle in each layer, on a grid over the level
+        let colors = |level: &Level| {
+            let mut colors = vec![];
+            for layer in level
+                .layers()
+                .iter()
+                .filter(|layer| layer.name.as_deref() != Some("Markers"))
+            {
+                for (x, y) in (0..80).flat_map(|x| {
+                    (0..40).map(move |y| (x as f32 / 2.0 + 0.2, y as f32 / 2.0 + 0.3))
+                }) {
+                    let color = layer
+                        .triangles
+                        .iter()
+                        .rev()
+                        .filter(|t| !t.wireframe)
+                        .find_map(|t| {
+                            let c = t.coords;
+                            let corners = [(c[0], c[1]), (c[3], c[4]), (c[6], c[7])];
+                            let area = signed_area(&corners);
+                            let weights = [1, 2, 0].map(|i| {
+                                let (a, b) = (corners[i], corners[(i + 1) % 3]);
+                                signed_area(&[a, b, (x, y)]) / area
+                            });
+                            weights.iter().all(|w| *w >= 0.0).then(|| {
+                                let vertex_colors = t.vertex_colors();
+                                [0, 1, 2, 3].map(|n| {
+                                    (0..3)
+                                        .map(|i| weights[i] * vertex_colors[i][n])
+                                        .sum::<f32>()
+                                })
+                            })
+                        });
+                    colors.push(color);
+                }
+            }
+            colors
+        };
+        for (exported, original) in colors(&exported).into_iter().zip(colors(&level)) {
+            match (exported, original) {
+                (Some(a), Some(b)) => assert!(
+                    a.iter().zip(b).all(|(a, b)| (a - b).abs() < 0.01),
+                    "{a:?} != {b:?}"
+                ),
+                (a, b) => assert_eq!(a.is_some(), b.is_some()),
+            }
+        }
+    }
+}
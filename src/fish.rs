@@ -1,7 +1,8 @@
 use js_sys::Math::random;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    types::{Entity, GameState, Triangle},
+    types::{Entity, GameState, Triangle, Z_FISH},
     utils::next_id,
 };
 
@@ -12,18 +13,39 @@ pub struct Fish {
     position: (f32, f32),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FishRace {
     Goldfish,
     Eel,
     Whale,
 }
 
+impl FishRace {
+    pub fn parse(value: &str) -> Option<FishRace> {
+        match value.trim() {
+            "goldfish" => Some(FishRace::Goldfish),
+            "eel" => Some(FishRace::Eel),
+            "whale" => Some(FishRace::Whale),
+            _ => None,
+        }
+    }
+
+    /// The name [`FishRace::parse`] reads.
+    pub fn name(self) -> &'static str {
+        match self {
+            FishRace::Goldfish => "goldfish",
+            FishRace::Eel => "eel",
+            FishRace::Whale => "whale",
+        }
+    }
+}
+
 impl Fish {
-    pub fn new(race: FishRace) -> Self {
+    pub fn new(race: FishRace, position: (f32, f32)) -> Self {
         Fish {
             id: next_id(),
             race,
-            position: ((random() * 20.0) as f32, (random() * 20.0) as f32),
+            position,
             triangles: vec![Triangle {
                 coords: [
                     0.0, 0.0, 1.0, //
@@ -42,7 +64,7 @@ impl Entity for Fish {
         &self.id
     }
 
-    fn triangles(&self) -> &Vec<crate::types::Triangle> {
+    fn triangles(&self) -> &[crate::types::Triangle] {
         &self.triangles
     }
 
@@ -50,6 +72,10 @@ impl Entity for Fish {
         self.position
     }
 
+    fn z_index(&self) -> i32 {
+        Z_FISH
+    }
+
     fn update(&mut self, _time_passed: f32, _game_state: &mut GameState) {
         self.position.0 += (random() as f32 - 0.5) * 0.1;
         self.position.1 += (random() as f32 - 0.5) * 0.1;
@@ -5,12 +5,14 @@ use nphysics2d::{
 
 use crate::{
     sick_physics::Physics,
-    types::{cyan, green, red, Entity, GameState, ShaderId, Triangle},
+    types::{cyan, green, red, Entity, GameState, ShaderId, Triangle, Z_HOOK},
     utils::next_id,
 };
 
 const HALF_WIDTH: f32 = 0.1;
 const HALF_HEIGHT: f32 = 0.1;
+// how hard the hook is thrown
+const THROW_IMPULSE: f32 = 0.1;
 
 pub struct FishingRod {
     id: String,
@@ -18,6 +20,8 @@ pub struct FishingRod {
     rotation: f32,
     triangles: Vec<Triangle>,
     once: bool,
+    // where the hook is thrown towards, in world coordinates
+    target: Option<(f32, f32)>,
 
     body_handle: Option<DefaultBodyHandle>,
     collider_handle: Option<DefaultColliderHandle>,
@@ -28,7 +32,7 @@ impl Entity for FishingRod {
         &self.id
     }
 
-    fn triangles(&self) -> &Vec<Triangle> {
+    fn triangles(&self) -> &[Triangle] {
         &self.triangles
     }
 
@@ -37,9 +41,10 @@ impl Entity for FishingRod {
         if self.once {
             self.once = false;
             if let Some(body) = self.body_handle.and_then(|h| gs.physics.bodies.get_mut(h)) {
+                let (x, y) = throw_impulse(self.position, self.target);
                 body.apply_force(
                     0,
-                    &Force2::from_slice(&[0.1, 0.0, 0.0]),
+                    &Force2::from_slice(&[x, y, 0.0]),
                     nphysics2d::math::ForceType::Impulse,
                     true,
                 );
@@ -61,6 +66,10 @@ impl Entity for FishingRod {
         self.position
     }
 
+    fn z_index(&self) -> i32 {
+        Z_HOOK
+    }
+
     fn rotation(&self) -> f32 {
         self.rotation
     }
@@ -85,12 +94,14 @@ impl Entity for FishingRod {
 }
 
 impl FishingRod {
-    pub fn new(position: (f32, f32)) -> Self {
+    /// A hook thrown from `position` towards `target`, or to the right without a target.
+    pub fn new(position: (f32, f32), target: Option<(f32, f32)>) -> Self {
         Self {
             id: next_id() + &"fishing_hook",
             position,
             rotation: 0.0,
             once: true,
+            target,
             triangles: vec![
                 Triangle::from_points(
                     (-HALF_WIDTH, -HALF_HEIGHT),
@@ -110,3 +121,92 @@ impl FishingRod {
         }
     }
 }
+
+// towards `target`, or to the right without one or if it is where the throw starts
+fn throw_impulse(from: (f32, f32), target: Option<(f32, f32)>) -> (f32, f32) {
+    let (x, y) = match target {
+        Some(target) => (target.0 - from.0, target.1 - from.1),
+        None => (1.0, 0.0),
+    };
+    let length = x.hypot(y);
+    if length == 0.0 {
+        return (THROW_IMPULSE, 0.0);
+    }
+    (x / length * THROW_IMPULSE, y / length * THROW_IMPULSE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::throw_impulse;
+    use crate::{
+        camera::Camera,
+        metadata::CameraFraming,
+        player::Player,
+        sick_physics::Physics,
+        types::{Entities, Entity, EntityOps, GameState},
+        user_input::UserInput,
+    };
+
+    #[test]
+    fn thrown_towards_the_mouse() {
+        let framing = CameraFraming {
+            center: (0.0, 0.0),
+            width: 20.0,
+        };
+        let mut camera = Camera::new(&framing, None);
+        camera.screen_size = (200.0, 100.0);
+        camera.center = (10.0, 5.0);
+        // the bottom right corner of the screen
+        let input = UserInput {
+            throw_rod: true,
+            mouse: Some((200.0, 100.0)),
+            ..Default::default()
+        };
+
+        let mut physics = Physics::new((0.0, 0.0));
+        let mut entities = Entities::new();
+        let mut entity_ops = EntityOps::new();
+        let mut player = Player::new((5.0, 5.0));
+        // what `Game::tick` hands to the entities
+        let mut game_state = GameState {
+            input: &input,
+            physics: &mut physics,
+            entities: &entities,
+            entity_ops: &mut entity_ops,
+            camera_center: camera.center,
+            mouse: input.mouse.map(|mouse| camera.screen_to_world(mouse)),
+        };
+        assert_eq!(game_state.mouse, Some((20.0, 10.0)));
+        player.update(0.0, &mut game_state);
+        entities.apply_ops(&mut entity_ops, &mut physics);
+
+        // stepped before the update, like `Game::tick` does
+        for _ in 0..10 {
+            physics.step();
+            let mut game_state = GameState {
+                input: &UserInput::default(),
+                physics: &mut physics,
+                entities: &entities,
+                entity_ops: &mut entity_ops,
+                camera_center: camera.center,
+                mouse: None,
+            };
+            for entity in entities.iter() {
+                entity.update(1.0 / 60.0, &mut game_state);
+            }
+        }
+
+        let hook = entities.iter().next().unwrap();
+        let (x, y) = hook.position();
+        // on the way from the player to the mouse
+        assert!(x > 5.0 && y > 5.0, "{:?}", (x, y));
+        assert!(((x - 5.0) - (y - 5.0) * 3.0).abs() < 1e-3, "{:?}", (x, y));
+    }
+
+    #[test]
+    fn impulse() {
+        assert_eq!(throw_impulse((1.0, 1.0), Some((1.0, -3.0))), (0.0, -0.1));
+        assert_eq!(throw_impulse((1.0, 1.0), None), (0.1, 0.0));
+        assert_eq!(throw_impulse((1.0, 1.0), Some((1.0, 1.0))), (0.1, 0.0));
+    }
+}
@@ -3,9 +3,12 @@ use std::{cell::RefCell, rc::Rc};
 use js_sys::Math::random;
 
 use crate::{
-    fish::{Fish, FishRace},
-    level::Level,
+    camera::Camera,
+    export,
+    fish::Fish,
+    level::{Level, LevelError},
     log,
+    metadata::LevelMetadata,
     player::Player,
     sick_physics::Physics,
     types::{Entities, Entity, EntityOps, GameState, ShaderId, Triangle},
@@ -18,6 +21,8 @@ pub struct Game {
     entity_ops: EntityOps,
     input_handler: InputHandler,
     physics: Rc<RefCell<Physics>>,
+    metadata: LevelMetadata,
+    camera: Camera,
 
     last_fps_print: f64,
     frames_drawn: usize,
@@ -33,7 +38,7 @@ impl Entity for ShitItem {
     fn id(&self) -> &String {
         &self.id
     }
-    fn triangles(&self) -> &Vec<crate::types::Triangle> {
+    fn triangles(&self) -> &[crate::types::Triangle] {
         &self.triangles
     }
     fn update(&mut self, _time_passed: f32, _game_state: &mut GameState) {
@@ -64,45 +69,67 @@ impl ShitItem {
 }
 
 impl Game {
-    pub fn new() -> Game {
+    pub fn new() -> Result<Game, LevelError> {
         let mut entity_ops = EntityOps::new();
 
-        let level = Level::load_from_svg_str(include_str!("../assets/map.svg"));
-        let ground = level.ground();
-        let player = Player::new(level.player_pos());
+        // converted from map.svg by build.sh
+        let level = Level::load(include_str!("../assets/map.ron"))?;
+        let metadata = level.metadata().clone();
+        log!(
+            "loading {} by {}",
+            metadata.name.as_deref().unwrap_or("unnamed level"),
+            metadata.author.as_deref().unwrap_or("unknown author")
+        );
+        let player = Player::new(level.player_pos().unwrap_or_default());
+        let camera = Camera::new(&metadata.camera, level.camera_bounds());
+
+        let mut physics = Physics::new(metadata.gravity);
+        for collider in level.colliders() {
+            physics.insert_ground(collider);
+        }
+        for volume in level.water_volumes() {
+            physics.insert_water(volume);
+        }
+
+        for spawn in level.fish_spawns() {
+            for _ in 0..spawn.fish_count() {
+                let position = spawn.random_point(|| random() as f32);
+                entity_ops.insert(Fish::new(spawn.race, position));
+            }
+        }
+
         entity_ops.insert(level);
         entity_ops.insert(player);
 
-        let mut physics = Physics::new();
-
-        physics.insert_ground(
-            ground.0 + ground.2 / 2.0,
-            ground.1 + ground.3 / 2.0,
-            ground.2 / 2.0,
-            ground.3 / 2.0,
-        );
         let physics = Rc::new(RefCell::new(physics));
 
-        let fish = Fish::new(FishRace::Goldfish);
-        entity_ops.insert(fish);
-
         let mut input_handler = user_input::InputHandler::new();
         input_handler.attach();
 
-        Game {
+        Ok(Game {
             render_buffer: vec![],
             physics,
+            metadata,
+            camera,
             entities: Entities::new(),
             entity_ops,
             input_handler,
             frames_drawn: 0,
             last_fps_print: 0.0,
-        }
+        })
     }
 
     pub fn tick(&mut self, time_passed: f32) {
         self.render_buffer.clear();
 
+        let target = self.camera.target.as_ref().and_then(|target| {
+            let mut entities = self.entities.iter();
+            entities
+                .find(|entity| entity.id() == target)
+                .map(|entity| entity.position())
+        });
+        self.camera.update(target, time_passed);
+
         {
             let input = self.input_handler.current_state();
             let mut physics = self.physics.as_ref().borrow_mut();
@@ -115,6 +142,8 @@ impl Game {
                 input: &input,
                 entities: &self.entities,
                 entity_ops: &mut self.entity_ops,
+                camera_center: self.camera.center,
+                mouse: input.mouse.map(|mouse| self.camera.screen_to_world(mouse)),
             };
 
             for entity in self.entities.iter() {
@@ -122,6 +151,10 @@ impl Game {
             }
 
             self.entities.apply_ops(&mut self.entity_ops, &mut *physics);
+
+            if input.export_world {
+                log!("{}", self.export_svg());
+            }
         }
 
         self.input_handler.after_update();
@@ -132,6 +165,24 @@ impl Game {
         &self.entities
     }
 
+    /// The world as it is right now, as an SVG document that loads as the same level. Pressing
+    /// `p` logs it to the console.
+    pub fn export_svg(&self) -> svg::Document {
+        export::world_to_svg(&self.entities)
+    }
+
+    pub fn metadata(&self) -> &LevelMetadata {
+        &self.metadata
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
     fn handle_fps(&mut self) {
         self.frames_drawn += 1;
 
@@ -168,6 +219,7 @@ pub fn random_shit_items(n: usize) -> Vec<ShitItem> {
                     color: [0.9; 4],
                     shader_id: ShaderId::Default,
                     wireframe: false,
+                    vertex_colors: None,
                 }],
                 moving,
             }
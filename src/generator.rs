@@ -0,0 +1,501 @@
+//! Generates lake levels from a seed, so that there is always a fresh fishing spot.
+//!
+//! The level is written as SVG and loaded like a drawn one, [`generate_svg`] can be opened in
+//! Inkscape as well. The terrain is a noise silhouette with lake basins cut into it, trees and
+//! rocks are copies of the `tree` and `rocks` shapes in `map.svg`.
+
+use svg::node::element::path::Data;
+use svg::node::element::tag::Type;
+use svg::node::element::{Element, Group, Path, Rectangle};
+use svg::node::Node;
+use svg::parser::Event;
+use svg::Document;
+
+use crate::level::{Level, LevelError};
+use crate::svg_path::{flatten_path, Point, DEFAULT_CURVE_TOLERANCE};
+
+// the level the decorations are copied from
+const PROTOTYPES: &str = include_str!("../assets/map.svg");
+// the distance between the points of the terrain outline
+const STEP: f32 = 1.0;
+// the height the hills go up and down around, and how far the ground goes below the deepest
+// point of the terrain
+const GROUND: f32 = 20.0;
+const BEDROCK: f32 = 12.0;
+// the sky above the highest hill
+const SKY: f32 = 20.0;
+// the flat strip on the left where the player starts
+const SPAWN: (f32, f32) = (2.0, 14.0);
+// the shallowest lake, below its lower bank
+const MIN_DEPTH: f32 = 3.0;
+
+#[derive(Clone, Debug)]
+pub struct LakeSettings {
+    /// The width of the level in world units.
+    pub width: f32,
+    /// The number of lakes, at least one.
+    pub lakes: usize,
+    /// How far the hills go up and down.
+    pub hilliness: f32,
+    /// Trees per world unit of dry land.
+    pub tree_density: f32,
+}
+
+impl Default for LakeSettings {
+    fn default() -> Self {
+        LakeSettings {
+            width: 160.0,
+            lakes: 2,
+            hilliness: 6.0,
+            tree_density: 0.06,
+        }
+    }
+}
+
+// splitmix64, see https://prng.di.unimi.it/splitmix64.c
+struct Random(u64);
+
+impl Random {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // in `0..1`
+    fn next(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+}
+
+// smooth value noise in `-1..1` for `0..=width`, big hills with smaller bumps on them
+fn noise(random: &mut Random, width: f32) -> impl Fn(f32) -> f32 {
+    let octaves: Vec<(f32, f32, Vec<f32>)> = [(32.0, 0.6), (12.0, 0.3), (5.0, 0.1)]
+        .into_iter()
+        .map(|(scale, amplitude)| {
+            let count = (width / scale).ceil() as usize + 2;
+            let values = (0..count).map(|_| random.range(-1.0, 1.0)).collect();
+            (scale, amplitude, values)
+        })
+        .collect();
+
+    move |x| {
+        octaves
+            .iter()
+            .map(|(scale, amplitude, values)| {
+                let position = x.max(0.0) / scale;
+                let i = position.floor() as usize;
+                let t = position - i as f32;
+                let t = t * t * (3.0 - 2.0 * t);
+                amplitude * (values[i] + (values[i + 1] - values[i]) * t)
+            })
+            .sum()
+    }
+}
+
+// two decimals are plenty and keep the SVG readable
+fn round(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
+
+// the height of the ground at each multiple of `STEP`, y goes down
+struct Terrain {
+    heights: Vec<f32>,
+}
+
+impl Terrain {
+    fn x(&self, i: usize) -> f32 {
+        i as f32 * STEP
+    }
+
+    fn height_at(&self, x: f32) -> f32 {
+        let position = (x / STEP).clamp(0.0, (self.heights.len() - 1) as f32);
+        let i = (position.floor() as usize).min(self.heights.len() - 2);
+        let t = position - i as f32;
+        self.heights[i] + (self.heights[i + 1] - self.heights[i]) * t
+    }
+
+    // the highest point of the ground between `from` and `to`
+    fn top_between(&self, from: f32, to: f32) -> f32 {
+        let inner = (0..self.heights.len())
+            .filter(|i| (from..=to).contains(&self.x(*i)))
+            .map(|i| self.heights[i]);
+        inner
+            .chain([self.height_at(from), self.height_at(to)])
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+struct Lake {
+    // the banks, the basin is between them
+    left: f32,
+    right: f32,
+    surface: f32,
+    // the outline of the water
+    polygon: Vec<Point>,
+}
+
+impl Lake {
+    // carves a basin between the outline points `left` and `right` and fills it with water
+    fn carve(terrain: &mut Terrain, left: usize, right: usize, random: &mut Random) -> Lake {
+        let (left_bank, right_bank) = (terrain.heights[left], terrain.heights[right]);
+        // deep enough below the lower bank, wherever the higher one is
+        let depth = (left_bank - right_bank).abs() + random.range(MIN_DEPTH, MIN_DEPTH * 2.0);
+        for i in left..=right {
+            let t = (i - left) as f32 / (right - left) as f32;
+            let height = left_bank + (right_bank - left_bank) * t + depth * 4.0 * t * (1.0 - t);
+            terrain.heights[i] = round(height);
+        }
+
+        let surface = round(left_bank.max(right_bank) + 0.5);
+        let crossing = |i: usize| {
+            let (a, b) = (terrain.heights[i], terrain.heights[i + 1]);
+            let t = (surface - a) / (b - a);
+            (round(terrain.x(i) + STEP * t), surface)
+        };
+        let mut polygon = vec![];
+        for i in left..right {
+            let (a, b) = (terrain.heights[i], terrain.heights[i + 1]);
+            if a > surface {
+                polygon.push((terrain.x(i), a));
+            }
+            if (a > surface) != (b > surface) {
+                polygon.push(crossing(i));
+            }
+        }
+
+        Lake {
+            left: terrain.x(left),
+            right: terrain.x(right),
+            surface,
+            polygon,
+        }
+    }
+
+    // the part of the water that is deep enough to swim in, as `(x, y, width, height)`
+    fn swimming_area(&self) -> Option<(f32, f32, f32, f32)> {
+        let (min_x, max_x, max_y) = self.polygon.iter().fold(
+            (f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            |(min_x, max_x, max_y), (x, y)| (min_x.min(*x), max_x.max(*x), max_y.max(*y)),
+        );
+        let width = max_x - min_x - 4.0;
+        let height = (max_y - self.surface - 1.5).min(2.0);
+        (width > 0.0 && height > 0.0).then_some((
+            round(min_x + 2.0),
+            round(self.surface + 0.5),
+            round(width),
+            round(height),
+        ))
+    }
+}
+
+// an element of `map.svg` to make copies of
+struct Prototype {
+    element: Element,
+    // the bounding box of its paths, as min and max corner
+    bounds: (Point, Point),
+}
+
+impl Prototype {
+    // the element with `id`, without the ids and the Inkscape editor state of its descendants
+    fn load(id: &str) -> Option<Prototype> {
+        let mut stack: Vec<Element> = vec![];
+        let mut points: Vec<Point> = vec![];
+
+        for event in svg::read(PROTOTYPES).ok()? {
+            let (name, kind, attributes) = match event {
+                Event::Tag(name, kind, attributes) => (name, kind, attributes),
+                _ => continue,
+            };
+            let is_prototype = attributes.get("id").map(|value| &**value) == Some(id);
+            if stack.is_empty() && (kind == Type::End || !is_prototype) {
+                continue;
+            }
+
+            let element = match kind {
+                Type::Start | Type::Empty => {
+                    let mut element = Element::new(name);
+                    for (attribute, value) in &attributes {
+                        if attribute != "id" && !attribute.starts_with("sodipodi:") {
+                            element.assign(attribute.as_str(), value.clone());
+                        }
+                    }
+                    if let Some(d) = attributes.get("d") {
+                        let data = Data::parse(d).ok()?;
+                        let subpaths = flatten_path(&data, DEFAULT_CURVE_TOLERANCE);
+                        points.extend(subpaths.into_iter().flat_map(|subpath| subpath.points));
+                    }
+                    if kind == Type::Start {
+                        stack.push(element);
+                        continue;
+                    }
+                    element
+                }
+                Type::End => stack.pop()?,
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.append(element),
+                None => {
+                    let bounds = points.iter().fold(
+                        (
+                            (f32::INFINITY, f32::INFINITY),
+                            (f32::NEG_INFINITY, f32::NEG_INFINITY),
+                        ),
+                        |(min, max), (x, y)| {
+                            (
+                                (min.0.min(*x), min.1.min(*y)),
+                                (max.0.max(*x), max.1.max(*y)),
+                            )
+                        },
+                    );
+                    return (!points.is_empty()).then_some(Prototype { element, bounds });
+                }
+            }
+        }
+        None
+    }
+
+    fn width(&self) -> f32 {
+        self.bounds.1 .0 - self.bounds.0 .0
+    }
+
+    // a copy scaled by `scale`, with the point `anchor` of the bounds moved to `to`
+    fn place(&self, label: &str, anchor: (f32, f32), to: Point, scale: f32) -> Group {
+        let (min, max) = self.bounds;
+        let anchor = (
+            min.0 + (max.0 - min.0) * anchor.0,
+            min.1 + (max.1 - min.1) * anchor.1,
+        );
+        let x = round(to.0 - anchor.0 * scale);
+        let y = round(to.1 - anchor.1 * scale);
+        Group::new()
+            .set("inkscape:label", label)
+            .set(
+                "transform",
+                format!("translate({x},{y}) scale({})", round(scale)),
+            )
+            .add(self.element.clone())
+    }
+}
+
+/// The level for `seed`, see [`generate_svg`].
+pub fn generate(seed: u64, settings: &LakeSettings) -> Result<Level, LevelError> {
+    Level::load_from_svg_str(&generate_svg(seed, settings).to_string())
+}
+
+/// A lake level as SVG, the same `seed` and `settings` always give the same level.
+pub fn generate_svg(seed: u64, settings: &LakeSettings) -> Document {
+    let mut random = Random(seed);
+    let width = settings.width.max(SPAWN.1 + 20.0);
+    let noise = noise(&mut random, width);
+
+    let points = (width / STEP).round() as usize + 1;
+    let mut terrain = Terrain {
+        heights: (0..points)
+            .map(|i| {
+                // flat where the player starts
+                let x = (i as f32 * STEP).max(SPAWN.1);
+                round(GROUND + settings.hilliness * noise(x))
+            })
+            .collect(),
+    };
+
+    // every lake gets a part of the level right of the spawn
+    let (start, end) = (SPAWN.1 + 4.0, width - 4.0);
+    let lakes = settings.lakes.max(1);
+    let part = (end - start) / lakes as f32;
+    let lakes: Vec<Lake> = (0..lakes)
+        .map(|n| {
+            let center = start + part * (n as f32 + 0.5 + random.range(-0.1, 0.1));
+            let half_width = (part * random.range(0.25, 0.4)).max(3.0 * STEP);
+            let left = ((center - half_width) / STEP).floor() as usize;
+            let right = ((center + half_width) / STEP).ceil() as usize;
+            Lake::carve(&mut terrain, left, right.min(points - 1), &mut random)
+        })
+        .collect();
+
+    let top = terrain
+        .heights
+        .iter()
+        .copied()
+        .fold(f32::INFINITY, f32::min)
+        - SKY;
+    let bottom = terrain.heights.iter().copied().fold(0.0, f32::max) + BEDROCK;
+    let bounds = (0.0, round(top), width, round(bottom - top));
+
+    let player_x = round(random.range(SPAWN.0, SPAWN.1 - 1.0));
+    let player_y = round(terrain.top_between(player_x, player_x + 1.0) - 2.1);
+
+    let mut document = Document::new()
+        .set(
+            "xmlns:inkscape",
+            "http://www.inkscape.org/namespaces/inkscape",
+        )
+        .set("viewBox", bounds)
+        .set("data-name", format!("Lake {seed}"))
+        .set(
+            "data-camera",
+            format!("{} {} 40", player_x + 0.5, player_y + 1.0),
+        );
+
+    // half buried in the lake beds, so they go behind the terrain
+    if let Some(rocks) = Prototype::load("rocks") {
+        for lake in &lakes {
+            let center = (lake.left + lake.right) / 2.0;
+            let scale = ((lake.right - lake.left) * 0.4 / rocks.width()).min(1.0);
+            let bed = (center, terrain.height_at(center));
+            document = document.add(rocks.place("rocks", (0.5, 0.5), bed, scale));
+        }
+    }
+
+    let mut outline = Data::new().move_to((0.0, terrain.heights[0]));
+    for (i, height) in terrain.heights.iter().enumerate().skip(1) {
+        outline = outline.line_to((terrain.x(i), *height));
+    }
+    let outline = outline
+        .line_to((width, round(bottom)))
+        .line_to((0.0, round(bottom)))
+        .close();
+    document = document.add(
+        Path::new()
+            .set("id", "shore")
+            .set("data-solid", "")
+            .set("d", outline)
+            .set("fill", "#a0693c")
+            .set("stroke", "#000000")
+            .set("stroke-width", 0.1),
+    );
+
+    for (n, lake) in lakes.iter().enumerate() {
+        let mut outline = Data::new();
+        for (i, point) in lake.polygon.iter().enumerate() {
+            outline = match i {
+                0 => outline.move_to(*point),
+                _ => outline.line_to(*point),
+            };
+        }
+        document = document.add(
+            Path::new()
+                .set("id", format!("lake-{}", n + 1))
+                .set("data-water", "")
+                .set("d", outline.close())
+                .set("fill", "#008fff")
+                .set("fill-opacity", 0.49),
+        );
+
+        if let Some((x, y, width, height)) = lake.swimming_area() {
+            let race = if random.next() < 0.7 {
+                "goldfish"
+            } else {
+                "eel"
+            };
+            document = document.add(
+                Rectangle::new()
+                    .set("id", format!("fish-spawn-{}", n + 1))
+                    .set("data-race", race)
+                    .set("x", x)
+                    .set("y", y)
+                    .set("width", width)
+                    .set("height", height),
+            );
+        }
+    }
+
+    // on dry land, apart from each other and from the player
+    if let Some(tree) = Prototype::load("tree") {
+        let wet: f32 = lakes.iter().map(|lake| lake.right - lake.left).sum();
+        let count = ((width - wet) * settings.tree_density).round() as usize;
+        let mut trees: Vec<f32> = vec![];
+        for _ in 0..count * 20 {
+            if trees.len() == count {
+                break;
+            }
+            let x = random.range(1.0, width - 1.0);
+            let blocked = lakes
+                .iter()
+                .any(|lake| x > lake.left - 2.0 && x < lake.right + 2.0)
+                || (x - player_x - 0.5).abs() < 3.0
+                || trees.iter().any(|other| (x - other).abs() < 3.0);
+            if !blocked {
+                trees.push(x);
+            }
+        }
+        trees.sort_by(f32::total_cmp);
+
+        for x in trees {
+            let scale = random.range(0.8, 1.3);
+            // sunk into the ground a bit, so that they do not float on slopes
+            let root = (x, terrain.height_at(x) + 0.3);
+            document = document.add(tree.place("tree", (0.5, 1.0), root, scale));
+        }
+    }
+
+    document
+        .add(
+            Rectangle::new()
+                .set("id", "player")
+                .set("x", player_x)
+                .set("y", player_y)
+                .set("width", 1)
+                .set("height", 2),
+        )
+        .add(
+            Rectangle::new()
+                .set("data-marker", "camera-bounds")
+                .set("x", bounds.0)
+                .set("y", bounds.1)
+                .set("width", bounds.2)
+                .set("height", bounds.3),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, generate_svg, LakeSettings, Prototype};
+    use crate::lint::lint;
+
+    #[test]
+    fn small_lake() {
+        let settings = LakeSettings {
+            width: 60.0,
+            lakes: 1,
+            tree_density: 0.05,
+            ..Default::default()
+        };
+        insta::assert_snapshot!(generate_svg(7, &settings).to_string());
+    }
+
+    #[test]
+    fn playable() {
+        let settings = LakeSettings::default();
+        for seed in 0..50 {
+            let level = generate(seed, &settings).unwrap();
+            assert_eq!(lint(&level), vec![], "seed {seed}");
+            assert_eq!(level.water_volumes().len(), settings.lakes, "seed {seed}");
+            assert_eq!(level.fish_spawns().len(), settings.lakes, "seed {seed}");
+            assert!(level.camera_bounds().is_some());
+        }
+    }
+
+    #[test]
+    fn seeds() {
+        let settings = LakeSettings::default();
+        let svg = |seed| generate_svg(seed, &settings).to_string();
+        assert_eq!(svg(1), svg(1));
+        assert_ne!(svg(1), svg(2));
+    }
+
+    #[test]
+    fn prototypes() {
+        let tree = Prototype::load("tree").unwrap();
+        assert_eq!(tree.element.get_children().len(), 8);
+        assert!(Prototype::load("rocks").is_some());
+        assert!(Prototype::load("missing").is_none());
+    }
+}
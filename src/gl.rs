@@ -0,0 +1,117 @@
+//! The WebGL calls the renderer makes, so they can be recorded in tests instead, see
+//! [`crate::recording_gl::RecordingGl`].
+
+use wasm_bindgen::JsValue;
+use web_sys::{WebGlBuffer, WebGlProgram, WebGlRenderingContext, WebGlUniformLocation};
+
+use crate::utils::{float_32_array, uint_32_array};
+
+/// A subset of `WebGlRenderingContext`. Buffer data is passed as slices, the WebGL
+/// implementation makes views into wasm memory from them.
+pub trait Gl {
+    type Buffer;
+    type Program;
+    type UniformLocation;
+
+    /// Whether the extension is supported, it is enabled if it is.
+    fn enable_extension(&self, name: &str) -> Result<bool, JsValue>;
+    fn drawing_buffer_size(&self) -> (u32, u32);
+
+    fn enable(&self, capability: u32);
+    fn blend_func(&self, source: u32, destination: u32);
+    fn clear(&self, color: [f32; 4]);
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32);
+
+    fn create_buffer(&self) -> Option<Self::Buffer>;
+    fn bind_buffer(&self, target: u32, buffer: Option<&Self::Buffer>);
+    fn buffer_data_f32(&self, target: u32, data: &[f32], usage: u32) -> Result<(), JsValue>;
+    fn buffer_data_u32(&self, target: u32, data: &[u32], usage: u32) -> Result<(), JsValue>;
+
+    fn use_program(&self, program: &Self::Program);
+    fn uniform_matrix4fv(&self, location: &Self::UniformLocation, value: &[f32; 16]);
+    fn uniform1f(&self, location: &Self::UniformLocation, value: f32);
+
+    /// Feeds the attribute at `index` with `size` floats per vertex from `buffer`.
+    fn bind_attribute(&self, buffer: &Self::Buffer, index: u32, size: i32);
+    fn disable_vertex_attrib_array(&self, index: u32);
+    fn draw_elements(&self, mode: u32, count: i32, type_: u32, offset: i32);
+}
+
+impl Gl for WebGlRenderingContext {
+    type Buffer = WebGlBuffer;
+    type Program = WebGlProgram;
+    type UniformLocation = WebGlUniformLocation;
+
+    fn enable_extension(&self, name: &str) -> Result<bool, JsValue> {
+        Ok(self.get_extension(name)?.is_some())
+    }
+
+    fn drawing_buffer_size(&self) -> (u32, u32) {
+        (
+            self.drawing_buffer_width() as u32,
+            self.drawing_buffer_height() as u32,
+        )
+    }
+
+    fn enable(&self, capability: u32) {
+        self.enable(capability);
+    }
+
+    fn blend_func(&self, source: u32, destination: u32) {
+        self.blend_func(source, destination);
+    }
+
+    fn clear(&self, [r, g, b, a]: [f32; 4]) {
+        self.clear_color(r, g, b, a);
+        self.clear_depth(1.0);
+        self.clear(
+            WebGlRenderingContext::COLOR_BUFFER_BIT | WebGlRenderingContext::DEPTH_BUFFER_BIT,
+        );
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.viewport(x, y, width, height);
+    }
+
+    fn create_buffer(&self) -> Option<WebGlBuffer> {
+        self.create_buffer()
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<&WebGlBuffer>) {
+        self.bind_buffer(target, buffer);
+    }
+
+    fn buffer_data_f32(&self, target: u32, data: &[f32], usage: u32) -> Result<(), JsValue> {
+        self.buffer_data_with_array_buffer_view(target, &float_32_array(data)?.into(), usage);
+        Ok(())
+    }
+
+    fn buffer_data_u32(&self, target: u32, data: &[u32], usage: u32) -> Result<(), JsValue> {
+        self.buffer_data_with_array_buffer_view(target, &uint_32_array(data)?.into(), usage);
+        Ok(())
+    }
+
+    fn use_program(&self, program: &WebGlProgram) {
+        self.use_program(Some(program));
+    }
+
+    fn uniform_matrix4fv(&self, location: &WebGlUniformLocation, value: &[f32; 16]) {
+        self.uniform_matrix4fv_with_f32_array(Some(location), false, value);
+    }
+
+    fn uniform1f(&self, location: &WebGlUniformLocation, value: f32) {
+        self.uniform1f(Some(location), value);
+    }
+
+    fn bind_attribute(&self, buffer: &WebGlBuffer, index: u32, size: i32) {
+        crate::utils::bind_buffer_to_attribute(self, buffer, index, size);
+    }
+
+    fn disable_vertex_attrib_array(&self, index: u32) {
+        self.disable_vertex_attrib_array(index);
+    }
+
+    fn draw_elements(&self, mode: u32, count: i32, type_: u32, offset: i32) {
+        self.draw_elements_with_i32(mode, count, type_, offset);
+    }
+}
@@ -0,0 +1,564 @@
+use std::collections::HashMap;
+
+use crate::svg_path::Point;
+use crate::transform::Transform;
+use crate::triangulation::signed_area;
+use crate::types::Color;
+
+// `href` chains longer than this are treated as broken
+const MAX_HREF_DEPTH: usize = 16;
+
+// radial gradients are split into triangles until interpolating the position between their
+// corners is off by less than this
+const RADIAL_TOLERANCE: f32 = 0.005;
+// how often a triangle may be halved for a radial gradient, 2^10 pieces at most
+const MAX_RADIAL_SPLITS: usize = 10;
+// a gradient repeated more often than this across a triangle is not split at every repetition
+const MAX_BREAKS: usize = 256;
+
+/// https://www.w3.org/TR/SVG/pservers.html#LinearGradientElementGradientUnitsAttribute
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GradientUnits {
+    #[default]
+    ObjectBoundingBox,
+    UserSpaceOnUse,
+}
+
+impl GradientUnits {
+    pub fn parse(value: &str) -> Option<GradientUnits> {
+        match value.trim() {
+            "objectBoundingBox" => Some(GradientUnits::ObjectBoundingBox),
+            "userSpaceOnUse" => Some(GradientUnits::UserSpaceOnUse),
+            _ => None,
+        }
+    }
+}
+
+/// https://www.w3.org/TR/SVG/pservers.html#LinearGradientElementSpreadMethodAttribute
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Spread {
+    #[default]
+    Pad,
+    Reflect,
+    Repeat,
+}
+
+impl Spread {
+    pub fn parse(value: &str) -> Option<Spread> {
+        match value.trim() {
+            "pad" => Some(Spread::Pad),
+            "reflect" => Some(Spread::Reflect),
+            "repeat" => Some(Spread::Repeat),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, t: f32) -> f32 {
+        match self {
+            Spread::Pad => t.clamp(0.0, 1.0),
+            Spread::Repeat => t.rem_euclid(1.0),
+            Spread::Reflect => 1.0 - (t.rem_euclid(2.0) - 1.0).abs(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: Color,
+}
+
+/// A `<linearGradient>` or `<radialGradient>` element as written in the document. Everything
+/// it does not specify is taken from the gradient its `href` points to.
+#[derive(Clone, Debug, Default)]
+pub struct GradientElement {
+    pub radial: bool,
+    pub href: Option<String>,
+    pub units: Option<GradientUnits>,
+    pub transform: Option<Transform>,
+    pub spread: Option<Spread>,
+    /// `x1`, `y1`, `x2`, `y2` or `cx`, `cy`, `r`, `fx`, `fy`, with percentages as fractions.
+    pub coordinates: HashMap<&'static str, f32>,
+    pub stops: Vec<GradientStop>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GradientShape {
+    Linear {
+        start: Point,
+        end: Point,
+    },
+    Radial {
+        center: Point,
+        radius: f32,
+        focus: Point,
+    },
+}
+
+/// A gradient with all references resolved and the defaults filled in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub shape: GradientShape,
+    pub units: GradientUnits,
+    pub transform: Transform,
+    pub spread: Spread,
+    pub stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    /// Resolves the gradient with the given id, following `href`s. `None` if there is no such
+    /// gradient or the references form a cycle.
+    pub fn resolve(id: &str, elements: &HashMap<String, GradientElement>) -> Option<Gradient> {
+        let element = elements.get(id)?;
+        let mut chain = vec![element];
+        while let Some(href) = &chain[chain.len() - 1].href {
+            if chain.len() > MAX_HREF_DEPTH {
+                return None;
+            }
+            match elements.get(href) {
+                Some(referenced) => chain.push(referenced),
+                // a dangling reference just does not contribute anything
+                None => break,
+            }
+        }
+
+        let units = chain.iter().find_map(|e| e.units).unwrap_or_default();
+        let transform = chain.iter().find_map(|e| e.transform).unwrap_or_default();
+        let spread = chain.iter().find_map(|e| e.spread).unwrap_or_default();
+        let stops = chain
+            .iter()
+            .map(|e| &e.stops)
+            .find(|stops| !stops.is_empty())
+            .cloned()
+            .unwrap_or_default();
+        let coordinate = |name: &str, default: f32| {
+            chain
+                .iter()
+                .find_map(|e| e.coordinates.get(name).copied())
+                .unwrap_or(default)
+        };
+
+        let shape = if element.radial {
+            let (cx, cy) = (coordinate("cx", 0.5), coordinate("cy", 0.5));
+            GradientShape::Radial {
+                center: (cx, cy),
+                radius: coordinate("r", 0.5),
+                focus: (coordinate("fx", cx), coordinate("fy", cy)),
+            }
+        } else {
+            GradientShape::Linear {
+                start: (coordinate("x1", 0.0), coordinate("y1", 0.0)),
+                end: (coordinate("x2", 1.0), coordinate("y2", 0.0)),
+            }
+        };
+
+        Some(Gradient {
+            shape,
+            units,
+            transform,
+            spread,
+            stops,
+        })
+    }
+
+    /// The transform from gradient space to the user space of an element with the given
+    /// bounding box `(x, y, width, height)`.
+    pub fn to_user_space(&self, (x, y, width, height): (f32, f32, f32, f32)) -> Transform {
+        match self.units {
+            GradientUnits::UserSpaceOnUse => self.transform,
+            GradientUnits::ObjectBoundingBox => Transform::translate(x, y)
+                .then(&Transform::scale(width, height))
+                .then(&self.transform),
+        }
+    }
+
+    /// The color at `point`, given in gradient space. `None` if there are no stops, in which
+    /// case nothing is painted.
+    #[cfg(test)]
+    pub fn color_at(&self, point: Point) -> Option<Color> {
+        self.color_at_position(self.position(point))
+    }
+
+    /// Splits a triangle into triangles whose colors can be interpolated between their corners,
+    /// with the colors of their corners. Linear gradients are split where they pass a stop,
+    /// radial ones are also split into pieces small enough to follow the circles.
+    /// `to_gradient` maps the corners into gradient space.
+    pub fn split(
+        &self,
+        triangle: [Point; 3],
+        to_gradient: &Transform,
+    ) -> Vec<([Point; 3], [Color; 3])> {
+        let position = |point: Point| self.position(to_gradient.apply(point));
+        let mut pieces = vec![];
+        self.refine(triangle, triangle.map(position), &position, 0, &mut pieces);
+
+        for position in self.breaks(&pieces) {
+            pieces = pieces
+                .into_iter()
+                .flat_map(|(corners, positions)| split_at(corners, positions, position))
+                .collect();
+        }
+
+        pieces
+            .into_iter()
+            .filter(|(corners, _)| signed_area(corners) != 0.0)
+            .filter_map(|(corners, positions)| {
+                let colors = positions.map(|position| self.color_at_position(position));
+                Some((corners, [colors[0]?, colors[1]?, colors[2]?]))
+            })
+            .collect()
+    }
+
+    // halves radial gradient triangles at their longest edge until the position between the
+    // corners is close enough to a linear one
+    fn refine(
+        &self,
+        corners: [Point; 3],
+        positions: [f32; 3],
+        position: &impl Fn(Point) -> f32,
+        depth: usize,
+        out: &mut Vec<([Point; 3], [f32; 3])>,
+    ) {
+        let linear = matches!(self.shape, GradientShape::Linear { .. });
+        if linear || depth == MAX_RADIAL_SPLITS {
+            out.push((corners, positions));
+            return;
+        }
+
+        let middle = |i: usize, j: usize| {
+            let (a, b) = (corners[i], corners[j]);
+            ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+        };
+        let centroid = (
+            corners.iter().map(|p| p.0).sum::<f32>() / 3.0,
+            corners.iter().map(|p| p.1).sum::<f32>() / 3.0,
+        );
+        let error = [(0, 1), (1, 2), (2, 0)]
+            .into_iter()
+            .map(|(i, j)| (position(middle(i, j)) - (positions[i] + positions[j]) / 2.0).abs())
+            .chain([(position(centroid) - positions.iter().sum::<f32>() / 3.0).abs()])
+            .fold(0.0, f32::max);
+        if error <= RADIAL_TOLERANCE {
+            out.push((corners, positions));
+            return;
+        }
+
+        let length = |i: usize, j: usize| {
+            let (a, b) = (corners[i], corners[j]);
+            (b.0 - a.0).hypot(b.1 - a.1)
+        };
+        // the corners in the same order, starting with the longest edge
+        let i = (0..3)
+            .max_by(|i, j| length(*i, (i + 1) % 3).total_cmp(&length(*j, (j + 1) % 3)))
+            .unwrap();
+        let (a, b, c) = (i, (i + 1) % 3, (i + 2) % 3);
+        let m = middle(a, b);
+        let mp = position(m);
+        for (corners, positions) in [
+            (
+                [corners[a], m, corners[c]],
+                [positions[a], mp, positions[c]],
+            ),
+            (
+                [m, corners[b], corners[c]],
+                [mp, positions[b], positions[c]],
+            ),
+        ] {
+            self.refine(corners, positions, position, depth + 1, out);
+        }
+    }
+
+    // the positions the colors are not linear across, where the pieces have to be split
+    fn breaks(&self, pieces: &[([Point; 3], [f32; 3])]) -> Vec<f32> {
+        let positions = pieces.iter().flat_map(|(_, positions)| positions);
+        let from = positions.clone().copied().fold(f32::INFINITY, f32::min);
+        let to = positions.copied().fold(f32::NEG_INFINITY, f32::max);
+
+        let mut offsets = vec![0.0, 1.0];
+        let mut last = 0.0_f32;
+        for stop in &self.stops {
+            last = stop.offset.max(last);
+            offsets.push(last.clamp(0.0, 1.0));
+        }
+        // positions of the offsets before spreading, repeated every `period`
+        let (period, offsets) = match self.spread {
+            Spread::Pad => (f32::INFINITY, offsets),
+            Spread::Repeat => (1.0, offsets),
+            Spread::Reflect => {
+                let reflected = offsets.iter().map(|offset| 2.0 - offset);
+                (2.0, offsets.iter().copied().chain(reflected).collect())
+            }
+        };
+
+        let mut breaks = if period.is_infinite() {
+            offsets
+        } else {
+            if (to - from) / period > MAX_BREAKS as f32 {
+                return vec![];
+            }
+            let first = (from / period).floor() as i32;
+            let last = (to / period).floor() as i32;
+            (first..=last)
+                .flat_map(|k| offsets.iter().map(move |offset| k as f32 * period + offset))
+                .collect()
+        };
+        breaks.retain(|position| from < *position && *position < to);
+        breaks.sort_by(f32::total_cmp);
+        breaks.dedup();
+        breaks
+    }
+
+    // See https://www.w3.org/TR/SVG/pservers.html#GradientStops
+    fn color_at_position(&self, position: f32) -> Option<Color> {
+        let t = self.spread.apply(position);
+
+        let first = self.stops.first()?;
+        let mut color = first.color;
+        let mut offset = first.offset;
+        for stop in self.stops.iter() {
+            // offsets are clamped to be increasing
+            let stop_offset = stop.offset.max(offset);
+            if t < stop_offset {
+                if stop_offset == offset {
+                    return Some(stop.color);
+                }
+                let f = (t - offset) / (stop_offset - offset);
+                let mut mixed = color;
+                for (mixed, (from, to)) in mixed.iter_mut().zip(color.iter().zip(stop.color)) {
+                    *mixed = from + (to - from) * f;
+                }
+                return Some(mixed);
+            }
+            color = stop.color;
+            offset = stop_offset;
+        }
+        Some(color)
+    }
+
+    // position along the gradient vector before spreading, 0 at the start and 1 at the end
+    fn position(&self, (x, y): Point) -> f32 {
+        match self.shape {
+            GradientShape::Linear { start, end } => {
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let length_squared = dx * dx + dy * dy;
+                if length_squared == 0.0 {
+                    // the last stop paints the whole area
+                    return 1.0;
+                }
+                ((x - start.0) * dx + (y - start.1) * dy) / length_squared
+            }
+            GradientShape::Radial {
+                center,
+                radius,
+                focus,
+            } => {
+                if radius <= 0.0 {
+                    return 1.0;
+                }
+
+                // a focus outside of the circle is moved onto it, like SVG 1.1 requires
+                let (mut ex, mut ey) = (center.0 - focus.0, center.1 - focus.1);
+                let distance = ex.hypot(ey);
+                let max_distance = radius * 0.999;
+                if distance > max_distance {
+                    ex *= max_distance / distance;
+                    ey *= max_distance / distance;
+                }
+                let (fx, fy) = (center.0 - ex, center.1 - ey);
+
+                // solve |d - t * e| = t * r for the circle through the point, where d points
+                // from the focus to the point and e from the focus to the center
+                let (dx, dy) = (x - fx, y - fy);
+                let a = ex * ex + ey * ey - radius * radius;
+                let b = dx * ex + dy * ey;
+                let c = dx * dx + dy * dy;
+                (b - (b * b - a * c).sqrt()) / a
+            }
+        }
+    }
+}
+
+// splits a triangle where the position, interpolated between its corners, is `at`
+fn split_at(corners: [Point; 3], positions: [f32; 3], at: f32) -> Vec<([Point; 3], [f32; 3])> {
+    let below = positions.iter().filter(|position| **position < at).count();
+    // the corner on its own side of the line
+    let alone = match below {
+        1 => positions.iter().position(|position| *position < at),
+        2 => positions.iter().position(|position| *position >= at),
+        _ => None,
+    };
+    let a = match alone {
+        Some(a) => a,
+        None => return vec![(corners, positions)],
+    };
+
+    let (b, c) = ((a + 1) % 3, (a + 2) % 3);
+    let cut = |other: usize| {
+        let f = (at - positions[a]) / (positions[other] - positions[a]);
+        let (from, to) = (corners[a], corners[other]);
+        (from.0 + (to.0 - from.0) * f, from.1 + (to.1 - from.1) * f)
+    };
+    let (p, q) = (cut(b), cut(c));
+    // in the same direction as the triangle
+    vec![
+        ([corners[a], p, q], [positions[a], at, at]),
+        (
+            [p, corners[b], corners[c]],
+            [at, positions[b], positions[c]],
+        ),
+        ([p, corners[c], q], [at, positions[c], at]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Gradient, GradientElement, GradientShape, GradientStop, Spread};
+    use crate::transform::Transform;
+    use crate::triangulation::signed_area;
+
+    fn assert_color(actual: Option<[f32; 4]>, expected: [f32; 4]) {
+        let actual = actual.unwrap();
+        assert!(
+            actual
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a - b).abs() < 1e-4),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    fn black_to_white() -> GradientElement {
+        GradientElement {
+            stops: vec![
+                GradientStop {
+                    offset: 0.0,
+                    color: [0.0, 0.0, 0.0, 1.0],
+                },
+                GradientStop {
+                    offset: 1.0,
+                    color: [1.0, 1.0, 1.0, 1.0],
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn linear() {
+        let elements = HashMap::from([("sky".to_string(), black_to_white())]);
+        let gradient = Gradient::resolve("sky", &elements).unwrap();
+
+        assert_color(gradient.color_at((-1.0, 0.0)), [0.0, 0.0, 0.0, 1.0]);
+        assert_color(gradient.color_at((0.25, 7.0)), [0.25, 0.25, 0.25, 1.0]);
+        assert_color(gradient.color_at((2.0, 0.0)), [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn spread() {
+        let mut element = black_to_white();
+        element.spread = Some(Spread::Reflect);
+        let elements = HashMap::from([("sky".to_string(), element)]);
+        let reflect = Gradient::resolve("sky", &elements).unwrap();
+        let repeat = Gradient {
+            spread: Spread::Repeat,
+            ..reflect.clone()
+        };
+
+        assert_color(reflect.color_at((1.25, 0.0)), [0.75, 0.75, 0.75, 1.0]);
+        assert_color(repeat.color_at((1.25, 0.0)), [0.25, 0.25, 0.25, 1.0]);
+    }
+
+    #[test]
+    fn radial_with_focus() {
+        let mut element = black_to_white();
+        element.radial = true;
+        element.coordinates.insert("fx", 0.25);
+        let elements = HashMap::from([("sun".to_string(), element)]);
+        let gradient = Gradient::resolve("sun", &elements).unwrap();
+
+        assert_color(gradient.color_at((0.25, 0.5)), [0.0, 0.0, 0.0, 1.0]);
+        assert_color(gradient.color_at((1.0, 0.5)), [1.0, 1.0, 1.0, 1.0]);
+        assert_color(gradient.color_at((0.0, 0.5)), [1.0, 1.0, 1.0, 1.0]);
+        // halfway between the focus and the circle on either side
+        assert_color(gradient.color_at((0.625, 0.5)), [0.5, 0.5, 0.5, 1.0]);
+        assert_color(gradient.color_at((0.125, 0.5)), [0.5, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn split() {
+        let mut element = black_to_white();
+        element.stops.insert(
+            1,
+            GradientStop {
+                offset: 0.5,
+                color: [1.0, 0.0, 0.0, 1.0],
+            },
+        );
+        let elements = HashMap::from([("fire".to_string(), element)]);
+        let gradient = Gradient::resolve("fire", &elements).unwrap();
+        let triangle = [(-1.0, 0.0), (2.0, 0.0), (2.0, 1.0)];
+
+        let pieces = gradient.split(triangle, &Transform::identity());
+        // no piece crosses a stop
+        let band = |point| {
+            [0.0, 0.5, 1.0]
+                .iter()
+                .filter(|&&o| gradient.position(point) > o + 1e-4)
+                .count()
+        };
+        for (corners, _) in pieces.iter() {
+            let bands: Vec<_> = corners.iter().map(|c| band(*c)).collect();
+            assert!(
+                bands.iter().max().unwrap() - bands.iter().min().unwrap() <= 1,
+                "{corners:?}"
+            );
+        }
+        let area: f32 = pieces.iter().map(|(t, _)| signed_area(t)).sum();
+        assert!((area - signed_area(&triangle)).abs() < 1e-5);
+        for (corners, colors) in pieces {
+            assert!(signed_area(&corners) > 0.0);
+            for (corner, color) in corners.iter().zip(colors) {
+                assert_color(gradient.color_at(*corner), color);
+            }
+        }
+
+        let radial = Gradient {
+            shape: GradientShape::Radial {
+                center: (0.5, 0.5),
+                radius: 0.5,
+                focus: (0.5, 0.5),
+            },
+            ..gradient
+        };
+        let pieces = radial.split([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)], &Transform::identity());
+        assert!(pieces.len() > 16, "{}", pieces.len());
+    }
+
+    #[test]
+    fn href_inherits_stops_and_attributes() {
+        let mut template = black_to_white();
+        template.spread = Some(Spread::Repeat);
+        let mut sky = GradientElement {
+            href: Some("template".to_string()),
+            ..Default::default()
+        };
+        sky.coordinates.insert("x2", 2.0);
+        let looping = GradientElement {
+            href: Some("looping".to_string()),
+            ..Default::default()
+        };
+        let elements = HashMap::from([
+            ("template".to_string(), template),
+            ("sky".to_string(), sky),
+            ("looping".to_string(), looping),
+        ]);
+
+        let gradient = Gradient::resolve("sky", &elements).unwrap();
+        assert_eq!(gradient.spread, Spread::Repeat);
+        assert_color(gradient.color_at((1.0, 0.0)), [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(Gradient::resolve("looping", &elements), None);
+        assert_eq!(Gradient::resolve("missing", &elements), None);
+    }
+}
@@ -1,246 +1,1252 @@
-use std::borrow::BorrowMut;
-use std::cell::RefCell;
-
-use crate::types::{Color, Entity, ShaderId, Triangle};
-use regex::Regex;
-use svg;
-use svg::node::element::path::{Command, Data, Position};
-use svg::node::element::Style;
+use crate::color::{parse_color, parse_opacity, Paint};
+use crate::css::StyleSheet;
+use crate::export::{self, Snapshot};
+use crate::fish::FishRace;
+use crate::gradient::{Gradient, GradientElement, GradientStop, GradientUnits, Spread};
+use crate::markup::{
+    parse_label, split_label, Exit, FishSpawn, ItemSpawn, MarkerKind, Trigger, DEFAULT_FISH_DENSITY,
+};
+use crate::mesh::{self, StaticMesh};
+use crate::metadata::{parse_vector, CameraFraming, LevelMetadata};
+use crate::shapes;
+use crate::stroke::{self, parse_dash_array, parse_length, LineCap, LineJoin, StrokeStyle};
+use crate::svg_path::{flatten_path, Point, Subpath, DEFAULT_CURVE_TOLERANCE};
+use crate::transform::Transform;
+use crate::triangulation::{self_intersects, signed_area, triangulate, FillRule};
+use crate::types::{Color, Entity, GameState, ShaderId, Triangle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::{error, fmt};
+use svg::node::element::path::Data;
+use svg::node::element::tag::Type;
+use svg::node::Attributes;
 use svg::parser::Event;
 
+// a painted element of the level, before triangulation
+struct Shape {
+    // the tag name and id of the element, for warnings
+    element: String,
+    id: Option<String>,
+    subpaths: Vec<Subpath>,
+    fill_rule: FillRule,
+    fill: Option<ShapePaint>,
+    // tessellated right away in local coordinates, then transformed to world coordinates
+    stroke: Option<(ShapePaint, Vec<[Point; 3]>)>,
+    // from local to world coordinates
+    transform: Transform,
+    // in local coordinates, for gradients relative to the bounding box
+    bounds: (f32, f32, f32, f32),
+    wireframe: bool,
+    // the surface height if the shape is a water volume
+    water_surface: Option<f32>,
+    // index into the render layers
+    layer: usize,
+}
+
+// gradients may be defined after they are used, so they are looked up once the whole
+// document is parsed
+#[derive(Clone)]
+enum ShapePaint {
+    Color(Color),
+    Gradient { id: String, opacity: f32 },
+    // no paint was given, drawn in magenta
+    Unpainted { opacity: f32 },
+}
+
+// a shape paint with the gradient resolved
+enum ResolvedPaint {
+    Color(Color),
+    // missing or unresolved paint
+    Unpainted {
+        opacity: f32,
+    },
+    Gradient {
+        gradient: Gradient,
+        // from world to gradient coordinates
+        transform: Transform,
+        opacity: f32,
+    },
+}
+
+impl ResolvedPaint {
+    fn resolve(
+        paint: &ShapePaint,
+        shape: &Shape,
+        gradients: &HashMap<String, GradientElement>,
+    ) -> Option<ResolvedPaint> {
+        let (id, opacity) = match paint {
+            ShapePaint::Color(color) => return Some(ResolvedPaint::Color(*color)),
+            ShapePaint::Unpainted { opacity } => {
+                return Some(ResolvedPaint::Unpainted { opacity: *opacity })
+            }
+            ShapePaint::Gradient { id, opacity } => (id, *opacity),
+        };
+
+        let gradient = match Gradient::resolve(id, gradients) {
+            Some(gradient) => gradient,
+            None => return Some(ResolvedPaint::Unpainted { opacity }),
+        };
+        if gradient.stops.is_empty() {
+            return None;
+        }
+        // a bounding box without area makes the gradient undefined, nothing is painted then
+        let transform = shape
+            .transform
+            .then(&gradient.to_user_space(shape.bounds))
+            .inverse()?;
+
+        Some(ResolvedPaint::Gradient {
+            gradient,
+            transform,
+            opacity,
+        })
+    }
+
+    // the triangle with the colors of its corners, split up where the paint needs more corners
+    fn paint(&self, triangle: [Point; 3]) -> Vec<([Point; 3], [Color; 3])> {
+        let color = match self {
+            ResolvedPaint::Color(color) => *color,
+            ResolvedPaint::Unpainted { opacity } => {
+                let mut color = UNPAINTED;
+                color[3] *= opacity;
+                color
+            }
+            ResolvedPaint::Gradient {
+                gradient,
+                transform,
+                opacity,
+            } => {
+                let mut pieces = gradient.split(triangle, transform);
+                for color in pieces.iter_mut().flat_map(|(_, colors)| colors) {
+                    color[3] *= opacity;
+                }
+                return pieces;
+            }
+        };
+        vec![(triangle, [color; 3])]
+    }
+}
+
+// elements without any fill and unresolved references are drawn in this color, so they
+// stand out
+const UNPAINTED: Color = [1.0, 0.0, 1.0, 1.0];
+
+// fills that lose a bigger part than this in triangulation get a warning
+const LOST_AREA_TOLERANCE: f32 = 0.01;
+
+// the size of a root element without a `viewBox` or size, like browsers do
+const DEFAULT_VIEWPORT: (f32, f32) = (300.0, 150.0);
+
+// children of these elements are not rendered directly
+const NOT_RENDERED: [&str; 6] = ["defs", "clipPath", "mask", "marker", "pattern", "symbol"];
+
+const LINEAR_GRADIENT_COORDINATES: [&str; 4] = ["x1", "y1", "x2", "y2"];
+const RADIAL_GRADIENT_COORDINATES: [&str; 5] = ["cx", "cy", "r", "fx", "fy"];
+
+// state that child elements inherit from their parents
+#[derive(Clone)]
+struct Inherited {
+    transform: Transform,
+    fill_rule: FillRule,
+    fill: Option<Paint>,
+    fill_opacity: f32,
+    stroke: Option<Paint>,
+    stroke_opacity: f32,
+    stroke_style: StrokeStyle,
+    // the `color` property, used by `currentColor`
+    color: Option<Color>,
+    // `opacity` is not inherited, but a group's opacity applies to all its children
+    opacity: f32,
+    // `display:none` hides all descendants, whatever they say themselves
+    hidden: bool,
+    // the Inkscape layer the element is in
+    layer: Option<usize>,
+}
+
+impl Default for Inherited {
+    fn default() -> Self {
+        Inherited {
+            transform: Transform::identity(),
+            fill_rule: FillRule::default(),
+            fill: None,
+            fill_opacity: 1.0,
+            stroke: None,
+            stroke_opacity: 1.0,
+            stroke_style: StrokeStyle::default(),
+            color: None,
+            opacity: 1.0,
+            hidden: false,
+            layer: None,
+        }
+    }
+}
+
+impl Inherited {
+    /// The fill with `fill-opacity` and `opacity` applied, `None` for `fill:none`.
+    fn fill_paint(&self) -> Option<ShapePaint> {
+        let opacity = self.fill_opacity * self.opacity;
+        match &self.fill {
+            Some(paint) => self.shape_paint(paint, opacity),
+            None => Some(ShapePaint::Unpainted { opacity }),
+        }
+    }
+
+    /// The stroke with `stroke-opacity` and `opacity` applied. Unlike fills, strokes default
+    /// to `none`.
+    fn stroke_paint(&self) -> Option<ShapePaint> {
+        let paint = self.stroke.as_ref()?;
+        self.shape_paint(paint, self.stroke_opacity * self.opacity)
+    }
+
+    fn shape_paint(&self, paint: &Paint, opacity: f32) -> Option<ShapePaint> {
+        let mut color = match paint {
+            Paint::None => return None,
+            Paint::Color(color) => *color,
+            Paint::CurrentColor => match self.color {
+                Some(color) => color,
+                None => return Some(ShapePaint::Unpainted { opacity }),
+            },
+            Paint::Url(id) => {
+                return Some(ShapePaint::Gradient {
+                    id: id.clone(),
+                    opacity,
+                })
+            }
+        };
+        color[3] *= opacity;
+        Some(ShapePaint::Color(color))
+    }
+}
+
+/// A loaded level. Besides SVG, levels can be stored pre-triangulated as RON, which is much
+/// faster to load, see [`Level::to_ron`] and the `svg_to_level` binary.
+#[derive(Serialize, Deserialize)]
 pub struct Level {
+    #[serde(skip)]
     id: String,
-    triangles: Vec<Triangle>,
-    player_pos: (f32, f32),
-    ground: (f32, f32, f32, f32),
-}
-
-fn update_point(
-    (x, y): &mut (f32, f32),
-    position: Position,
-    (new_x, new_y): (Option<f32>, Option<f32>),
-) -> (f32, f32) {
-    match position {
-        Position::Absolute => {
-            *x = new_x.unwrap_or(*x);
-            *y = new_y.unwrap_or(*y);
+    metadata: LevelMetadata,
+    /// In draw order.
+    layers: Vec<RenderLayer>,
+    // where the camera looks, the parallax layers are moved for it when they are drawn
+    #[serde(skip)]
+    camera_center: Point,
+    // the layers are uploaded to the GPU again when this changes
+    #[serde(skip)]
+    mesh_version: u64,
+    // only known when loading an SVG
+    #[serde(skip)]
+    warnings: Vec<LevelWarning>,
+    // the bounding box of the player marker
+    player: Option<(f32, f32, f32, f32)>,
+    colliders: Vec<Collider>,
+    water_volumes: Vec<WaterVolume>,
+    fish_spawns: Vec<FishSpawn>,
+    item_spawns: Vec<ItemSpawn>,
+    camera_bounds: Option<(f32, f32, f32, f32)>,
+    triggers: Vec<Trigger>,
+    exits: Vec<Exit>,
+}
+
+/// The triangles of a top level Inkscape layer, `<g inkscape:groupmode="layer">`. Content that
+/// is not in a layer forms its own layers in between.
+///
+/// The layer label can set `parallax` and `z-index`, like `Mountains parallax=0.25 z-index=-1`,
+/// or `data-parallax` and `data-z-index` attributes. Layers are drawn by increasing z-index,
+/// layers with the same z-index in document order. Entities are drawn in between by their
+/// z-index, see [`Entity::z_index`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RenderLayer {
+    pub name: Option<String>,
+    /// How far the layer moves with the camera, `1` for the foreground, `0.5` for a background
+    /// that scrolls at half the speed and `0` for one that does not scroll at all. Only the
+    /// drawing moves, colliders and markers in the layer stay where they are.
+    pub parallax: f32,
+    pub z_index: i32,
+    pub triangles: Vec<Triangle>,
+    /// Where the triangles came from, in the same order.
+    #[serde(default)]
+    pub elements: Vec<SourceElement>,
+}
+
+impl Default for RenderLayer {
+    fn default() -> Self {
+        RenderLayer {
+            name: None,
+            parallax: 1.0,
+            z_index: 0,
+            triangles: vec![],
+            elements: vec![],
         }
-        Position::Relative => {
-            *x += new_x.unwrap_or(0.0);
-            *y += new_y.unwrap_or(0.0);
+    }
+}
+
+/// A run of triangles in a [`RenderLayer`] that were made from the same element.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SourceElement {
+    pub element: String,
+    pub id: Option<String>,
+    /// The number of triangles.
+    pub triangles: usize,
+    /// The index of the water volume the element declares, if it is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub water_volume: Option<usize>,
+}
+
+/// Static level geometry that things collide with, declared by a `hitbox` marker or an element
+/// with a `data-solid` attribute.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Collider {
+    pub id: Option<String>,
+    /// The filled area of closed subpaths in world coordinates, each triangle is a convex
+    /// part of the collider.
+    pub triangles: Vec<[Point; 3]>,
+    /// Open subpaths, only their lines are solid.
+    pub polylines: Vec<Vec<Point>>,
+}
+
+/// A body of water, declared by a `water` marker or an element with a `data-water` attribute.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WaterVolume {
+    pub id: Option<String>,
+    /// The outline in world coordinates.
+    pub polygon: Vec<Point>,
+    /// The height of the water surface, the top of the polygon.
+    pub surface: f32,
+}
+
+/// Something wrong with an element that does not stop the level from loading.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelWarning {
+    pub element: String,
+    pub id: Option<String>,
+    pub problem: ElementProblem,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ElementProblem {
+    /// Painted without a fill, with `currentColor` but no `color` or with a missing gradient,
+    /// which is drawn in magenta.
+    Unpainted,
+    /// A collider or marker without any area.
+    ZeroSize,
+    /// A filled shape that triangulates to nothing.
+    Degenerate,
+    /// A closed path whose edges cross each other.
+    SelfIntersecting,
+    /// A fill whose triangles are off by `area` from what it should cover.
+    LostArea { area: f32 },
+}
+
+impl fmt::Display for LevelWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self.problem {
+            ElementProblem::Unpainted => "has no paint and is drawn in magenta".to_string(),
+            ElementProblem::ZeroSize => "has no area".to_string(),
+            ElementProblem::Degenerate => {
+                "is filled, but has no area after triangulation".to_string()
+            }
+            ElementProblem::SelfIntersecting => "has a path that intersects itself".to_string(),
+            ElementProblem::LostArea { area } => {
+                format!("lost an area of {area:.2} in triangulation")
+            }
+        };
+        write!(f, "{} {message}", describe(&self.element, &self.id))
+    }
+}
+
+/// Why a level could not be loaded. `element` is the tag name and `id` the `id` attribute of
+/// the broken element, if it has one.
+#[derive(Debug, PartialEq)]
+pub enum LevelError {
+    Xml {
+        message: String,
+    },
+    Ron {
+        message: String,
+    },
+    MissingAttribute {
+        element: String,
+        id: Option<String>,
+        attribute: String,
+    },
+    InvalidAttribute {
+        element: String,
+        id: Option<String>,
+        attribute: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Xml { message } => write!(f, "malformed SVG: {message}"),
+            LevelError::Ron { message } => write!(f, "malformed level: {message}"),
+            LevelError::MissingAttribute {
+                element,
+                id,
+                attribute,
+            } => write!(
+                f,
+                "{} is missing the \"{attribute}\" attribute",
+                describe(element, id)
+            ),
+            LevelError::InvalidAttribute {
+                element,
+                id,
+                attribute,
+                message,
+            } => write!(
+                f,
+                "{} has an invalid \"{attribute}\" attribute: {message}",
+                describe(element, id)
+            ),
         }
     }
-    (*x, *y)
 }
 
-impl Level {
-    #[allow(dead_code)]
-    pub fn load_from_svg_str(content: &str) -> Level {
-        Self::parse(svg::read(content).unwrap())
-    }
-
-    fn parse(parser: svg::parser::Parser) -> Level {
-        // points, color, wireframe
-        let polygons: RefCell<Vec<(Vec<f32>, Color, bool)>> =
-            RefCell::new(vec![(vec![0.0, 0.0], [1.0, 0.0, 1.0, 1.0], false)]);
-        let mut player_pos = (0.0, 0.0);
-        let mut hitbox = (0.0, 0.0, 0.0, 0.0);
-
-        for event in parser {
-            match event {
-                Event::Tag("path", _, attributes) => {
-                    let data = attributes.get("d").unwrap();
-                    let data = Data::parse(data).unwrap();
-
-                    let color = color_from_style(
-                        attributes.get("style").map(|v| Style::new(v.to_string())),
+impl error::Error for LevelError {}
+
+// an element as it appears in messages
+fn describe(element: &str, id: &Option<String>) -> String {
+    match id {
+        Some(id) => format!("<{element} id=\"{id}\">"),
+        None => format!("<{element}>"),
+    }
+}
+
+// attribute access that reports which element is broken
+struct Element<'a> {
+    name: &'a str,
+    attributes: &'a Attributes,
+    // from the style sheets, the ones that win come last
+    declarations: Vec<(&'a str, &'a str)>,
+}
+
+// a gameplay marker, see `crate::markup`
+struct Marker<'a> {
+    kind: MarkerKind,
+    // `key=value` words from the label
+    properties: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Element<'a> {
+    fn id(&self) -> Option<String> {
+        self.attributes.get("id").map(|v| v.to_string())
+    }
+
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.attributes.get(name).map(|v| v.as_ref())
+    }
+
+    fn required(&self, name: &str) -> Result<&'a str, LevelError> {
+        self.attribute(name)
+            .ok_or_else(|| LevelError::MissingAttribute {
+                element: self.name.to_string(),
+                id: self.id(),
+                attribute: name.to_string(),
+            })
+    }
+
+    fn invalid(&self, attribute: &str, message: impl ToString) -> LevelError {
+        LevelError::InvalidAttribute {
+            element: self.name.to_string(),
+            id: self.id(),
+            attribute: attribute.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn parse_number(&self, name: &str, value: &str) -> Result<f32, LevelError> {
+        value
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| self.invalid(name, format!("\"{value}\" is not a number")))
+    }
+
+    fn number(&self, name: &str) -> Result<Option<f32>, LevelError> {
+        self.attribute(name)
+            .map(|value| self.parse_number(name, value))
+            .transpose()
+    }
+
+    fn required_number(&self, name: &str) -> Result<f32, LevelError> {
+        self.parse_number(name, self.required(name)?)
+    }
+
+    /// An attribute parsed with `parse`, which returns `None` for invalid values.
+    fn parsed<T>(
+        &self,
+        name: &str,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Result<Option<T>, LevelError> {
+        self.attribute(name)
+            .map(|value| parse(value).ok_or_else(|| self.invalid(name, format!("\"{value}\""))))
+            .transpose()
+    }
+
+    /// A presentation property, the `style` attribute wins over style sheets, which win over
+    /// the attribute of the same name. `inherit` is treated like a missing property, all
+    /// properties we read are inherited anyway.
+    fn property(&self, name: &str) -> Option<&'a str> {
+        self.attribute("style")
+            .and_then(|style| style_property(style, name))
+            .or_else(|| {
+                self.declarations
+                    .iter()
+                    .rev()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| *value)
+            })
+            .or_else(|| self.attribute(name))
+            .filter(|value| value.trim() != "inherit")
+    }
+
+    fn paint_property(&self, name: &str) -> Result<Option<Paint>, LevelError> {
+        self.property(name)
+            .map(|value| {
+                Paint::parse(value)
+                    .ok_or_else(|| self.invalid(name, format!("\"{value}\" is not a color")))
+            })
+            .transpose()
+    }
+
+    /// A length, percentages are of `percentage_of`.
+    fn length_property(&self, name: &str, percentage_of: f32) -> Result<Option<f32>, LevelError> {
+        self.property(name)
+            .map(|value| {
+                parse_length(value, percentage_of)
+                    .ok_or_else(|| self.invalid(name, format!("\"{value}\" is not a length")))
+            })
+            .transpose()
+    }
+
+    /// The marker this element declares, from `data-marker`, the label or the id.
+    fn marker(&self) -> Result<Option<Marker<'a>>, LevelError> {
+        let label = self.attribute("inkscape:label").and_then(parse_label);
+        let kind = match self.attribute("data-marker") {
+            Some(value) => Some(MarkerKind::parse(value).ok_or_else(|| {
+                self.invalid("data-marker", format!("\"{value}\" is not a marker"))
+            })?),
+            None => match &label {
+                Some((kind, _)) => Some(*kind),
+                None => self.id().and_then(|id| MarkerKind::from_id(&id)),
+            },
+        };
+        Ok(kind.map(|kind| Marker {
+            kind,
+            properties: label.map(|(_, properties)| properties).unwrap_or_default(),
+        }))
+    }
+
+    /// A marker or layer property, the `data-` attribute wins over the `key=value` properties
+    /// from the label.
+    fn label_property<T>(
+        &self,
+        properties: &[(&str, &str)],
+        name: &str,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Result<Option<T>, LevelError> {
+        let attribute = format!("data-{name}");
+        if self.attribute(&attribute).is_some() {
+            return self.parsed(&attribute, parse);
+        }
+        properties
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| {
+                parse(value).ok_or_else(|| {
+                    self.invalid(
+                        "inkscape:label",
+                        format!("\"{value}\" is not a valid {name}"),
                     )
-                    .unwrap_or([1.0, 0.0, 1.0, 1.0]);
+                })
+            })
+            .transpose()
+    }
 
-                    let current_pos = RefCell::new((0.0, 0.0));
+    fn opacity_property(&self, name: &str) -> Result<Option<f32>, LevelError> {
+        self.property(name)
+            .map(|value| {
+                parse_opacity(value)
+                    .ok_or_else(|| self.invalid(name, format!("\"{value}\" is not an opacity")))
+            })
+            .transpose()
+    }
+}
 
-                    let push_point = |position: Position, x: Option<f32>, y: Option<f32>| {
-                        let p =
-                            update_point(current_pos.borrow_mut().borrow_mut(), position, (x, y));
+impl Level {
+    /// Loads a level in either format, SVG documents start with `<`.
+    pub fn load(content: &str) -> Result<Level, LevelError> {
+        if content.trim_start().starts_with('<') {
+            Self::load_from_svg_str(content)
+        } else {
+            Self::load_from_ron_str(content)
+        }
+    }
 
-                        if let Some(polygon) = polygons.borrow_mut().last_mut() {
-                            polygon.0.push(p.0);
-                            polygon.0.push(p.1);
-                        };
-                    };
+    pub fn load_from_ron_str(content: &str) -> Result<Level, LevelError> {
+        let level: Level = ron::from_str(content).map_err(|e| LevelError::Ron {
+            message: e.to_string(),
+        })?;
+        Ok(level.prepared())
+    }
 
-                    for command in data.iter() {
-                        match command {
-                            Command::Move(position, parameters) => {
-                                let mut point_it = parameters.array_chunks::<2>();
-
-                                if let Some([x, y]) = point_it.next() {
-                                    let mut current_pos = current_pos.borrow_mut();
-
-                                    update_point(
-                                        current_pos.borrow_mut(),
-                                        *position,
-                                        (Some(*x), Some(*y)),
-                                    );
-                                    polygons.borrow_mut().push((
-                                        vec![current_pos.0, current_pos.1],
-                                        color,
-                                        false,
-                                    ));
-                                }
-
-                                for [x, y] in point_it {
-                                    push_point(*position, Some(*x), Some(*y));
-                                }
-                            }
-                            Command::Line(position, parameters) => {
-                                for [x, y] in parameters.array_chunks::<2>() {
-                                    push_point(*position, Some(*x), Some(*y));
-                                }
-                            }
-                            Command::HorizontalLine(position, parameters) => {
-                                for x in parameters.iter() {
-                                    push_point(*position, Some(*x), None);
-                                }
-                            }
-                            Command::VerticalLine(position, parameters) => {
-                                for y in parameters.iter() {
-                                    push_point(*position, None, Some(*y));
-                                }
-                            }
-                            Command::CubicCurve(position, parameters) => {
-                                for [_x1, _y1, _x2, _y2, x, y] in parameters.array_chunks::<6>() {
-                                    push_point(*position, Some(*x), Some(*y));
-                                }
-                            }
-                            Command::SmoothCubicCurve(position, parameters) => {
-                                for [_x1, _x2, x, y] in parameters.array_chunks::<4>() {
-                                    push_point(*position, Some(*x), Some(*y));
-                                }
-                            }
-                            Command::QuadraticCurve(position, parameters) => {
-                                for [_x1, _y1, x, y] in parameters.array_chunks::<4>() {
-                                    push_point(*position, Some(*x), Some(*y));
-                                }
-                            }
-                            Command::SmoothQuadraticCurve(position, parameters) => {
-                                for [x, y] in parameters.array_chunks::<2>() {
-                                    push_point(*position, Some(*x), Some(*y));
-                                }
-                            }
-                            Command::EllipticalArc(position, parameters) => {
-                                for [_rx, _ry, _x_axis_rotation, _large_arc_flag, _sweep_flag, x, y] in
-                                    parameters.array_chunks::<7>()
-                                {
-                                    push_point(*position, Some(*x), Some(*y));
-                                }
-                            }
-                            Command::Close => {
-                                polygons.borrow_mut().push((
-                                    vec![0.0, 0.0],
-                                    [1.0, 0.0, 1.0, 1.0],
-                                    false,
-                                ));
-                            }
-                        }
+    /// The level as RON, which [`Level::load_from_ron_str`] reads back.
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        // one triangle per line
+        let config = ron::ser::PrettyConfig::new().depth_limit(4);
+        ron::ser::to_string_pretty(self, config)
+    }
+
+    pub fn load_from_svg_str(content: &str) -> Result<Level, LevelError> {
+        Self::load_from_svg_str_with_tolerance(content, DEFAULT_CURVE_TOLERANCE)
+    }
+
+    /// Like [`Level::load_from_svg_str`], but curves are flattened so that no point of the
+    /// resulting polygon is further than `tolerance` SVG units away from the real curve.
+    pub fn load_from_svg_str_with_tolerance(
+        content: &str,
+        tolerance: f32,
+    ) -> Result<Level, LevelError> {
+        let parser = svg::read(content).map_err(|e| LevelError::Xml {
+            message: e.to_string(),
+        })?;
+        Self::parse(parser, tolerance)
+    }
+
+    fn parse(parser: svg::parser::Parser, tolerance: f32) -> Result<Level, LevelError> {
+        let mut level_shapes: Vec<Shape> = vec![];
+        let mut metadata = LevelMetadata::default();
+        let mut player = None;
+        let mut warnings = vec![];
+        let mut colliders = vec![];
+        let mut water_volumes = vec![];
+        let mut fish_spawns = vec![];
+        let mut item_spawns = vec![];
+        let mut camera_bounds = None;
+        let mut triggers = vec![];
+        let mut exits = vec![];
+        let mut layers: Vec<RenderLayer> = vec![];
+        // the layer for content outside of Inkscape layers, until the next Inkscape layer
+        let mut open_layer: Option<usize> = None;
+        let mut gradients: HashMap<String, GradientElement> = HashMap::new();
+        // the gradient whose stops are being read, with its id
+        let mut open_gradient: Option<(Option<String>, GradientElement)> = None;
+        // the bottom of the stack is the document itself
+        let mut stack = vec![("", Inherited::default())];
+        // what percentages of stroke lengths are relative to, known once the root element is read
+        let mut viewport_diagonal = normalized_diagonal(DEFAULT_VIEWPORT);
+
+        // style sheets apply to the whole document, wherever they are
+        let events: Vec<Event> = parser.collect();
+        let sheet = style_sheet(&events);
+
+        for event in events {
+            let (name, kind, attributes) = match event {
+                Event::Tag(name, kind, attributes) => (name, kind, attributes),
+                Event::Error(e) => {
+                    return Err(LevelError::Xml {
+                        message: e.to_string(),
+                    })
+                }
+                _ => continue,
+            };
+
+            if kind == Type::End && (name == "linearGradient" || name == "radialGradient") {
+                if let Some((Some(id), gradient)) = open_gradient.take() {
+                    gradients.insert(id, gradient);
+                }
+            }
+
+            if kind == Type::End {
+                match stack.pop() {
+                    Some((open, _)) if open == name && !stack.is_empty() => continue,
+                    Some(("", _)) | None => {
+                        return Err(LevelError::Xml {
+                            message: format!("unexpected </{name}>"),
+                        })
+                    }
+                    Some((open, _)) => {
+                        return Err(LevelError::Xml {
+                            message: format!("expected </{open}>, found </{name}>"),
+                        })
                     }
                 }
-                Event::Tag("rect", _, attributes) => {
-                    let x = attributes.get("x").unwrap().parse::<f32>().unwrap();
-                    let y = attributes.get("y").unwrap().parse::<f32>().unwrap();
-                    let width = attributes.get("width").unwrap().parse::<f32>().unwrap();
-                    let height = attributes.get("height").unwrap().parse::<f32>().unwrap();
-
-                    let color = color_from_style(
-                        attributes.get("style").map(|v| Style::new(v.to_string())),
-                    )
-                    .unwrap_or([1.0, 0.0, 1.0, 1.0]);
+            }
 
-                    let mut wireframe = false;
+            let element = Element {
+                name,
+                attributes: &attributes,
+                declarations: sheet.declarations(
+                    name,
+                    attributes.get("id").map(|id| id.as_ref()),
+                    attributes.get("class").map(|class| class.as_ref()),
+                ),
+            };
 
-                    if let Some(id) = attributes.get("id").map(|v| v.to_string()) {
-                        if id == "player" {
-                            player_pos = (x, y);
-                            wireframe = true;
-                        } else if id.starts_with("hitbox") {
-                            hitbox = (x, y, width, height);
-                            wireframe = true;
+            let parent = stack.last().unwrap().1.clone();
+            let mut local_transform = element
+                .parsed("transform", Transform::parse)?
+                .unwrap_or_default();
+            // the root element carries the metadata, its scale applies to the whole level
+            if name == "svg" && stack.len() == 1 {
+                metadata = level_metadata(&element)?;
+                viewport_diagonal = normalized_diagonal(viewport_size(&element));
+                local_transform =
+                    Transform::scale(metadata.scale, metadata.scale).then(&local_transform);
+            }
+            let color = match element.property("color") {
+                Some(value) => match Paint::parse(value) {
+                    Some(Paint::Color(color)) => Some(color),
+                    Some(Paint::CurrentColor) => parent.color,
+                    _ => {
+                        return Err(element.invalid("color", format!("\"{value}\" is not a color")))
+                    }
+                },
+                None => parent.color,
+            };
+            let inherited = Inherited {
+                transform: parent.transform.then(&local_transform),
+                fill_rule: element
+                    .property("fill-rule")
+                    .and_then(FillRule::parse)
+                    .unwrap_or(parent.fill_rule),
+                fill: element.paint_property("fill")?.or(parent.fill),
+                fill_opacity: element
+                    .opacity_property("fill-opacity")?
+                    .unwrap_or(parent.fill_opacity),
+                stroke: element.paint_property("stroke")?.or(parent.stroke),
+                stroke_opacity: element
+                    .opacity_property("stroke-opacity")?
+                    .unwrap_or(parent.stroke_opacity),
+                stroke_style: StrokeStyle {
+                    width: element
+                        .length_property("stroke-width", viewport_diagonal)?
+                        .unwrap_or(parent.stroke_style.width),
+                    join: element
+                        .property("stroke-linejoin")
+                        .and_then(LineJoin::parse)
+                        .unwrap_or(parent.stroke_style.join),
+                    cap: element
+                        .property("stroke-linecap")
+                        .and_then(LineCap::parse)
+                        .unwrap_or(parent.stroke_style.cap),
+                    miter_limit: match element.property("stroke-miterlimit") {
+                        Some(value) => element.parse_number("stroke-miterlimit", value)?,
+                        None => parent.stroke_style.miter_limit,
+                    },
+                    dash_array: match element.property("stroke-dasharray") {
+                        Some(value) => {
+                            parse_dash_array(value, viewport_diagonal).ok_or_else(|| {
+                                element.invalid("stroke-dasharray", format!("\"{value}\""))
+                            })?
+                        }
+                        None => parent.stroke_style.dash_array.clone(),
+                    },
+                    dash_offset: element
+                        .length_property("stroke-dashoffset", viewport_diagonal)?
+                        .unwrap_or(parent.stroke_style.dash_offset),
+                },
+                color,
+                opacity: parent.opacity * element.opacity_property("opacity")?.unwrap_or(1.0),
+                hidden: parent.hidden || element.property("display").map(str::trim) == Some("none"),
+                layer: parent.layer,
+            };
+            let mut inherited = inherited;
+
+            // children of the root element are at the top of the stack
+            let is_layer = name == "g"
+                && stack.len() == 2
+                && element.attribute("inkscape:groupmode") == Some("layer");
+            if is_layer {
+                let (name, properties) =
+                    split_label(element.attribute("inkscape:label").unwrap_or_default());
+                layers.push(RenderLayer {
+                    name: Some(name.join(" ")).filter(|name| !name.is_empty()),
+                    parallax: element
+                        .label_property(&properties, "parallax", |value| value.parse::<f32>().ok())?
+                        .unwrap_or(1.0),
+                    z_index: element
+                        .label_property(&properties, "z-index", |value| value.parse::<i32>().ok())?
+                        .unwrap_or(0),
+                    triangles: vec![],
+                    elements: vec![],
+                });
+                inherited.layer = Some(layers.len() - 1);
+                open_layer = None;
+            }
+
+            if kind == Type::Start {
+                stack.push((name, inherited.clone()));
+            }
+
+            match name {
+                "linearGradient" | "radialGradient" => {
+                    let gradient = gradient_element(&element)?;
+                    match (kind, element.id()) {
+                        (Type::Start, id) => open_gradient = Some((id, gradient)),
+                        (_, Some(id)) => {
+                            gradients.insert(id, gradient);
                         }
+                        _ => {}
+                    }
+                    continue;
+                }
+                "stop" => {
+                    if let Some((_, gradient)) = open_gradient.as_mut() {
+                        gradient.stops.push(gradient_stop(&element, &inherited)?);
                     }
+                    continue;
+                }
+                _ => {}
+            }
+
+            // hidden gradients can still be used, but hidden shapes are not part of the level
+            if inherited.hidden || stack.iter().any(|(open, _)| NOT_RENDERED.contains(open)) {
+                continue;
+            }
+
+            // flatten in local coordinates, but keep the tolerance in world units
+            let scale = inherited.transform.scale_factor();
+            let local_tolerance = if scale > 0.0 {
+                tolerance / scale
+            } else {
+                tolerance
+            };
+
+            let mut subpaths: Vec<Subpath> = match name {
+                "path" => {
+                    let data =
+                        Data::parse(element.required("d")?).map_err(|e| element.invalid("d", e))?;
+
+                    flatten_path(&data, local_tolerance)
+                }
+                "rect" => shapes::rect(
+                    (
+                        element.number("x")?.unwrap_or(0.0),
+                        element.number("y")?.unwrap_or(0.0),
+                    ),
+                    element.required_number("width")?,
+                    element.required_number("height")?,
+                    element.number("rx")?,
+                    element.number("ry")?,
+                    local_tolerance,
+                )
+                .into_iter()
+                .collect(),
+                "circle" => shapes::circle(
+                    element.number("cx")?.unwrap_or(0.0),
+                    element.number("cy")?.unwrap_or(0.0),
+                    element.required_number("r")?,
+                    local_tolerance,
+                )
+                .into_iter()
+                .collect(),
+                "ellipse" => shapes::ellipse(
+                    element.number("cx")?.unwrap_or(0.0),
+                    element.number("cy")?.unwrap_or(0.0),
+                    element.required_number("rx")?,
+                    element.required_number("ry")?,
+                    local_tolerance,
+                )
+                .into_iter()
+                .collect(),
+                "line" => vec![shapes::line(
+                    (
+                        element.number("x1")?.unwrap_or(0.0),
+                        element.number("y1")?.unwrap_or(0.0),
+                    ),
+                    (
+                        element.number("x2")?.unwrap_or(0.0),
+                        element.number("y2")?.unwrap_or(0.0),
+                    ),
+                )],
+                "polygon" | "polyline" => {
+                    let value = element.required("points")?;
+                    let points = shapes::parse_points(value)
+                        .ok_or_else(|| element.invalid("points", format!("\"{value}\"")))?;
+
+                    shapes::poly(points, name == "polygon")
+                        .into_iter()
+                        .collect()
+                }
+                _ => continue,
+            };
+
+            // stroke in local coordinates, so the stroke width is transformed along with the path
+            let stroke = inherited.stroke_paint().map(|paint| {
+                let mut triangles =
+                    stroke::stroke(&subpaths, &inherited.stroke_style, local_tolerance);
+                for p in triangles.iter_mut().flatten() {
+                    *p = inherited.transform.apply(*p);
+                }
+                (paint, triangles)
+            });
+            let local_points: Vec<Point> = subpaths
+                .iter()
+                .flat_map(|subpath| subpath.points.iter().copied())
+                .collect();
+            let bounds = bounding_box(&local_points);
 
-                    let path = vec![
-                        // first triangle
-                        x,
-                        y,
-                        x + width,
-                        y,
-                        x + width,
-                        y + height,
-                        // second triangle
-                        x,
-                        y,
-                        x + width,
-                        y + height,
-                        x,
-                        y + height,
-                    ];
-
-                    polygons.borrow_mut().push((path, color, wireframe));
+            for subpath in subpaths.iter_mut() {
+                for p in subpath.points.iter_mut() {
+                    *p = inherited.transform.apply(*p);
+                }
+            }
+
+            let marker = element.marker()?;
+            let kind = marker.as_ref().map(|marker| marker.kind);
+            let points: Vec<Point> = subpaths
+                .iter()
+                .flat_map(|subpath| subpath.points.iter().copied())
+                .collect();
+            // the outer ring is the largest one, islands and holes are not part of an area
+            let outline = subpaths
+                .iter()
+                .max_by(|a, b| {
+                    signed_area(&a.points)
+                        .abs()
+                        .total_cmp(&signed_area(&b.points).abs())
+                })
+                .filter(|outline| outline.points.len() >= 3)
+                .map(|outline| outline.points.clone());
+
+            // markers are only drawn as wireframes, except for water which is part of the scenery
+            let wireframe = kind.map_or(false, |kind| kind != MarkerKind::Water);
+
+            match (&marker, outline.clone()) {
+                (Some(marker), _) if marker.kind == MarkerKind::Player && !points.is_empty() => {
+                    player = Some(bounding_box(&points));
+                }
+                (Some(marker), Some(area)) if marker.kind == MarkerKind::FishSpawn => {
+                    fish_spawns.push(FishSpawn {
+                        id: element.id(),
+                        area,
+                        race: element
+                            .label_property(&marker.properties, "race", FishRace::parse)?
+                            .unwrap_or(FishRace::Goldfish),
+                        density: element
+                            .label_property(&marker.properties, "density", |value| {
+                                value.parse::<f32>().ok().filter(|density| *density >= 0.0)
+                            })?
+                            .unwrap_or(DEFAULT_FISH_DENSITY),
+                    });
+                }
+                (Some(marker), _) if marker.kind == MarkerKind::ItemSpawn && !points.is_empty() => {
+                    let (x, y, width, height) = bounding_box(&points);
+                    item_spawns.push(ItemSpawn {
+                        id: element.id(),
+                        position: (x + width / 2.0, y + height / 2.0),
+                        item: element.label_property(&marker.properties, "item", |value| {
+                            Some(value.to_string())
+                        })?,
+                    });
+                }
+                (Some(marker), _)
+                    if marker.kind == MarkerKind::CameraBounds && !points.is_empty() =>
+                {
+                    camera_bounds = Some(bounding_box(&points));
+                }
+                (Some(marker), Some(area)) if marker.kind == MarkerKind::Trigger => {
+                    triggers.push(Trigger {
+                        id: element.id(),
+                        area,
+                        event: element.label_property(&marker.properties, "event", |value| {
+                            Some(value.to_string())
+                        })?,
+                    });
+                }
+                (Some(marker), Some(area)) if marker.kind == MarkerKind::Exit => {
+                    exits.push(Exit {
+                        id: element.id(),
+                        area,
+                        target: element.label_property(&marker.properties, "target", |value| {
+                            Some(value.to_string())
+                        })?,
+                    });
                 }
                 _ => {}
             }
+
+            let is_solid =
+                element.attribute("data-solid").is_some() || kind == Some(MarkerKind::Hitbox);
+            let mut empty_collider = false;
+            if is_solid {
+                let (closed, open): (Vec<Subpath>, Vec<Subpath>) =
+                    subpaths.iter().cloned().partition(|subpath| subpath.closed);
+                let collider = Collider {
+                    id: element.id(),
+                    triangles: triangulate(&closed, inherited.fill_rule).triangles,
+                    polylines: open.into_iter().map(|subpath| subpath.points).collect(),
+                };
+                empty_collider = collider.triangles.is_empty() && collider.polylines.is_empty();
+                if !empty_collider {
+                    colliders.push(collider);
+                }
+            }
+
+            let warning = |problem| LevelWarning {
+                element: name.to_string(),
+                id: element.id(),
+                problem,
+            };
+            let (_, _, width, height) = bounding_box(&points);
+            let zero_marker =
+                !is_solid && kind.is_some() && (points.is_empty() || width == 0.0 || height == 0.0);
+            if empty_collider || zero_marker {
+                warnings.push(warning(ElementProblem::ZeroSize));
+            }
+            let filled = is_solid || inherited.fill_paint().is_some();
+            let self_intersecting = subpaths
+                .iter()
+                .any(|subpath| subpath.closed && self_intersects(&subpath.points));
+            if filled && self_intersecting {
+                warnings.push(warning(ElementProblem::SelfIntersecting));
+            }
+
+            let is_water =
+                element.attribute("data-water").is_some() || kind == Some(MarkerKind::Water);
+            let water_surface = match outline {
+                Some(polygon) if is_water => {
+                    let surface = bounding_box(&polygon).1;
+                    water_volumes.push(WaterVolume {
+                        id: element.id(),
+                        polygon,
+                        surface,
+                    });
+                    Some(surface)
+                }
+                _ => None,
+            };
+
+            let layer = match (inherited.layer, open_layer) {
+                (Some(layer), _) | (None, Some(layer)) => layer,
+                (None, None) => {
+                    layers.push(RenderLayer::default());
+                    open_layer = Some(layers.len() - 1);
+                    layers.len() - 1
+                }
+            };
+
+            level_shapes.push(Shape {
+                element: name.to_string(),
+                id: element.id(),
+                subpaths,
+                fill_rule: inherited.fill_rule,
+                fill: match (water_surface, metadata.water_color) {
+                    (Some(_), Some(color)) if inherited.fill_paint().is_some() => {
+                        Some(ShapePaint::Color(color))
+                    }
+                    _ => inherited.fill_paint(),
+                },
+                stroke,
+                transform: inherited.transform,
+                bounds,
+                wireframe,
+                water_surface,
+                layer,
+            });
         }
 
-        let mut triangles: Vec<Triangle> = vec![];
+        if let Some((open, _)) = stack.get(1) {
+            return Err(LevelError::Xml {
+                message: format!("<{open}> is never closed"),
+            });
+        }
 
-        for (points, color, wireframe) in polygons.borrow().iter() {
-            // not even a triangle
-            if points.len() < 6 {
-                continue;
+        let mut water_volume = 0;
+        for shape in level_shapes.iter() {
+            let layer = &mut layers[shape.layer];
+            let first_triangle = layer.triangles.len();
+
+            // the stroke is painted on top of the fill
+            let mut lost_area = 0.0;
+            let fill = shape.fill.clone().map(|paint| {
+                let triangulation = triangulate(&shape.subpaths, shape.fill_rule);
+                lost_area = triangulation.lost_area;
+                (paint, triangulation.triangles)
+            });
+
+            let fill_shader = match shape.water_surface {
+                Some(surface) => ShaderId::Water { surface },
+                None => ShaderId::Default,
+            };
+            // the outline of a lake is not water itself
+            let paints = fill.iter().map(|fill| (fill, fill_shader.clone())).chain(
+                shape
+                    .stroke
+                    .iter()
+                    .map(|stroke| (stroke, ShaderId::Default)),
+            );
+
+            let warning = |problem| LevelWarning {
+                element: shape.element.clone(),
+                id: shape.id.clone(),
+                problem,
+            };
+            let has_area = shape
+                .subpaths
+                .iter()
+                .any(|subpath| subpath.points.len() >= 3);
+            if let Some((_, painted)) = &fill {
+                if has_area && painted.is_empty() {
+                    warnings.push(warning(ElementProblem::Degenerate));
+                }
+                let covered: f32 = painted
+                    .iter()
+                    .map(|triangle| signed_area(triangle) / 2.0)
+                    .sum();
+                if lost_area > (covered + lost_area) * LOST_AREA_TOLERANCE {
+                    warnings.push(warning(ElementProblem::LostArea { area: lost_area }));
+                }
             }
 
-            let path_triangles = earcutr::earcut(points.as_slice(), &vec![], 2);
-
-            for [a, b, c] in path_triangles.array_chunks::<3>() {
-                let mut triangle = Triangle {
-                    coords: [
-                        points[*a * 2] as f32,
-                        points[*a * 2 + 1] as f32,
-                        0.0 as f32,
-                        points[*b * 2] as f32,
-                        points[*b * 2 + 1] as f32,
-                        0.0 as f32,
-                        points[*c * 2] as f32,
-                        points[*c * 2 + 1] as f32,
-                        0.0 as f32,
-                    ],
-                    color: *color,
-                    shader_id: ShaderId::Default,
-                    wireframe: *wireframe,
+            let mut unpainted = false;
+            for ((paint, painted), shader_id) in paints {
+                let paint = match ResolvedPaint::resolve(paint, shape, &gradients) {
+                    Some(paint) => paint,
+                    None => continue,
                 };
+                // markers are meant to be seen only while debugging
+                if let ResolvedPaint::Unpainted { .. } = paint {
+                    unpainted |= !shape.wireframe && !painted.is_empty();
+                }
 
-                if triangle.color[0] < 0.0001 && triangle.color[1] < triangle.color[2] {
-                    triangle.shader_id = ShaderId::Water;
+                for ([a, b, c], colors) in painted.iter().flat_map(|t| paint.paint(*t)) {
+                    let mut triangle = Triangle {
+                        coords: [a.0, a.1, 0.0, b.0, b.1, 0.0, c.0, c.1, 0.0],
+                        color: colors[0],
+                        shader_id: shader_id.clone(),
+                        wireframe: shape.wireframe,
+                        vertex_colors: None,
+                    };
+
+                    if let ResolvedPaint::Gradient { .. } = paint {
+                        for (i, channel) in triangle.color.iter_mut().enumerate() {
+                            *channel = colors.iter().map(|color| color[i]).sum::<f32>() / 3.0;
+                        }
+                        triangle.vertex_colors = Some(colors);
+                    }
+
+                    layer.triangles.push(triangle);
                 }
+            }
+            if unpainted {
+                warnings.push(warning(ElementProblem::Unpainted));
+            }
 
-                triangles.push(triangle);
+            let source = SourceElement {
+                element: shape.element.clone(),
+                id: shape.id.clone(),
+                triangles: layer.triangles.len() - first_triangle,
+                water_volume: shape.water_surface.map(|_| water_volume),
+            };
+            if source.water_volume.is_some() {
+                water_volume += 1;
+            }
+            if source.triangles > 0 {
+                layer.elements.push(source);
             }
         }
 
-        Level {
-            id: "level".to_string(),
-            triangles,
-            player_pos,
-            ground: hitbox,
-        }
+        // stable, so layers with the same z-index stay in document order
+        layers.sort_by_key(|layer| layer.z_index);
+
+        let level = Level {
+            id: String::new(),
+            camera_center: (0.0, 0.0),
+            warnings,
+            metadata,
+            layers,
+            mesh_version: 0,
+            player,
+            colliders,
+            water_volumes,
+            fish_spawns,
+            item_spawns,
+            camera_bounds,
+            triggers,
+            exits,
+        };
+        Ok(level.prepared())
+    }
+
+    // fills in what is not stored
+    fn prepared(mut self) -> Level {
+        self.id = "level".to_string();
+        self.camera_center = self.metadata.camera.center;
+        self.mesh_version = mesh::next_version();
+        self
+    }
+
+    pub fn metadata(&self) -> &LevelMetadata {
+        &self.metadata
+    }
+
+    /// Moves the parallax layers for a camera looking at `center`, the offsets are applied when
+    /// the layers are drawn. While the camera looks at the center of the level's camera framing,
+    /// all layers are where they are in the SVG.
+    pub fn follow_camera(&mut self, center: Point) {
+        self.camera_center = center;
+    }
+
+    // how far the camera moved a parallax layer
+    fn layer_offset(&self, layer: &RenderLayer) -> Point {
+        let start = self.metadata.camera.center;
+        (
+            (self.camera_center.0 - start.0) * (1.0 - layer.parallax),
+            (self.camera_center.1 - start.1) * (1.0 - layer.parallax),
+        )
     }
 
-    pub fn player_pos(&self) -> (f32, f32) {
-        self.player_pos
+    /// The layers in draw order, where the camera framing puts them.
+    pub fn layers(&self) -> &[RenderLayer] {
+        &self.layers
     }
 
-    pub fn ground(&self) -> (f32, f32, f32, f32) {
-        self.ground
+    pub fn water_volumes(&self) -> &[WaterVolume] {
+        &self.water_volumes
+    }
+
+    /// The top left corner of the player marker.
+    pub fn player_pos(&self) -> Option<Point> {
+        self.player.map(|(x, y, _, _)| (x, y))
+    }
+
+    /// The bounding box of the player marker, `(x, y, width, height)`.
+    pub fn player_bounds(&self) -> Option<(f32, f32, f32, f32)> {
+        self.player
+    }
+
+    /// Problems found while loading the level from SVG.
+    pub fn warnings(&self) -> &[LevelWarning] {
+        &self.warnings
+    }
+
+    pub fn colliders(&self) -> &[Collider] {
+        &self.colliders
+    }
+
+    pub fn fish_spawns(&self) -> &[FishSpawn] {
+        &self.fish_spawns
+    }
+
+    pub fn item_spawns(&self) -> &[ItemSpawn] {
+        &self.item_spawns
+    }
+
+    /// The area the camera should stay in, `(x, y, width, height)`.
+    pub fn camera_bounds(&self) -> Option<(f32, f32, f32, f32)> {
+        self.camera_bounds
+    }
+
+    pub fn triggers(&self) -> &[Trigger] {
+        &self.triggers
+    }
+
+    pub fn exits(&self) -> &[Exit] {
+        &self.exits
     }
 }
 
@@ -248,31 +1254,219 @@ impl Entity for Level {
     fn id(&self) -> &String {
         &self.id
     }
-    fn triangles(&self) -> &Vec<Triangle> {
-        &self.triangles
+    // drawn from its static meshes
+    fn triangles(&self) -> &[Triangle] {
+        &[]
+    }
+    fn update(&mut self, _time_passed: f32, game_state: &mut GameState) {
+        self.follow_camera(game_state.camera_center);
+    }
+    fn static_meshes(&self) -> Option<Vec<StaticMesh>> {
+        let meshes = self.layers.iter().map(|layer| StaticMesh {
+            version: self.mesh_version,
+            triangles: &layer.triangles,
+            offset: self.layer_offset(layer),
+            z_index: layer.z_index,
+        });
+        Some(meshes.collect())
+    }
+    fn export(&self, snapshot: &mut Snapshot) {
+        export::add_level(snapshot, self);
     }
 }
 
-fn color_from_style(style: Option<Style>) -> Option<[f32; 4]> {
-    let style = style?;
-    let style_str = style.to_string();
+/// Axis aligned bounds of `points` as `(x, y, width, height)`.
+fn bounding_box(points: &[(f32, f32)]) -> (f32, f32, f32, f32) {
+    let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+    let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for (x, y) in points {
+        min_x = min_x.min(*x);
+        min_y = min_y.min(*y);
+        max_x = max_x.max(*x);
+        max_y = max_y.max(*y);
+    }
+    (min_x, min_y, max_x - min_x, max_y - min_y)
+}
 
-    let re = Regex::new(r"fill:#([0-9a-z]{6})").unwrap();
+/// Collects the rules of all `<style>` elements.
+fn style_sheet(events: &[Event]) -> StyleSheet {
+    let mut content = String::new();
+    let mut in_style = false;
+    for event in events {
+        match event {
+            Event::Tag("style", kind, _) => in_style = *kind == Type::Start,
+            // the parser splits CDATA sections into declarations and text
+            Event::Text(text) | Event::Declaration(text) if in_style => content.push_str(text),
+            _ => {}
+        }
+    }
+    StyleSheet::parse(&content.replace("<![CDATA[", "").replace("]]>", ""))
+}
+
+/// Returns the value of a CSS property from a `style` attribute like `fill:#ff0000;stroke:none`.
+fn style_property<'a>(style: &'a str, name: &str) -> Option<&'a str> {
+    style.split(';').rev().find_map(|declaration| {
+        let (key, value) = declaration.split_once(':')?;
+        if key.trim() == name {
+            Some(value.trim())
+        } else {
+            None
+        }
+    })
+}
+
+/// Reads the `data-` attributes of the root element, see [`crate::metadata`].
+fn level_metadata(element: &Element) -> Result<LevelMetadata, LevelError> {
+    let defaults = LevelMetadata::default();
+    let scale = element
+        .parsed("data-scale", |value| {
+            value
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|scale| *scale > 0.0)
+        })?
+        .unwrap_or(defaults.scale);
+    let camera = element
+        .parsed("data-camera", CameraFraming::parse)?
+        .unwrap_or(defaults.camera);
+
+    Ok(LevelMetadata {
+        name: element.attribute("data-name").map(|name| name.to_string()),
+        author: element
+            .attribute("data-author")
+            .map(|author| author.to_string()),
+        gravity: element
+            .parsed("data-gravity", parse_vector)?
+            .unwrap_or(defaults.gravity),
+        scale,
+        clear_color: element
+            .parsed("data-clear-color", parse_color)?
+            .unwrap_or(defaults.clear_color),
+        water_color: element.parsed("data-water-color", parse_color)?,
+        camera: CameraFraming {
+            center: (camera.center.0 * scale, camera.center.1 * scale),
+            width: camera.width * scale,
+        },
+    })
+}
+
+/// The size of the viewport the root element establishes, from its `viewBox` or its `width`
+/// and `height`.
+fn viewport_size(element: &Element) -> (f32, f32) {
+    let view_box = element.attribute("viewBox").and_then(|value| {
+        let numbers = value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|number| !number.is_empty())
+            .map(|number| number.parse::<f32>().ok())
+            .collect::<Option<Vec<f32>>>()?;
+        match numbers[..] {
+            [_, _, width, height] if width > 0.0 && height > 0.0 => Some((width, height)),
+            _ => None,
+        }
+    });
+    // percentages of the surrounding page are no use, they parse as 0
+    let size = |name| {
+        element
+            .attribute(name)
+            .and_then(|value| parse_length(value, 0.0))
+            .filter(|size| *size > 0.0)
+    };
+    view_box
+        .or_else(|| Some((size("width")?, size("height")?)))
+        .unwrap_or(DEFAULT_VIEWPORT)
+}
 
-    let rgb_str = re.captures(&style_str)?.get(1)?.as_str();
+/// What percentages of lengths that are neither horizontal nor vertical, like stroke widths,
+/// are relative to.
+///
+/// See https://www.w3.org/TR/SVG/coords.html#Units
+fn normalized_diagonal((width, height): (f32, f32)) -> f32 {
+    ((width * width + height * height) / 2.0).sqrt()
+}
 
-    let rgb = hex::decode(&rgb_str.as_bytes()).unwrap();
-    let vals: Vec<f32> = rgb.into_iter().map(|v| v as f32 / 255.0).collect();
+/// Reads the attributes of a `<linearGradient>` or `<radialGradient>`, its stops follow as
+/// child elements.
+fn gradient_element(element: &Element) -> Result<GradientElement, LevelError> {
+    let radial = element.name == "radialGradient";
 
-    let mut out = [1.0; 4];
-    for i in 0..3 {
-        out[i] = vals[i];
+    let mut coordinates = HashMap::new();
+    let names: &[&'static str] = if radial {
+        &RADIAL_GRADIENT_COORDINATES
+    } else {
+        &LINEAR_GRADIENT_COORDINATES
+    };
+    for name in names {
+        if let Some(value) = element.attribute(name) {
+            // percentages are fractions, like numbers in bounding box units
+            let coordinate = parse_length(value, 1.0)
+                .ok_or_else(|| element.invalid(name, format!("\"{value}\" is not a length")))?;
+            coordinates.insert(*name, coordinate);
+        }
     }
-    Some(out)
+
+    Ok(GradientElement {
+        radial,
+        href: element
+            .attribute("href")
+            .or_else(|| element.attribute("xlink:href"))
+            .and_then(|href| href.trim().strip_prefix('#'))
+            .map(|id| id.to_string()),
+        units: element.parsed("gradientUnits", GradientUnits::parse)?,
+        transform: element.parsed("gradientTransform", Transform::parse)?,
+        spread: element.parsed("spreadMethod", Spread::parse)?,
+        coordinates,
+        stops: vec![],
+    })
+}
+
+fn gradient_stop(element: &Element, inherited: &Inherited) -> Result<GradientStop, LevelError> {
+    let offset = match element.attribute("offset") {
+        Some(value) => parse_opacity(value)
+            .ok_or_else(|| element.invalid("offset", format!("\"{value}\" is not an offset")))?,
+        None => 0.0,
+    };
+    let mut color = match element.paint_property("stop-color")? {
+        Some(Paint::Color(color)) => color,
+        Some(Paint::CurrentColor) => inherited.color.unwrap_or(UNPAINTED),
+        Some(_) => {
+            return Err(element.invalid("stop-color", "only colors are allowed"));
+        }
+        None => [0.0, 0.0, 0.0, 1.0],
+    };
+    color[3] *= element.opacity_property("stop-opacity")?.unwrap_or(1.0);
+
+    Ok(GradientStop { offset, color })
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::fish::FishRace;
+    use crate::markup::{ItemSpawn, Trigger, DEFAULT_FISH_DENSITY};
+    use crate::metadata::{CameraFraming, LevelMetadata};
+    use crate::types::{Entity, ShaderId};
+
+    // the triangles of all layers, in draw order
+    fn all_triangles(level: &super::Level) -> Vec<crate::types::Triangle> {
+        level
+            .layers
+            .iter()
+            .flat_map(|layer| layer.triangles.clone())
+            .collect()
+    }
+
+    fn path_triangles(d: &str) -> Vec<[f32; 9]> {
+        let content = format!(
+            r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <path d="{d}" style="fill:#00ff00"/>
+</svg>"#
+        );
+
+        let level = super::Level::load_from_svg_str_with_tolerance(&content, 0.5).unwrap();
+
+        all_triangles(&level).iter().map(|t| t.coords).collect()
+    }
+
     #[test]
     fn example() {
         let content = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
@@ -281,10 +1475,9 @@ mod tests {
     <path d="M49,144L141,146L152,173L172,187L207,191L241,186L255,165L259,147L292,147L289,268L42,274L49,144Z" style="fill:none;stroke:black;stroke-width:1px;"/>
 </svg>"#;
 
-        let level = super::Level::load_from_svg_str(content);
+        let level = super::Level::load_from_svg_str(content).unwrap();
 
-        insta::assert_debug_snapshot!(&level
-            .triangles
+        insta::assert_debug_snapshot!(&all_triangles(&level)
             .iter()
             .map(|t| t.coords)
             .collect::<Vec<_>>());
@@ -294,12 +1487,654 @@ mod tests {
     fn bigger_example() {
         let content = include_str!("../test_data/map1.svg");
 
-        let level = super::Level::load_from_svg_str(content);
+        let level = super::Level::load_from_svg_str(content).unwrap();
+
+        insta::assert_debug_snapshot!(&all_triangles(&level)
+            .iter()
+            .map(|t| t.coords)
+            .collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cubic_curve() {
+        insta::assert_debug_snapshot!(path_triangles("M10,50 C10,10 90,10 90,50 Z"));
+    }
+
+    #[test]
+    fn smooth_cubic_curve() {
+        insta::assert_debug_snapshot!(path_triangles(
+            "M10,50 C10,20 40,20 40,50 s30,30 30,0 L70,90 L10,90 Z"
+        ));
+    }
+
+    #[test]
+    fn quadratic_curve() {
+        insta::assert_debug_snapshot!(path_triangles("M10,50 Q50,10 90,50 Z"));
+    }
+
+    #[test]
+    fn smooth_quadratic_curve() {
+        insta::assert_debug_snapshot!(path_triangles("M10,50 Q30,20 50,50 t40,0 L90,90 L10,90 Z"));
+    }
+
+    #[test]
+    fn elliptical_arc() {
+        insta::assert_debug_snapshot!(path_triangles("M10,50 A40,20 30 1 1 90,50 Z"));
+    }
+
+    #[test]
+    fn holes() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg" style="fill-rule:evenodd">
+    <path d="M0,0 H10 V10 H0 Z M2,2 H8 V8 H2 Z" style="fill:#00ff00"/>
+    <path d="M20,0 H30 V10 H20 Z M22,2 V8 H28 V2 Z" fill-rule="nonzero" style="fill:#00ff00"/>
+</svg>"#;
 
-        insta::assert_debug_snapshot!(&level
+        let level = super::Level::load_from_svg_str(content).unwrap();
+
+        insta::assert_debug_snapshot!(&all_triangles(&level)
+            .iter()
+            .map(|t| t.coords)
+            .collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn transforms() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <g transform="translate(10,20)">
+        <g transform="scale(2)">
+            <path d="M0,0 H1 V1 Z" transform="rotate(90)" style="fill:#00ff00"/>
+            <rect id="player" x="1" y="1" width="1" height="2"/>
+        </g>
+        <rect id="hitbox" x="0" y="0" width="4" height="2" transform="skewX(45)"/>
+    </g>
+    <rect x="0" y="0" width="1" height="1" transform="matrix(1 0 0 1 -5 -5)" style="fill:#00ff00"/>
+</svg>"#;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+
+        assert_eq!(level.player_pos(), Some((12.0, 22.0)));
+        let hitbox: Vec<(f32, f32)> = level.colliders()[0]
             .triangles
+            .iter()
+            .flatten()
+            .copied()
+            .collect();
+        assert_eq!(super::bounding_box(&hitbox), (10.0, 20.0, 6.0, 2.0));
+        insta::assert_debug_snapshot!(&all_triangles(&level)
+            .iter()
+            .map(|t| t.coords)
+            .collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn basic_shapes() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <circle cx="10" cy="10" r="2" style="fill:#00ff00"/>
+    <ellipse cx="20" cy="10" rx="3" ry="1" style="fill:#00ff00"/>
+    <polygon points="30,10 35,10 35,15" style="fill:#00ff00"/>
+    <polyline points="40,10 45,10 45,15 40,15" style="fill:#00ff00"/>
+    <line x1="50" y1="10" x2="60" y2="20" style="fill:#00ff00"/>
+    <rect x="60" y="10" width="10" height="4" rx="1" style="fill:#00ff00"/>
+</svg>"#;
+
+        let level = super::Level::load_from_svg_str_with_tolerance(content, 0.1).unwrap();
+
+        insta::assert_debug_snapshot!(&all_triangles(&level)
             .iter()
             .map(|t| t.coords)
             .collect::<Vec<_>>());
     }
+
+    #[test]
+    fn fills() {
+        let content = r##"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <rect width="1" height="1" style="fill:#00FF00"/>
+    <rect width="1" height="1" fill="rgb(255, 0, 0)" style="fill-opacity:0.5"/>
+    <g style="opacity:0.5" fill="navy">
+        <rect width="1" height="1" opacity="0.5"/>
+        <rect width="1" height="1" style="fill:hsl(0, 100%, 50%);fill-opacity:50%"/>
+        <rect width="1" height="1" fill="#0f08" style="fill:#00f"/>
+    </g>
+    <rect width="1" height="1" color="#f00" fill="currentColor"/>
+    <rect width="1" height="1" style="fill:none"/>
+    <rect width="1" height="1"/>
+</svg>"##;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+        let mut colors: Vec<[f32; 4]> = all_triangles(&level).iter().map(|t| t.color).collect();
+        colors.dedup();
+
+        assert_eq!(
+            colors,
+            vec![
+                [0.0, 1.0, 0.0, 1.0],
+                [1.0, 0.0, 0.0, 0.5],
+                [0.0, 0.0, 128.0 / 255.0, 0.25],
+                [1.0, 0.0, 0.0, 0.25],
+                [0.0, 0.0, 1.0, 0.5],
+                [1.0, 0.0, 0.0, 1.0],
+                [1.0, 0.0, 1.0, 1.0],
+            ]
+        );
+    }
+
+    #[test]
+    fn strokes() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <g style="stroke:blue;stroke-width:2px" transform="scale(2)">
+        <line x1="0" y1="0" x2="10" y2="0"/>
+        <line x1="0" y1="0" x2="10" y2="0" style="stroke-dasharray:2,3"/>
+    </g>
+    <rect width="4" height="4" style="fill:#0f0;stroke:red;stroke-opacity:0.5;stroke-linejoin:bevel"/>
+</svg>"#;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+        let area = |color: [f32; 4]| -> f32 {
+            all_triangles(&level)
+                .iter()
+                .filter(|t| t.color == color)
+                .map(|t| {
+                    let c = t.coords;
+                    ((c[3] - c[0]) * (c[7] - c[1]) - (c[6] - c[0]) * (c[4] - c[1])).abs() / 2.0
+                })
+                .sum()
+        };
+
+        // 20 long and 4 wide after scaling, plus two dashes of 4
+        assert_eq!(area([0.0, 0.0, 1.0, 1.0]), 80.0 + 2.0 * 16.0);
+        assert_eq!(area([0.0, 1.0, 0.0, 1.0]), 16.0);
+        // 5x5 without the 3x3 inside and the 4 beveled corners, with nothing covered twice
+        assert_eq!(area([1.0, 0.0, 0.0, 0.5]), 25.0 - 9.0 - 4.0 * 0.125);
+        // the fill is drawn before its stroke
+        assert_eq!(
+            all_triangles(&level).last().unwrap().color,
+            [1.0, 0.0, 0.0, 0.5]
+        );
+    }
+
+    #[test]
+    fn stroke_units() {
+        let content = r#"<svg viewBox="0 0 30 40" xmlns="http://www.w3.org/2000/svg">
+    <line x1="0" y1="0" x2="10" y2="0" style="stroke:red;stroke-width:3pt"/>
+    <line x1="0" y1="0" x2="10" y2="0" style="stroke:lime;stroke-width:10%"/>
+    <line x1="0" y1="0" x2="10" y2="0" style="stroke:blue;stroke-width:0.26458333mm"/>
+</svg>"#;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+        let width = |color: [f32; 4]| -> f32 {
+            let area: f32 = all_triangles(&level)
+                .iter()
+                .filter(|t| t.color == color)
+                .map(|t| {
+                    let c = t.coords;
+                    ((c[3] - c[0]) * (c[7] - c[1]) - (c[6] - c[0]) * (c[4] - c[1])).abs() / 2.0
+                })
+                .sum();
+            area / 10.0
+        };
+
+        assert_eq!(width([1.0, 0.0, 0.0, 1.0]), 4.0);
+        // of the diagonal of the view box divided by sqrt(2)
+        assert!((width([0.0, 1.0, 0.0, 1.0]) - 3.5355).abs() < 0.001);
+        // Inkscape's default stroke width
+        assert!((width([0.0, 0.0, 1.0, 1.0]) - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn gradients() {
+        let content = r##"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+    <defs>
+        <linearGradient id="black-to-white">
+            <stop offset="0" style="stop-color:black"/>
+            <stop offset="100%" stop-color="white" stop-opacity="0.5"/>
+        </linearGradient>
+        <rect id="not-rendered" width="100" height="100" fill="red"/>
+    </defs>
+    <rect x="10" y="0" width="10" height="10" fill="url(#black-to-white)"/>
+    <rect x="0" y="20" width="10" height="10" fill="url(#vertical)"/>
+    <rect x="0" y="40" width="10" height="10" fill="url(#missing)"/>
+    <linearGradient id="vertical" xlink:href="#black-to-white" gradientUnits="userSpaceOnUse"
+        x1="0" y1="0" x2="10" y2="0" gradientTransform="translate(0,20) rotate(90)"/>
+</svg>"##;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+        assert_eq!(all_triangles(&level).len(), 6);
+
+        let black = [0.0, 0.0, 0.0, 1.0];
+        let white = [1.0, 1.0, 1.0, 0.5];
+        for (i, triangle) in all_triangles(&level).iter().enumerate() {
+            for (corner, color) in triangle.vertex_colors().iter().enumerate() {
+                let (x, y) = (triangle.coords[corner * 3], triangle.coords[corner * 3 + 1]);
+                let expected = match i / 2 {
+                    0 if x == 10.0 => black,
+                    0 => white,
+                    1 if y == 20.0 => black,
+                    1 => white,
+                    _ => [1.0, 0.0, 1.0, 1.0],
+                };
+                assert_eq!(*color, expected, "{x}, {y}");
+            }
+        }
+        assert!(all_triangles(&level)[4].vertex_colors.is_none());
+    }
+
+    // the color the triangles interpolate at `point`
+    fn interpolated_color(level: &super::Level, (x, y): (f32, f32)) -> [f32; 4] {
+        for triangle in all_triangles(&level).iter() {
+            let c = triangle.coords;
+            let (ax, ay, bx, by, cx, cy) = (c[0], c[1], c[3], c[4], c[6], c[7]);
+            let area = (bx - ax) * (cy - ay) - (cx - ax) * (by - ay);
+            let u = ((bx - x) * (cy - y) - (cx - x) * (by - y)) / area;
+            let v = ((cx - x) * (ay - y) - (ax - x) * (cy - y)) / area;
+            let w = 1.0 - u - v;
+            if u < 0.0 || v < 0.0 || w < 0.0 {
+                continue;
+            }
+            let colors = triangle.vertex_colors();
+            let mut color = [0.0; 4];
+            for (i, channel) in color.iter_mut().enumerate() {
+                *channel = u * colors[0][i] + v * colors[1][i] + w * colors[2][i];
+            }
+            return color;
+        }
+        panic!("nothing at {x}, {y}");
+    }
+
+    #[test]
+    fn gradient_interiors() {
+        let content = r##"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <linearGradient id="fire">
+        <stop offset="0" stop-color="black"/>
+        <stop offset="0.5" stop-color="red"/>
+        <stop offset="1" stop-color="white"/>
+    </linearGradient>
+    <radialGradient id="sun">
+        <stop offset="0" stop-color="white"/>
+        <stop offset="1" stop-color="black"/>
+    </radialGradient>
+    <rect x="0" y="0" width="10" height="10" fill="url(#fire)"/>
+    <rect x="20" y="0" width="10" height="10" fill="url(#sun)"/>
+</svg>"##;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+        let assert_color = |point: (f32, f32), expected: [f32; 4]| {
+            let actual = interpolated_color(&level, point);
+            assert!(
+                actual
+                    .iter()
+                    .zip(expected)
+                    .all(|(a, b)| (a - b).abs() < 0.02),
+                "{actual:?} != {expected:?} at {point:?}"
+            );
+        };
+
+        // the middle stop is not skipped
+        assert_color((2.5, 3.0), [0.5, 0.0, 0.0, 1.0]);
+        assert_color((5.0, 9.0), [1.0, 0.0, 0.0, 1.0]);
+        assert_color((7.5, 1.0), [1.0, 0.5, 0.5, 1.0]);
+
+        // the circle is not just the corners of the rect
+        assert_color((25.0, 5.0), [1.0, 1.0, 1.0, 1.0]);
+        assert_color((27.5, 5.0), [0.5, 0.5, 0.5, 1.0]);
+        assert_color((25.0, 1.0), [0.2, 0.2, 0.2, 1.0]);
+        assert_color((21.0, 1.0), [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn water_volumes() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <path id="water-lake" d="M0,10 H10 V20 H0 Z M4,12 H6 V14 H4 Z" fill-rule="evenodd" fill="rgb(0, 0, 128)" stroke="black"/>
+    <g transform="translate(0, 5)">
+        <rect data-water="" x="20" y="10" width="5" height="5" fill="teal"/>
+    </g>
+    <rect id="blue-rock" x="40" y="10" width="5" height="5" fill="blue"/>
+</svg>"#;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+
+        assert_eq!(
+            level.water_volumes(),
+            &[
+                super::WaterVolume {
+                    id: Some("water-lake".to_string()),
+                    polygon: vec![(0.0, 10.0), (10.0, 10.0), (10.0, 20.0), (0.0, 20.0)],
+                    surface: 10.0,
+                },
+                super::WaterVolume {
+                    id: None,
+                    polygon: vec![(20.0, 15.0), (25.0, 15.0), (25.0, 20.0), (20.0, 20.0)],
+                    surface: 15.0,
+                },
+            ]
+        );
+
+        let shader_ids: Vec<ShaderId> = all_triangles(&level)
+            .iter()
+            .map(|t| t.shader_id.clone())
+            .collect();
+        let lake = shader_ids
+            .iter()
+            .filter(|id| **id == ShaderId::Water { surface: 10.0 });
+        let pond = shader_ids
+            .iter()
+            .filter(|id| **id == ShaderId::Water { surface: 15.0 });
+        // the lake has a hole, its outline and the rock are drawn normally
+        assert_eq!(lake.count(), 8);
+        assert_eq!(pond.count(), 2);
+        assert!(all_triangles(&level)
+            .iter()
+            .filter(|t| t.color == [0.0, 0.0, 0.0, 1.0] || t.color == [0.0, 0.0, 1.0, 1.0])
+            .all(|t| t.shader_id == ShaderId::Default));
+    }
+
+    #[test]
+    fn colliders() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <rect id="hitbox-1" x="0" y="0" width="4" height="2"/>
+    <rect id="hitbox-2" x="10" y="0" width="4" height="2"/>
+    <path id="terrain" data-solid="" d="M0,10 L10,5 L20,10 Z M30,10 L40,5"/>
+    <path id="decoration" d="M0,20 H10 V30 Z"/>
+</svg>"#;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+        let colliders = level.colliders();
+
+        assert_eq!(colliders.len(), 3);
+        assert_eq!(colliders[0].id.as_deref(), Some("hitbox-1"));
+        assert_eq!(colliders[0].triangles.len(), 2);
+        assert_eq!(colliders[1].id.as_deref(), Some("hitbox-2"));
+        assert_eq!(colliders[2].id.as_deref(), Some("terrain"));
+        assert_eq!(colliders[2].triangles.len(), 1);
+        assert_eq!(
+            colliders[2].polylines,
+            vec![vec![(30.0, 10.0), (40.0, 5.0)]]
+        );
+    }
+
+    #[test]
+    fn markers() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg"
+        xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">
+    <rect id="rect12" inkscape:label="fish-spawn race=eel density=0.5" x="0" y="0" width="4" height="2"/>
+    <rect id="fish-spawn-deep" data-race="whale" inkscape:label="fish-spawn race=eel" x="0" y="10" width="4" height="2"/>
+    <rect data-marker="item-spawn" data-item="worm" x="19" y="4" width="2" height="2"/>
+    <rect inkscape:label="camera-bounds" x="-10" y="-10" width="120" height="60"/>
+    <g transform="translate(50, 0)">
+        <rect id="trigger-shop" data-event="open-shop" x="0" y="0" width="5" height="5"/>
+    </g>
+    <rect id="exit" data-target="ocean" x="90" y="0" width="5" height="5"/>
+    <rect id="lake" inkscape:label="Lake" x="0" y="20" width="10" height="10" fill="blue"/>
+</svg>"#;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+
+        let spawns = level.fish_spawns();
+        assert_eq!(spawns.len(), 2);
+        assert_eq!(spawns[0].id.as_deref(), Some("rect12"));
+        assert_eq!(spawns[0].race, FishRace::Eel);
+        assert_eq!(spawns[0].density, 0.5);
+        assert_eq!(spawns[0].fish_count(), 4);
+        assert_eq!(spawns[1].race, FishRace::Whale);
+        assert_eq!(spawns[1].density, DEFAULT_FISH_DENSITY);
+
+        assert_eq!(
+            level.item_spawns(),
+            &[ItemSpawn {
+                id: None,
+                position: (20.0, 5.0),
+                item: Some("worm".to_string()),
+            }]
+        );
+        assert_eq!(level.camera_bounds(), Some((-10.0, -10.0, 120.0, 60.0)));
+        assert_eq!(
+            level.triggers(),
+            &[Trigger {
+                id: Some("trigger-shop".to_string()),
+                area: vec![(50.0, 0.0), (55.0, 0.0), (55.0, 5.0), (50.0, 5.0)],
+                event: Some("open-shop".to_string()),
+            }]
+        );
+        assert_eq!(level.exits()[0].target.as_deref(), Some("ocean"));
+
+        // only the lake is drawn normally, the markers are wireframes
+        let (wireframes, drawn): (Vec<_>, Vec<_>) =
+            all_triangles(&level).into_iter().partition(|t| t.wireframe);
+        assert_eq!(drawn.len(), 2);
+        assert!(!wireframes.is_empty());
+    }
+
+    #[test]
+    fn metadata() {
+        let content = r##"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg"
+        data-name="Pond" data-author="nuuls" data-gravity="1, 5" data-scale="0.5"
+        data-clear-color="#000" data-water-color="rgba(0, 0, 255, 0.5)" data-camera="20 10 40">
+    <rect id="player" x="10" y="4" width="1" height="2"/>
+    <rect id="water" x="0" y="10" width="10" height="10" fill="teal"/>
+</svg>"##;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+
+        assert_eq!(
+            level.metadata(),
+            &LevelMetadata {
+                name: Some("Pond".to_string()),
+                author: Some("nuuls".to_string()),
+                gravity: (1.0, 5.0),
+                scale: 0.5,
+                clear_color: [0.0, 0.0, 0.0, 1.0],
+                water_color: Some([0.0, 0.0, 1.0, 0.5]),
+                camera: CameraFraming {
+                    center: (10.0, 5.0),
+                    width: 20.0,
+                },
+            }
+        );
+        // the scale applies to all of the level
+        assert_eq!(level.player_pos(), Some((5.0, 2.0)));
+        assert_eq!(level.water_volumes()[0].surface, 5.0);
+        assert!(all_triangles(&level)
+            .iter()
+            .filter(|t| t.shader_id != ShaderId::Default)
+            .all(|t| t.color == [0.0, 0.0, 1.0, 0.5]));
+
+        let defaults = super::Level::load_from_svg_str(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g data-scale="2"/></svg>"#,
+        )
+        .unwrap();
+        assert_eq!(defaults.metadata(), &LevelMetadata::default());
+    }
+
+    #[test]
+    fn hidden_elements_and_style_sheets() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <rect class="ground" x="0" y="0" width="1" height="1"/>
+    <defs>
+        <style type="text/css"><![CDATA[
+            .ground { fill: #00ff00 }
+            rect.rock, #stone { fill: gray; opacity: 0.5 }
+            .outlined { stroke: black }
+        ]]></style>
+    </defs>
+    <style>.sketch { fill: red }</style>
+    <g style="display:none">
+        <rect class="sketch" x="10" y="0" width="1" height="1"/>
+        <rect id="hitbox" style="display:inline" x="10" y="0" width="1" height="1"/>
+    </g>
+    <rect class="sketch" display="none" x="20" y="0" width="1" height="1"/>
+    <rect class="rock outlined" fill="blue" x="30" y="0" width="1" height="1"/>
+    <rect class="rock" style="fill:white" x="40" y="0" width="1" height="1"/>
+</svg>"#;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+        let colors: Vec<[f32; 4]> = all_triangles(&level).iter().map(|t| t.color).collect();
+
+        let gray = [128.0 / 255.0, 128.0 / 255.0, 128.0 / 255.0, 0.5];
+        assert_eq!(
+            colors,
+            [
+                [[0.0, 1.0, 0.0, 1.0]; 2].as_slice(),
+                &[gray; 2],
+                // the stroke is as wide as the rect
+                &[[0.0, 0.0, 0.0, 0.5]; 2],
+                &[[1.0, 1.0, 1.0, 0.5]; 2],
+            ]
+            .concat()
+        );
+        assert!(level.colliders().is_empty());
+    }
+
+    #[test]
+    fn layers() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg"
+        xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" data-camera="0 0 40">
+    <rect x="0" y="0" width="1" height="1" fill="white"/>
+    <g inkscape:groupmode="layer" inkscape:label="Water z-index=1">
+        <rect id="water" x="0" y="10" width="1" height="1" fill="blue"/>
+    </g>
+    <g inkscape:groupmode="layer" inkscape:label="Terrain">
+        <g inkscape:groupmode="layer" inkscape:label="Rocks z-index=5">
+            <rect x="0" y="0" width="1" height="1" fill="gray"/>
+        </g>
+    </g>
+    <g inkscape:groupmode="layer" inkscape:label="Sky" data-parallax="0.5" data-z-index="-1">
+        <rect x="0" y="0" width="1" height="1" fill="black"/>
+    </g>
+    <rect x="0" y="0" width="1" height="1" fill="white"/>
+</svg>"#;
+
+        let mut level = super::Level::load_from_svg_str(content).unwrap();
+
+        let layers: Vec<(Option<&str>, f32, i32, usize)> = level
+            .layers
+            .iter()
+            .map(|layer| {
+                let name = layer.name.as_deref();
+                (name, layer.parallax, layer.z_index, layer.triangles.len())
+            })
+            .collect();
+        assert_eq!(
+            layers,
+            vec![
+                (Some("Sky"), 0.5, -1, 2),
+                (None, 1.0, 0, 2),
+                (Some("Terrain"), 1.0, 0, 2),
+                (None, 1.0, 0, 2),
+                (Some("Water"), 1.0, 1, 2),
+            ]
+        );
+        assert_eq!(all_triangles(&level).len(), 10);
+
+        // the sky moves half as far as the camera, everything else stays
+        level.follow_camera((10.0, -4.0));
+        let offsets: Vec<(f32, f32)> = Entity::static_meshes(&level)
+            .unwrap()
+            .iter()
+            .map(|mesh| mesh.offset)
+            .collect();
+        assert_eq!(
+            offsets,
+            vec![(5.0, -2.0), (0.0, 0.0), (0.0, 0.0), (0.0, 0.0), (0.0, 0.0)]
+        );
+
+        assert_eq!(
+            load_error(r#"<g inkscape:groupmode="layer" inkscape:label="Sky parallax=slow"></g>"#),
+            r#"<g> has an invalid "inkscape:label" attribute: "slow" is not a valid parallax"#
+        );
+    }
+
+    #[test]
+    fn ron() {
+        let content = r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg"
+        xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" data-name="Pond">
+    <g inkscape:groupmode="layer" inkscape:label="Sky parallax=0.5">
+        <rect x="0" y="0" width="1" height="1" fill="black"/>
+    </g>
+    <rect id="player" x="1" y="2" width="1" height="2" stroke="red"/>
+    <rect id="water" x="0" y="10" width="10" height="10" fill="teal"/>
+    <rect id="fish-spawn" data-race="eel" x="0" y="10" width="10" height="10"/>
+</svg>"#;
+
+        let level = super::Level::load_from_svg_str(content).unwrap();
+        let ron = level.to_ron().unwrap();
+        let loaded = super::Level::load(&ron).unwrap();
+
+        assert_eq!(loaded.to_ron().unwrap(), ron);
+        assert_eq!(loaded.id, "level");
+        assert_eq!(loaded.metadata(), level.metadata());
+        assert_eq!(loaded.player_pos(), Some((1.0, 2.0)));
+        assert_eq!(loaded.water_volumes(), level.water_volumes());
+        assert_eq!(loaded.fish_spawns()[0].race, FishRace::Eel);
+        assert_eq!(all_triangles(&loaded).len(), all_triangles(&level).len());
+        assert_eq!(loaded.layers[0].parallax, 0.5);
+
+        assert!(matches!(
+            super::Level::load("(layers: 3)"),
+            Err(super::LevelError::Ron { .. })
+        ));
+    }
+
+    #[test]
+    fn converted_map_is_up_to_date() {
+        let level = super::Level::load(include_str!("../assets/map.svg")).unwrap();
+        // run build.sh or the svg_to_level binary if this fails
+        assert!(level.to_ron().unwrap() == include_str!("../assets/map.ron"));
+    }
+
+    fn load_error(body: &str) -> String {
+        let content = format!(
+            r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">{body}</svg>"#
+        );
+
+        super::Level::load_from_svg_str(&content)
+            .err()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            load_error(r#"<rect id="hitbox-1" x="1" y="1" height="2"/>"#),
+            r#"<rect id="hitbox-1"> is missing the "width" attribute"#
+        );
+        assert_eq!(
+            load_error(r#"<rect id="fish-spawn" data-race="shark" width="1" height="1"/>"#),
+            r#"<rect id="fish-spawn"> has an invalid "data-race" attribute: "shark""#
+        );
+        assert_eq!(
+            load_error(r#"<rect data-marker="spawn" width="1" height="1"/>"#),
+            r#"<rect> has an invalid "data-marker" attribute: "spawn" is not a marker"#
+        );
+        assert_eq!(
+            super::Level::load_from_svg_str(r#"<svg data-gravity="down"/>"#)
+                .err()
+                .unwrap()
+                .to_string(),
+            r#"<svg> has an invalid "data-gravity" attribute: "down""#
+        );
+        assert_eq!(
+            load_error(r#"<rect x="one" y="1" width="1" height="2"/>"#),
+            r#"<rect> has an invalid "x" attribute: "one" is not a number"#
+        );
+        assert_eq!(
+            load_error(r#"<path id="terrain" d="M 0,0 L 1,1 X 5"/>"#),
+            r#"<path id="terrain"> has an invalid "d" attribute: found an unknown path command 'X' (line 1, column 15)"#
+        );
+        assert_eq!(
+            load_error(r##"<path id="rocks" d="M0,0 H1 V1 Z" style="fill:#zzzzzz"/>"##),
+            r##"<path id="rocks"> has an invalid "fill" attribute: "#zzzzzz" is not a color"##
+        );
+        assert_eq!(
+            load_error(r#"<g style="opacity:half"></g>"#),
+            r#"<g> has an invalid "opacity" attribute: "half" is not an opacity"#
+        );
+        assert_eq!(
+            load_error(r#"<g transform="rotate(1 2)"></g>"#),
+            r#"<g> has an invalid "transform" attribute: "rotate(1 2)""#
+        );
+        assert_eq!(
+            load_error(r#"<g><rect x="0" y="0" width="1" height="1"/>"#),
+            "malformed SVG: expected </g>, found </svg>"
+        );
+    }
 }
@@ -5,6 +5,8 @@ extern crate js_sys;
 extern crate mat4;
 extern crate wasm_bindgen;
 extern crate web_sys;
+use drawing::Backend;
+use drawing::DefaultShader;
 use drawing::Shader;
 use drawing::WaterShader;
 use std::cell::RefCell;
@@ -12,13 +14,32 @@ use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{EventTarget, WebGlBuffer, WebGlProgram, WebGlRenderingContext};
+mod camera;
+mod color;
+mod css;
 mod drawing;
+mod export;
 mod fish;
 mod fishing_rod;
 mod game;
-mod level;
+pub mod generator;
+mod gl;
+mod gradient;
+pub mod level;
+pub mod lint;
+mod markup;
+mod mesh;
+mod metadata;
 mod player;
+pub mod raster;
+#[cfg(test)]
+mod recording_gl;
+mod shapes;
 mod sick_physics;
+mod stroke;
+mod svg_path;
+mod transform;
+mod triangulation;
 mod types;
 mod user_input;
 use game::Game;
@@ -38,7 +59,6 @@ fn into_shader(gl: &WebGlRenderingContext, program: WebGlProgram) -> Shader {
     Shader {
         camera_index: gl.get_uniform_location(&program, "camera").unwrap(),
         transform_index: gl.get_uniform_location(&program, "transform").unwrap(),
-        color_index: gl.get_uniform_location(&program, "color").unwrap(),
         coordinate_index: gl.get_attrib_location(&program, "coordinates") as u32,
         program: program,
     }
@@ -78,26 +98,22 @@ pub fn start() -> Result<(), JsValue> {
     let dY = Rc::new(RefCell::new(0.0));
     let _canvas_width = Rc::new(RefCell::new(canvas.width() as f32));
     let _canvas_height = Rc::new(RefCell::new(canvas.height() as f32));
-    let mut renderer = drawing::Renderer {
-        coordinate_buffer: gl.create_buffer().ok_or("failed to create buffer")?,
-        index_buffer: gl.create_buffer().ok_or("failed to create buffer")?,
-        shader: into_shader(&gl, program),
-        water_shader: WaterShader {
-            time_index: gl.get_uniform_location(&water_program, "time").unwrap(),
-            water_y_level_index: gl
-                .get_uniform_location(&water_program, "water_y_level")
-                .unwrap(),
-            base: into_shader(&gl, water_program),
-        },
-        gl,
-        camera: mat4::new_identity(),
-        time: 0.0,
+    let shader = DefaultShader {
+        vertex_color_index: gl.get_attrib_location(&program, "vertex_color") as u32,
+        base: into_shader(&gl, program),
     };
+    let water_shader = WaterShader {
+        time_index: gl.get_uniform_location(&water_program, "time").unwrap(),
+        vertex_color_index: gl.get_attrib_location(&water_program, "vertex_color") as u32,
+        water_y_level_index: gl.get_attrib_location(&water_program, "water_y_level") as u32,
+        base: into_shader(&gl, water_program),
+    };
+    let mut backend = drawing::WebGlBackend::new(gl, shader, water_shader)?;
 
     // get canvas as event target
     let _event_target: EventTarget = canvas.into();
 
-    let mut game = Game::new();
+    let mut game = Game::new().map_err(|e| JsValue::from_str(&e.to_string()))?;
     let window = web_sys::window().expect("should have a window in this context");
     let performance = window
         .performance()
@@ -115,9 +131,8 @@ pub fn start() -> Result<(), JsValue> {
                 *dY.borrow_mut() *= AMORTIZATION;
             }
             let now = performance.now() as f32 / 1000.0;
-            let time_passed = now - renderer.time;
-            renderer.time = now;
-            draw_scene(&mut renderer, &mut game, time_passed).unwrap();
+            let time_passed = now - backend.time;
+            draw_scene(&mut backend, &mut game, now, time_passed).unwrap();
             // Schedule ourself for another requestAnimationFrame callback.
             request_animation_frame(f.borrow().as_ref().unwrap());
         }) as Box<dyn FnMut(f32)>));
@@ -139,47 +154,17 @@ fn initShaderProgram(
     link_program(gl, &v_shader?, &f_shader?)
 }
 
-#[allow(non_snake_case)]
-#[allow(dead_code)]
 fn draw_scene(
-    renderer: &mut drawing::Renderer,
+    backend: &mut drawing::WebGlBackend,
     game: &mut Game,
+    time: f32,
     time_passed: f32,
 ) -> Result<(), JsValue> {
-    use WebGlRenderingContext as xD;
-    let gl = &renderer.gl;
-
-    gl.enable(xD::BLEND);
-    gl.blend_func(xD::SRC_ALPHA, xD::ONE_MINUS_SRC_ALPHA);
-    // gl.enable(WebGlRenderingContext::DEPTH_TEST); // Enable depth testing
-    // gl.depth_func(WebGlRenderingContext::LEQUAL); // Near things obscure far things
-
-    gl.clear(xD::COLOR_BUFFER_BIT | xD::DEPTH_BUFFER_BIT);
-    gl.clear_color(0.4, 0.7, 0.9, 1.0);
-    gl.clear_depth(1.0);
-
-    let canvas: web_sys::HtmlCanvasElement = gl
-        .canvas()
-        .unwrap()
-        .dyn_into::<web_sys::HtmlCanvasElement>()?;
-    gl.viewport(0, 0, canvas.width() as i32, canvas.height() as i32);
-    let aspect: f32 = canvas.width() as f32 / canvas.height() as f32;
-    let zoom = 1.0 / 20.0;
-
-    let mut tmp1 = mat4::new_identity();
-    let mut tmp2 = mat4::new_identity();
-    mat4::scale(&mut tmp1, &tmp2, &[zoom, -zoom * aspect, 1.0]);
-    mat4::translate(&mut tmp2, &tmp1, &[-30.0, -15.0, 0.0]);
-    renderer.camera = tmp2;
-
+    let (width, height) = backend.size();
+    game.camera_mut().screen_size = (width as f32, height as f32);
     game.tick(time_passed);
-    for en in game.entities().iter() {
-        for tri in en.triangles() {
-            renderer.triangle(&tri, en.position(), en.rotation())?;
-        }
-    }
-
-    // buffers
 
-    Ok(())
+    let entities = game.entities().iter().map(|entity| &*entity);
+    let clear_color = game.metadata().clear_color;
+    drawing::draw_frame(backend, clear_color, game.camera(), entities, time)
 }
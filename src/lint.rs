@@ -0,0 +1,268 @@
+//! Checks for mistakes in levels that load fine, but do not play well, see the `lint_level`
+//! binary.
+
+use std::fmt;
+
+use crate::level::{Collider, Level, LevelWarning};
+use crate::svg_path::Point;
+use crate::triangulation::{contains, segments_intersect};
+
+// overlaps smaller than this are colliders that just touch
+const EPSILON: f32 = 1e-4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    MissingPlayer,
+    /// The player starts inside the collider with this id.
+    PlayerInsideCollider(Option<String>),
+    /// Nothing below the player to land on.
+    PlayerAboveNothing,
+    OverlappingColliders(Option<String>, Option<String>),
+    /// The fish spawn with this id is not inside any water volume.
+    FishOutOfWater(Option<String>),
+    Element(LevelWarning),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |id: &Option<String>| match id {
+            Some(id) => format!("\"{id}\""),
+            None => "without id".to_string(),
+        };
+
+        match self {
+            Problem::MissingPlayer => write!(f, "there is no player marker"),
+            Problem::PlayerInsideCollider(id) => {
+                write!(f, "the player starts inside the collider {}", name(id))
+            }
+            Problem::PlayerAboveNothing => {
+                write!(f, "there is no collider below the player to land on")
+            }
+            Problem::OverlappingColliders(a, b) => {
+                write!(f, "the colliders {} and {} overlap", name(a), name(b))
+            }
+            Problem::FishOutOfWater(id) => {
+                write!(f, "the fish spawn {} is not in any water", name(id))
+            }
+            Problem::Element(warning) => write!(f, "{warning}"),
+        }
+    }
+}
+
+/// Everything that is wrong with `level`, problems found while loading it come first.
+pub fn lint(level: &Level) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = level
+        .warnings()
+        .iter()
+        .cloned()
+        .map(Problem::Element)
+        .collect();
+
+    let colliders = level.colliders();
+    for (i, a) in colliders.iter().enumerate() {
+        for b in &colliders[i + 1..] {
+            if colliders_overlap(a, b) {
+                problems.push(Problem::OverlappingColliders(a.id.clone(), b.id.clone()));
+            }
+        }
+    }
+
+    for spawn in level.fish_spawns() {
+        if spawn.area.is_empty() {
+            continue;
+        }
+        let count = spawn.area.len() as f32;
+        let (sum_x, sum_y) = spawn
+            .area
+            .iter()
+            .fold((0.0, 0.0), |(x, y), point| (x + point.0, y + point.1));
+        let center = (sum_x / count, sum_y / count);
+        let in_water = level
+            .water_volumes()
+            .iter()
+            .any(|water| contains(&water.polygon, center));
+        if !in_water {
+            problems.push(Problem::FishOutOfWater(spawn.id.clone()));
+        }
+    }
+
+    let (x, y, width, height) = match level.player_bounds() {
+        Some(bounds) => bounds,
+        None => {
+            problems.push(Problem::MissingPlayer);
+            return problems;
+        }
+    };
+    let player = [
+        [(x, y), (x + width, y), (x + width, y + height)],
+        [(x, y), (x + width, y + height), (x, y + height)],
+    ];
+    for collider in colliders {
+        let inside = collider
+            .triangles
+            .iter()
+            .any(|triangle| player.iter().any(|half| triangles_overlap(triangle, half)));
+        if inside {
+            problems.push(Problem::PlayerInsideCollider(collider.id.clone()));
+        }
+    }
+
+    // a vertical line from the feet of the player to the bottom of the level
+    let bottom = colliders
+        .iter()
+        .flat_map(|collider| {
+            collider
+                .triangles
+                .iter()
+                .flatten()
+                .chain(collider.polylines.iter().flatten())
+        })
+        .map(|point| point.1)
+        .fold(y + height, f32::max);
+    let fall = ((x + width / 2.0, y + height), (x + width / 2.0, bottom));
+    let lands = colliders.iter().any(|collider| {
+        let triangle_edges = collider
+            .triangles
+            .iter()
+            .flat_map(|[a, b, c]| [(*a, *b), (*b, *c), (*c, *a)]);
+        let line_edges = collider
+            .polylines
+            .iter()
+            .flat_map(|line| line.windows(2).map(|edge| (edge[0], edge[1])));
+        triangle_edges
+            .chain(line_edges)
+            .any(|edge| segments_intersect(edge, fall))
+    });
+    if !lands {
+        problems.push(Problem::PlayerAboveNothing);
+    }
+
+    problems
+}
+
+fn colliders_overlap(a: &Collider, b: &Collider) -> bool {
+    a.triangles
+        .iter()
+        .any(|t| b.triangles.iter().any(|u| triangles_overlap(t, u)))
+}
+
+// separating axis test, triangles that only share edges or corners do not overlap
+fn triangles_overlap(a: &[Point; 3], b: &[Point; 3]) -> bool {
+    for triangle in [a, b] {
+        for i in 0..3 {
+            let (p, q) = (triangle[i], triangle[(i + 1) % 3]);
+            let axis = (p.1 - q.1, q.0 - p.0);
+            let length = axis.0.hypot(axis.1);
+            if length == 0.0 {
+                continue;
+            }
+            let project = |points: &[Point; 3]| {
+                points
+                    .iter()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), point| {
+                        let distance = (point.0 * axis.0 + point.1 * axis.1) / length;
+                        (min.min(distance), max.max(distance))
+                    })
+            };
+            let ((a_min, a_max), (b_min, b_max)) = (project(a), project(b));
+            if a_max.min(b_max) - a_min.max(b_min) <= EPSILON {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lint, Problem};
+    use crate::level::{ElementProblem, Level};
+
+    fn problems(body: &str) -> Vec<String> {
+        let content = format!(
+            r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">{body}</svg>"#
+        );
+        let level = Level::load_from_svg_str(&content).unwrap();
+        lint(&level).iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn good_level() {
+        let level = r#"
+            <rect id="player" x="4" y="0" width="1" height="2"/>
+            <rect id="hitbox-ground" x="0" y="10" width="10" height="2" fill="green"/>
+            <rect id="hitbox-wall" x="10" y="0" width="2" height="12" fill="green"/>
+            <rect id="water-pond" x="0" y="12" width="12" height="5" fill="blue"/>
+            <rect id="fish-spawn-1" x="2" y="13" width="4" height="2"/>
+        "#;
+        assert_eq!(problems(level), Vec::<String>::new());
+    }
+
+    #[test]
+    fn fish_out_of_water() {
+        let level = r#"
+            <rect id="player" x="4" y="0" width="1" height="2"/>
+            <rect id="hitbox-ground" x="0" y="10" width="10" height="2" fill="green"/>
+            <rect id="water-pond" x="0" y="12" width="10" height="5" fill="blue"/>
+            <rect id="fish-spawn-pond" x="2" y="13" width="4" height="2"/>
+            <rect id="fish-spawn-sky" x="2" y="-10" width="4" height="2"/>
+        "#;
+        assert_eq!(
+            problems(level),
+            vec![r#"the fish spawn "fish-spawn-sky" is not in any water"#]
+        );
+    }
+
+    #[test]
+    fn bad_level() {
+        let level = r##"
+            <rect id="player" x="4" y="9" width="1" height="2"/>
+            <rect id="hitbox-ground" x="0" y="10" width="10" height="2" fill="green"/>
+            <rect id="hitbox-rock" x="8" y="8" width="4" height="4" fill="gray"/>
+            <rect id="hitbox-flat" x="0" y="20" width="10" height="0"/>
+            <path id="knot" d="M0,30 L10,40 L10,30 L0,40 Z" fill="blue"/>
+            <path id="line" d="M0,50 L10,50 L5,50 Z" fill="blue"/>
+            <rect id="sketch" x="0" y="60" width="1" height="1"/>
+            <rect x="0" y="70" width="1" height="1" fill="url(#missing)"/>
+            <path id="scribble" d="M6,7 L2,8 L3,5 L4,5 L0,8 Z M4,0 L1,8 L7,0 L2,6 L6,4 Z"
+                fill="blue" fill-rule="evenodd"/>
+        "##;
+        assert_eq!(
+            problems(level),
+            vec![
+                r#"<rect id="hitbox-flat"> has no area"#,
+                r#"<path id="knot"> has a path that intersects itself"#,
+                r#"<path id="scribble"> has a path that intersects itself"#,
+                r#"<path id="line"> is filled, but has no area after triangulation"#,
+                r#"<rect id="sketch"> has no paint and is drawn in magenta"#,
+                r#"<rect> has no paint and is drawn in magenta"#,
+                r#"<path id="scribble"> lost an area of 3.16 in triangulation"#,
+                r#"the colliders "hitbox-ground" and "hitbox-rock" overlap"#,
+                r#"the player starts inside the collider "hitbox-ground""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn player() {
+        assert_eq!(
+            lint(&Level::load_from_svg_str("<svg/>").unwrap()),
+            vec![Problem::MissingPlayer]
+        );
+        assert_eq!(
+            problems(r#"<rect id="player" x="4" y="0" width="1" height="2"/>"#),
+            vec!["there is no collider below the player to land on"]
+        );
+        // lines are enough to stand on
+        let level = Level::load_from_svg_str(
+            r#"<svg><rect id="player" x="4" y="0" width="1" height="2"/>
+                <path id="bridge" data-solid="" d="M0,5 H10"/></svg>"#,
+        )
+        .unwrap();
+        assert_eq!(lint(&level), vec![]);
+        assert!(!level
+            .warnings()
+            .iter()
+            .any(|warning| warning.problem == ElementProblem::ZeroSize));
+    }
+}
@@ -0,0 +1,220 @@
+//! Gameplay markers in level SVGs.
+//!
+//! Any shape can be turned into a marker, the first of these that is present decides which
+//! kind of marker it is:
+//!
+//! 1. a `data-marker` attribute, like `data-marker="fish-spawn"`
+//! 2. the first word of its `inkscape:label`, like `fish-spawn race=eel density=0.2`
+//! 3. its `id`, the kind alone or followed by a number or a `-` or `_` suffix, like
+//!    `fish-spawn-3` or `water1` but not `waterfall`
+//!
+//! | kind            | properties                     | in `Level`                     |
+//! |-----------------|--------------------------------|--------------------------------|
+//! | `player`        |                                | top left corner of the shape   |
+//! | `hitbox`        |                                | a collider                     |
+//! | `water`         |                                | a water volume                 |
+//! | `fish-spawn`    | `race`, `density` (per unit²)  | [`FishSpawn`]                  |
+//! | `item-spawn`    | `item`                         | [`ItemSpawn`] at its center    |
+//! | `camera-bounds` |                                | the bounding box of the shape  |
+//! | `trigger`       | `event`                        | [`Trigger`]                    |
+//! | `exit`          | `target`                       | [`Exit`]                       |
+//!
+//! Properties are read from `data-<property>` attributes, or from `<property>=<value>` words
+//! in the label. The attribute wins if both are present.
+
+use crate::fish::FishRace;
+use crate::svg_path::Point;
+use crate::triangulation::{contains, signed_area};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_FISH_DENSITY: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerKind {
+    Player,
+    Hitbox,
+    Water,
+    FishSpawn,
+    ItemSpawn,
+    CameraBounds,
+    Trigger,
+    Exit,
+}
+
+const KINDS: [(&str, MarkerKind); 8] = [
+    ("player", MarkerKind::Player),
+    ("hitbox", MarkerKind::Hitbox),
+    ("water", MarkerKind::Water),
+    ("fish-spawn", MarkerKind::FishSpawn),
+    ("item-spawn", MarkerKind::ItemSpawn),
+    ("camera-bounds", MarkerKind::CameraBounds),
+    ("trigger", MarkerKind::Trigger),
+    ("exit", MarkerKind::Exit),
+];
+
+impl MarkerKind {
+    pub fn parse(value: &str) -> Option<MarkerKind> {
+        KINDS
+            .iter()
+            .find(|(name, _)| *name == value.trim())
+            .map(|(_, kind)| *kind)
+    }
+
+    /// The kind an id like `hitbox-2` stands for.
+    pub fn from_id(id: &str) -> Option<MarkerKind> {
+        KINDS
+            .iter()
+            .find(|(name, _)| match id.strip_prefix(name) {
+                Some(rest) => rest
+                    .chars()
+                    .take(1)
+                    .all(|c| c == '-' || c == '_' || c.is_ascii_digit()),
+                None => false,
+            })
+            .map(|(_, kind)| *kind)
+    }
+}
+
+/// Splits an `inkscape:label` like `fish-spawn race=eel density=0.2` into the marker kind and
+/// its properties. Labels that do not start with a marker kind are just names.
+pub fn parse_label(label: &str) -> Option<(MarkerKind, Vec<(&str, &str)>)> {
+    let kind = MarkerKind::parse(label.split_whitespace().next()?)?;
+    Some((kind, split_label(label).1))
+}
+
+/// Splits a label into the words of its name and its `key=value` properties.
+pub fn split_label(label: &str) -> (Vec<&str>, Vec<(&str, &str)>) {
+    let (properties, name): (Vec<&str>, Vec<&str>) = label
+        .split_whitespace()
+        .partition(|word| word.contains('='));
+    let properties = properties
+        .into_iter()
+        .filter_map(|word| word.split_once('='))
+        .collect();
+    (name, properties)
+}
+
+/// An area fish of one race are spawned in when the level starts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FishSpawn {
+    pub id: Option<String>,
+    pub area: Vec<Point>,
+    pub race: FishRace,
+    /// Fish per square unit.
+    pub density: f32,
+}
+
+impl FishSpawn {
+    /// How many fish to spawn, at least one.
+    pub fn fish_count(&self) -> usize {
+        let area = signed_area(&self.area).abs() / 2.0;
+        ((area * self.density).round() as usize).max(1)
+    }
+
+    /// A random point inside the area, `random` returns numbers in `0..1`.
+    pub fn random_point(&self, mut random: impl FnMut() -> f32) -> Point {
+        let (min, max) = self.area.iter().fold(
+            (
+                (f32::INFINITY, f32::INFINITY),
+                (f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), (x, y)| {
+                (
+                    (min.0.min(*x), min.1.min(*y)),
+                    (max.0.max(*x), max.1.max(*y)),
+                )
+            },
+        );
+        let mut point = min;
+        // thin or weird areas fall back to the last point tried
+        for _ in 0..100 {
+            point = (
+                min.0 + (max.0 - min.0) * random(),
+                min.1 + (max.1 - min.1) * random(),
+            );
+            if contains(&self.area, point) {
+                break;
+            }
+        }
+        point
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ItemSpawn {
+    pub id: Option<String>,
+    pub position: Point,
+    pub item: Option<String>,
+}
+
+/// An area that fires `event` when the player enters it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trigger {
+    pub id: Option<String>,
+    pub area: Vec<Point>,
+    pub event: Option<String>,
+}
+
+/// An area that leaves the level, towards the level `target`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Exit {
+    pub id: Option<String>,
+    pub area: Vec<Point>,
+    pub target: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_label, FishSpawn, MarkerKind};
+    use crate::fish::FishRace;
+
+    #[test]
+    fn kinds() {
+        assert_eq!(MarkerKind::parse("exit"), Some(MarkerKind::Exit));
+        assert_eq!(MarkerKind::parse("exits"), None);
+        assert_eq!(MarkerKind::from_id("hitbox-1"), Some(MarkerKind::Hitbox));
+        assert_eq!(MarkerKind::from_id("path849"), None);
+        assert_eq!(MarkerKind::from_id("player"), Some(MarkerKind::Player));
+        assert_eq!(
+            MarkerKind::from_id("player-arrow"),
+            Some(MarkerKind::Player)
+        );
+        assert_eq!(MarkerKind::from_id("water1"), Some(MarkerKind::Water));
+        assert_eq!(MarkerKind::from_id("exit_cave"), Some(MarkerKind::Exit));
+        assert_eq!(MarkerKind::from_id("waterfall"), None);
+        assert_eq!(MarkerKind::from_id("exitsign"), None);
+        assert_eq!(MarkerKind::from_id("hitboxes-layer"), None);
+        assert_eq!(MarkerKind::from_id("triggered"), None);
+        assert_eq!(
+            parse_label("fish-spawn race=eel  density=0.2 deep"),
+            Some((
+                MarkerKind::FishSpawn,
+                vec![("race", "eel"), ("density", "0.2")]
+            ))
+        );
+        assert_eq!(parse_label("Lake"), None);
+    }
+
+    #[test]
+    fn fish_spawn() {
+        let spawn = FishSpawn {
+            id: None,
+            // an L shape with an area of 3
+            area: vec![
+                (0.0, 0.0),
+                (2.0, 0.0),
+                (2.0, 1.0),
+                (1.0, 1.0),
+                (1.0, 2.0),
+                (0.0, 2.0),
+            ],
+            race: FishRace::Eel,
+            density: 1.0,
+        };
+        assert_eq!(spawn.fish_count(), 3);
+
+        // the first candidate is in the cut out corner, the second one is inside
+        let mut numbers = [0.75, 0.75, 0.25, 0.75].into_iter();
+        assert_eq!(spawn.random_point(|| numbers.next().unwrap()), (0.5, 1.5));
+    }
+}
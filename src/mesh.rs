@@ -0,0 +1,163 @@
+//! Triangles packed into vertex and index arrays, so that a whole level or all moving entities
+//! can be drawn with a few draw calls, see [`crate::drawing::draw_frame`].
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::types::{ShaderId, Triangle};
+
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+
+/// A version that no other static mesh has, see [`StaticMesh::version`].
+pub fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Triangles of an entity that are uploaded to the GPU once and kept there, see
+/// [`crate::types::Entity::static_meshes`].
+pub struct StaticMesh<'a> {
+    /// The triangles are uploaded again when this changes.
+    pub version: u64,
+    pub triangles: &'a [Triangle],
+    /// Moves the triangles when they are drawn, without uploading them again.
+    pub offset: (f32, f32),
+    /// Like [`crate::types::Entity::z_index`], an entity's meshes can be drawn at different
+    /// depths.
+    pub z_index: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Program {
+    Default,
+    Water,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Primitive {
+    Triangles,
+    /// Wireframes, each triangle as three lines.
+    Lines,
+}
+
+/// A run of indices that is drawn with one draw call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DrawRange {
+    pub program: Program,
+    pub primitive: Primitive,
+    /// The first index.
+    pub start: usize,
+    pub count: usize,
+}
+
+/// Vertex attributes in separate arrays, one entry per vertex in each. Triangles do not share
+/// vertices, they are drawn in the order they were added.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    /// `x, y, z` in world coordinates.
+    pub positions: Vec<f32>,
+    /// `r, g, b, a`.
+    pub colors: Vec<f32>,
+    /// The surface of the water volume for water, `0` for everything else.
+    pub water_levels: Vec<f32>,
+    pub indices: Vec<u32>,
+    pub draws: Vec<DrawRange>,
+}
+
+impl Mesh {
+    /// Adds the triangles of an entity that is rotated by `rotation` radians and then moved to
+    /// `position`.
+    pub fn push(&mut self, triangles: &[Triangle], position: (f32, f32), rotation: f32) {
+        let (sin, cos) = rotation.sin_cos();
+        for triangle in triangles {
+            let first = (self.positions.len() / 3) as u32;
+            for point in triangle.coords.chunks(3) {
+                self.positions.extend([
+                    point[0] * cos - point[1] * sin + position.0,
+                    point[0] * sin + point[1] * cos + position.1,
+                    point[2],
+                ]);
+            }
+
+            let (program, water_level) = match triangle.shader_id {
+                ShaderId::Default => (Program::Default, 0.0),
+                ShaderId::Water { surface } => (Program::Water, surface + position.1),
+            };
+            self.colors.extend(triangle.vertex_colors().concat());
+            self.water_levels.extend([water_level; 3]);
+
+            let [a, b, c] = [first, first + 1, first + 2];
+            let (primitive, indices) = match triangle.wireframe {
+                false => (Primitive::Triangles, vec![a, b, c]),
+                true => (Primitive::Lines, vec![a, b, b, c, c, a]),
+            };
+            match self.draws.last_mut() {
+                Some(draw) if draw.program == program && draw.primitive == primitive => {
+                    draw.count += indices.len();
+                }
+                _ => self.draws.push(DrawRange {
+                    program,
+                    primitive,
+                    start: self.indices.len(),
+                    count: indices.len(),
+                }),
+            }
+            self.indices.extend(indices);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DrawRange, Mesh, Primitive, Program};
+    use crate::types::{ShaderId, Triangle};
+
+    #[test]
+    fn draw_ranges() {
+        let triangle = |shader_id, wireframe| Triangle {
+            coords: [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            color: [1.0, 0.0, 0.0, 1.0],
+            shader_id,
+            wireframe,
+            vertex_colors: None,
+        };
+        let water = ShaderId::Water { surface: 2.0 };
+
+        let mut mesh = Mesh::default();
+        mesh.push(
+            &[
+                triangle(ShaderId::Default, false),
+                triangle(ShaderId::Default, false),
+                triangle(water.clone(), false),
+                triangle(ShaderId::Default, true),
+            ],
+            (0.0, 0.0),
+            0.0,
+        );
+        let range = |program, primitive, start, count| DrawRange {
+            program,
+            primitive,
+            start,
+            count,
+        };
+        assert_eq!(
+            mesh.draws,
+            vec![
+                range(Program::Default, Primitive::Triangles, 0, 6),
+                range(Program::Water, Primitive::Triangles, 6, 3),
+                range(Program::Default, Primitive::Lines, 9, 6),
+            ]
+        );
+        assert_eq!(&mesh.indices[9..], &[9, 10, 10, 11, 11, 9]);
+        assert_eq!(mesh.water_levels[6..9], [2.0; 3]);
+
+        // turned a quarter to the left, then moved
+        let mut mesh = Mesh::default();
+        mesh.push(
+            &[triangle(water, false)],
+            (10.0, 5.0),
+            std::f32::consts::FRAC_PI_2,
+        );
+        let positions: Vec<f32> = mesh.positions.iter().map(|x| x.round()).collect();
+        assert_eq!(positions, [10.0, 5.0, 0.0, 10.0, 6.0, 0.0, 9.0, 5.0, 0.0]);
+        assert_eq!(mesh.water_levels, [7.0; 3]);
+    }
+}
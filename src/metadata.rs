@@ -0,0 +1,114 @@
+//! Level wide settings, read from `data-` attributes of the root `<svg>` element:
+//!
+//! | attribute          | example         | default               |
+//! |--------------------|-----------------|-----------------------|
+//! | `data-name`        | `Lake Tutorial` |                       |
+//! | `data-author`      | `nuuls`         |                       |
+//! | `data-gravity`     | `0 9.81`        | `0 9.81`              |
+//! | `data-scale`       | `0.5`           | `1`                   |
+//! | `data-clear-color` | `#66b3e6`       | a light blue          |
+//! | `data-water-color` | `teal`          | the fill of the water |
+//! | `data-camera`      | `30 15 40`      | `30 15 40`            |
+//!
+//! `data-scale` is the size of an SVG unit in world units, everything in the level is scaled by
+//! it. The gravity is given in world units. `data-camera` is the center and the visible width
+//! of the initial view, in SVG units.
+
+use crate::svg_path::Point;
+use crate::types::Color;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelMetadata {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    /// World units per second², down is positive y.
+    pub gravity: (f32, f32),
+    /// World units per SVG unit.
+    pub scale: f32,
+    pub clear_color: Color,
+    /// Replaces the fill of all water volumes.
+    pub water_color: Option<Color>,
+    pub camera: CameraFraming,
+}
+
+impl Default for LevelMetadata {
+    fn default() -> Self {
+        LevelMetadata {
+            name: None,
+            author: None,
+            gravity: (0.0, 9.81),
+            scale: 1.0,
+            clear_color: [0.4, 0.7, 0.9, 1.0],
+            water_color: None,
+            camera: CameraFraming::default(),
+        }
+    }
+}
+
+/// What the camera shows when the level starts, in world units.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraFraming {
+    pub center: Point,
+    /// The visible width, the height follows from the aspect ratio of the canvas.
+    pub width: f32,
+}
+
+impl Default for CameraFraming {
+    fn default() -> Self {
+        CameraFraming {
+            center: (30.0, 15.0),
+            width: 40.0,
+        }
+    }
+}
+
+impl CameraFraming {
+    /// Parses `<center x> <center y> <width>`, separated by whitespace or commas.
+    pub fn parse(value: &str) -> Option<CameraFraming> {
+        match parse_numbers(value)?[..] {
+            [x, y, width] if width > 0.0 => Some(CameraFraming {
+                center: (x, y),
+                width,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a vector like `0 9.81` or `0,9.81`.
+pub fn parse_vector(value: &str) -> Option<(f32, f32)> {
+    match parse_numbers(value)?[..] {
+        [x, y] => Some((x, y)),
+        _ => None,
+    }
+}
+
+fn parse_numbers(value: &str) -> Option<Vec<f32>> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse::<f32>().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_vector, CameraFraming};
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_vector("0 9.81"), Some((0.0, 9.81)));
+        assert_eq!(parse_vector(" -1.5,2 "), Some((-1.5, 2.0)));
+        assert_eq!(parse_vector("1"), None);
+        assert_eq!(parse_vector("1 2 3"), None);
+        assert_eq!(
+            CameraFraming::parse("10, 5, 20"),
+            Some(CameraFraming {
+                center: (10.0, 5.0),
+                width: 20.0
+            })
+        );
+        assert_eq!(CameraFraming::parse("10 5 0"), None);
+    }
+}
@@ -3,7 +3,7 @@ use nphysics2d::object::{DefaultBodyHandle, DefaultColliderHandle};
 use crate::{
     fishing_rod::FishingRod,
     sick_physics::Physics,
-    types::{red, Entity, GameState, ShaderId, Triangle},
+    types::{red, Entity, GameState, ShaderId, Triangle, Z_PLAYER},
 };
 
 pub struct Player {
@@ -21,7 +21,7 @@ impl Entity for Player {
         &self.id
     }
 
-    fn triangles(&self) -> &Vec<Triangle> {
+    fn triangles(&self) -> &[Triangle] {
         &self.triangles
     }
 
@@ -30,7 +30,8 @@ impl Entity for Player {
             + if gs.input.move_right { 1.0 } else { 0.0 };
 
         if gs.input.throw_rod {
-            gs.entity_ops.insert(FishingRod::new(self.position));
+            gs.entity_ops
+                .insert(FishingRod::new(self.position, gs.mouse));
         }
 
         // walk
@@ -53,6 +54,10 @@ impl Entity for Player {
         self.position
     }
 
+    fn z_index(&self) -> i32 {
+        Z_PLAYER
+    }
+
     fn rotation(&self) -> f32 {
         self.rotation
     }
@@ -0,0 +1,406 @@
+//! A [`Backend`] that draws on the CPU into an RGBA buffer, so frames can be compared to
+//! reference images in tests without a GPU. It follows the WebGL backend closely: pixel centers
+//! are sampled, colors are interpolated across triangles, wireframes are one pixel wide lines
+//! and everything is alpha blended. The water shader is evaluated per pixel like in
+//! `water.frag`.
+
+use std::convert::Infallible;
+
+use crate::drawing::{Backend, Mat4};
+use crate::mesh::{Mesh, Primitive, Program, StaticMesh};
+use crate::svg_path::Point;
+use crate::types::Color;
+
+// from water.frag
+const WATER_HEIGHT: f32 = 0.1;
+const FOAM: [f32; 3] = [1.0, 1.0, 1.0];
+
+pub struct Rasterizer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    camera: Mat4,
+    time: f32,
+}
+
+// a vertex after the camera, with what the shaders need
+#[derive(Clone, Copy)]
+struct Vertex {
+    screen: Point,
+    world: Point,
+    color: Color,
+    water_level: f32,
+}
+
+impl Vertex {
+    fn interpolate(vertices: &[Vertex], weights: &[f32]) -> Vertex {
+        let mut result = Vertex {
+            screen: (0.0, 0.0),
+            world: (0.0, 0.0),
+            color: [0.0; 4],
+            water_level: 0.0,
+        };
+        for (vertex, weight) in vertices.iter().zip(weights) {
+            result.world.0 += vertex.world.0 * weight;
+            result.world.1 += vertex.world.1 * weight;
+            for (c, v) in result.color.iter_mut().zip(vertex.color) {
+                *c += v * weight;
+            }
+            result.water_level += vertex.water_level * weight;
+        }
+        result
+    }
+}
+
+impl Rasterizer {
+    pub fn new(width: u32, height: u32) -> Rasterizer {
+        Rasterizer {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+            camera: mat4::new_identity(),
+            time: 0.0,
+        }
+    }
+
+    /// Four bytes per pixel, rows from the top.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn draw(&mut self, mesh: &Mesh) {
+        for draw in &mesh.draws {
+            let indices = &mesh.indices[draw.start..draw.start + draw.count];
+            let vertices: Vec<Vertex> = indices.iter().map(|i| self.vertex(mesh, *i)).collect();
+            match draw.primitive {
+                Primitive::Triangles => {
+                    for triangle in vertices.chunks(3) {
+                        self.fill_triangle(draw.program, [triangle[0], triangle[1], triangle[2]]);
+                    }
+                }
+                Primitive::Lines => {
+                    for line in vertices.chunks(2) {
+                        self.line(draw.program, line[0], line[1]);
+                    }
+                }
+            }
+        }
+    }
+
+    fn vertex(&self, mesh: &Mesh, index: u32) -> Vertex {
+        let i = index as usize;
+        let [x, y, z] = [0, 1, 2].map(|n| mesh.positions[i * 3 + n]);
+        let m = &self.camera;
+        let w = m[3] * x + m[7] * y + m[11] * z + m[15];
+        let clip_x = (m[0] * x + m[4] * y + m[8] * z + m[12]) / w;
+        let clip_y = (m[1] * x + m[5] * y + m[9] * z + m[13]) / w;
+        Vertex {
+            screen: (
+                (clip_x + 1.0) / 2.0 * self.width as f32,
+                (1.0 - clip_y) / 2.0 * self.height as f32,
+            ),
+            world: (x, y),
+            color: [0, 1, 2, 3].map(|n| mesh.colors[i * 4 + n]),
+            water_level: mesh.water_levels[i],
+        }
+    }
+
+    fn fill_triangle(&mut self, program: Program, [a, b, c]: [Vertex; 3]) {
+        let area = edge(a.screen, b.screen, c.screen);
+        if area == 0.0 {
+            return;
+        }
+        let (b, c) = match area > 0.0 {
+            true => (b, c),
+            false => (c, b),
+        };
+        let area = area.abs();
+
+        let xs = [a.screen.0, b.screen.0, c.screen.0];
+        let ys = [a.screen.1, b.screen.1, c.screen.1];
+        let (min_x, max_x) = self.pixel_range(&xs, self.width);
+        let (min_y, max_y) = self.pixel_range(&ys, self.height);
+        let edges = [
+            (b.screen, c.screen),
+            (c.screen, a.screen),
+            (a.screen, b.screen),
+        ];
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let center = (x as f32 + 0.5, y as f32 + 0.5);
+                let weights = edges.map(|(from, to)| edge(from, to, center));
+                let inside = weights
+                    .iter()
+                    .zip(edges)
+                    .all(|(weight, (from, to))| *weight > 0.0 || *weight == 0.0 && owns(from, to));
+                if inside {
+                    let vertex = Vertex::interpolate(&[a, b, c], &weights.map(|w| w / area));
+                    self.blend(x, y, program, vertex);
+                }
+            }
+        }
+    }
+
+    // leaves out the last pixel, so connected lines do not blend twice
+    fn line(&mut self, program: Program, a: Vertex, b: Vertex) {
+        let (dx, dy) = (b.screen.0 - a.screen.0, b.screen.1 - a.screen.1);
+        let steps = dx.abs().max(dy.abs()).round().max(1.0) as usize;
+        for step in 0..steps {
+            let t = step as f32 / steps as f32;
+            let (x, y) = (a.screen.0 + dx * t, a.screen.1 + dy * t);
+            if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+                continue;
+            }
+            let vertex = Vertex::interpolate(&[a, b], &[1.0 - t, t]);
+            self.blend(x as u32, y as u32, program, vertex);
+        }
+    }
+
+    // pixels whose centers can be inside `coordinates`
+    fn pixel_range(&self, coordinates: &[f32], size: u32) -> (u32, u32) {
+        let min = coordinates.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = coordinates
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+        let clamp = |value: f32| value.clamp(0.0, size as f32) as u32;
+        (clamp((min - 0.5).floor()), clamp((max + 0.5).ceil()))
+    }
+
+    fn blend(&mut self, x: u32, y: u32, program: Program, vertex: Vertex) {
+        let color = match program {
+            Program::Default => vertex.color,
+            Program::Water => water_color(vertex, self.time),
+        };
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let alpha = color[3];
+        for (pixel, channel) in self.pixels[i..i + 4].iter_mut().zip(color) {
+            let value = channel * alpha + *pixel as f32 / 255.0 * (1.0 - alpha);
+            *pixel = to_byte(value);
+        }
+    }
+}
+
+impl Backend for Rasterizer {
+    type Error = Infallible;
+
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn begin_frame(
+        &mut self,
+        clear_color: Color,
+        camera: Mat4,
+        time: f32,
+    ) -> Result<(), Infallible> {
+        let clear = clear_color.map(to_byte);
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&clear);
+        }
+        self.camera = camera;
+        self.time = time;
+        Ok(())
+    }
+
+    fn draw_static(&mut self, _key: (&str, usize), mesh: &StaticMesh) -> Result<(), Infallible> {
+        let mut vertices = Mesh::default();
+        vertices.push(mesh.triangles, mesh.offset, 0.0);
+        self.draw(&vertices);
+        Ok(())
+    }
+
+    fn draw_mesh(&mut self, mesh: &Mesh) -> Result<(), Infallible> {
+        self.draw(mesh);
+        Ok(())
+    }
+}
+
+// twice the signed area of the triangle `a`, `b`, `p`
+fn edge(a: Point, b: Point, p: Point) -> f32 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+}
+
+// whether pixel centers exactly on the edge belong to the triangle, only one of two triangles
+// that share an edge owns it
+fn owns(from: Point, to: Point) -> bool {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    dy > 0.0 || dy == 0.0 && dx > 0.0
+}
+
+fn water_color(vertex: Vertex, time: f32) -> Color {
+    let Vertex { world, color, .. } = vertex;
+    let wave = ((world.0 * 0.3).sin() * 0.3 + time * 2.0).sin();
+    let water_start = vertex.water_level + wave * WATER_HEIGHT + WATER_HEIGHT;
+    if world.1 < water_start {
+        return [0.0, 0.0, 1.0, 0.0];
+    }
+    let a = (world.1 - water_start).clamp(0.0, 1.0);
+    let mix = |n: usize| FOAM[n] * (1.0 - a) + color[n] * a;
+    [mix(0), mix(1), mix(2), color[3]]
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    use super::Rasterizer;
+    use crate::camera::Camera;
+    use crate::drawing::{draw_frame, Backend};
+    use crate::level::Level;
+    use crate::mesh::Mesh;
+    use crate::types::{Entity, Triangle};
+
+    // how far a channel may be off before the pixel counts as different
+    const CHANNEL_TOLERANCE: u8 = 2;
+    // how many pixels may be different
+    const PIXEL_TOLERANCE: f32 = 0.001;
+
+    fn read_png(path: &PathBuf) -> Option<(u32, u32, Vec<u8>)> {
+        let decoder = png::Decoder::new(File::open(path).ok()?);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        pixels.truncate(info.buffer_size());
+        Some((info.width, info.height, pixels))
+    }
+
+    fn write_png(path: &PathBuf, rasterizer: &Rasterizer) {
+        let (width, height) = rasterizer.size();
+        let mut encoder = png::Encoder::new(File::create(path).unwrap(), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(rasterizer.pixels()).unwrap();
+    }
+
+    /// Compares the frame to `src/snapshots/<name>.png`. Differences are written next to it as
+    /// `<name>.new.png`, `UPDATE_REFERENCE_IMAGES=1` replaces the reference instead.
+    fn assert_reference_image(name: &str, rasterizer: &Rasterizer) {
+        let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/snapshots");
+        let reference = directory.join(format!("{name}.png"));
+        if std::env::var_os("UPDATE_REFERENCE_IMAGES").is_some() {
+            write_png(&reference, rasterizer);
+            return;
+        }
+
+        let (width, height) = rasterizer.size();
+        let different = match read_png(&reference) {
+            Some((w, h, pixels)) if (w, h) == (width, height) => pixels
+                .chunks(4)
+                .zip(rasterizer.pixels().chunks(4))
+                .filter(|(a, b)| {
+                    a.iter()
+                        .zip(*b)
+                        .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+                })
+                .count(),
+            _ => (width * height) as usize,
+        };
+        if different as f32 > PIXEL_TOLERANCE * (width * height) as f32 {
+            let new = directory.join(format!("{name}.new.png"));
+            write_png(&new, rasterizer);
+            panic!(
+                "{different} pixels differ from {}, see {}",
+                reference.display(),
+                new.display()
+            );
+        }
+    }
+
+    fn pixel(rasterizer: &Rasterizer, x: usize, y: usize) -> &[u8] {
+        let i = (y * rasterizer.width as usize + x) * 4;
+        &rasterizer.pixels()[i..i + 4]
+    }
+
+    #[test]
+    fn triangles() {
+        let mut rasterizer = Rasterizer::new(4, 4);
+        // pixels map to world units, y down
+        let camera = [
+            0.5, 0.0, 0.0, 0.0, 0.0, -0.5, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 1.0, 0.0, 1.0,
+        ];
+        rasterizer
+            .begin_frame([0.0, 0.0, 0.0, 1.0], camera, 0.0)
+            .unwrap();
+
+        // two halves of a square, the shared edge is blended once
+        let half = [0.0, 0.0, 0.5, 1.0];
+        let mut square = Mesh::default();
+        square.push(
+            &[
+                Triangle::from_points((0.0, 0.0), (4.0, 0.0), (4.0, 4.0), half),
+                Triangle::from_points((0.0, 0.0), (4.0, 4.0), (0.0, 4.0), half),
+            ],
+            (0.0, 0.0),
+            0.0,
+        );
+        rasterizer.draw_mesh(&square).unwrap();
+        assert!(rasterizer
+            .pixels()
+            .chunks(4)
+            .all(|pixel| pixel == [0, 0, 128, 255]));
+
+        let mut line = Triangle::from_points((0.0, 1.5), (4.0, 1.5), (2.0, 1.5), [1.0; 4]);
+        line.wireframe = true;
+        let mut wireframe = Mesh::default();
+        wireframe.push(&[line], (0.0, 0.0), 0.0);
+        rasterizer.draw_mesh(&wireframe).unwrap();
+        assert_eq!(pixel(&rasterizer, 0, 1), [255; 4]);
+        assert_eq!(pixel(&rasterizer, 3, 1), [255; 4]);
+        assert_eq!(pixel(&rasterizer, 0, 2), [0, 0, 128, 255]);
+    }
+
+    #[test]
+    fn water_color() {
+        let draw = |water_color: &str| {
+            let content = format!(
+                r##"<svg viewBox="0 0 20 10" xmlns="http://www.w3.org/2000/svg"
+                    data-clear-color="#000" data-water-color="{water_color}" data-camera="10 5 20">
+                    <rect data-water="" x="0" y="2" width="20" height="8" fill="teal"/>
+                </svg>"##
+            );
+            let level = Level::load_from_svg_str(&content).unwrap();
+            let mut rasterizer = Rasterizer::new(20, 10);
+            let mut camera = Camera::new(&level.metadata().camera, level.camera_bounds());
+            camera.screen_size = (20.0, 10.0);
+            camera.update(None, 0.0);
+            let clear_color = level.metadata().clear_color;
+            draw_frame(
+                &mut rasterizer,
+                clear_color,
+                &camera,
+                [&level as &dyn Entity],
+                0.0,
+            )
+            .unwrap();
+            rasterizer
+        };
+
+        // deep enough to be past the foam
+        let red = draw("#f00");
+        assert_eq!(pixel(&red, 10, 8), [255, 0, 0, 255]);
+        let green = draw("rgba(0, 255, 0, 0.5)");
+        assert_eq!(pixel(&green, 10, 8), [0, 128, 0, 191]);
+        // above the surface
+        assert_eq!(pixel(&green, 10, 0), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn map() {
+        let level = Level::load_from_svg_str(include_str!("../assets/map.svg")).unwrap();
+        let mut rasterizer = Rasterizer::new(320, 180);
+        let entities = [&level as &dyn Entity];
+        let mut camera = Camera::new(&level.metadata().camera, level.camera_bounds());
+        camera.screen_size = (320.0, 180.0);
+        camera.update(None, 0.0);
+        let clear_color = level.metadata().clear_color;
+        draw_frame(&mut rasterizer, clear_color, &camera, entities, 1.0).unwrap();
+        assert_reference_image("map", &rasterizer);
+    }
+}
@@ -0,0 +1,160 @@
+//! A [`Gl`] that draws nothing and records the calls that matter for performance, so tests can
+//! check how the renderer talks to the GPU.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use wasm_bindgen::JsValue;
+
+use crate::drawing::{DefaultShader, Shader, WaterShader};
+use crate::gl::Gl;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GlCall {
+    UseProgram(&'static str),
+    /// The name of the uniform and its value.
+    Uniform(&'static str, Vec<f32>),
+    BufferData {
+        buffer: usize,
+        bytes: usize,
+        usage: u32,
+    },
+    DrawElements {
+        mode: u32,
+        count: i32,
+        offset: i32,
+    },
+}
+
+/// Buffers are numbered, programs and uniforms are their names in the shaders.
+pub struct RecordingGl {
+    size: (u32, u32),
+    calls: RefCell<Vec<GlCall>>,
+    buffers: Cell<usize>,
+    // by target
+    bound: RefCell<HashMap<u32, usize>>,
+}
+
+impl RecordingGl {
+    pub fn new(width: u32, height: u32) -> RecordingGl {
+        RecordingGl {
+            size: (width, height),
+            calls: RefCell::new(vec![]),
+            buffers: Cell::new(0),
+            bound: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The calls since the last time this was called.
+    pub fn take_calls(&self) -> Vec<GlCall> {
+        self.calls.take()
+    }
+
+    /// The default and the water shader, with the attribute locations WebGL would likely pick.
+    pub fn shaders() -> (DefaultShader<RecordingGl>, WaterShader<RecordingGl>) {
+        let base = |program| Shader {
+            program,
+            coordinate_index: 0,
+            camera_index: "camera",
+            transform_index: "transform",
+        };
+        let shader = DefaultShader {
+            base: base("default"),
+            vertex_color_index: 1,
+        };
+        let water_shader = WaterShader {
+            base: base("water"),
+            time_index: "time",
+            vertex_color_index: 1,
+            water_y_level_index: 2,
+        };
+        (shader, water_shader)
+    }
+
+    fn record(&self, call: GlCall) {
+        self.calls.borrow_mut().push(call);
+    }
+
+    fn buffer_data(&self, target: u32, bytes: usize, usage: u32) {
+        let buffer = *self
+            .bound
+            .borrow()
+            .get(&target)
+            .expect("no buffer is bound");
+        self.record(GlCall::BufferData {
+            buffer,
+            bytes,
+            usage,
+        });
+    }
+}
+
+impl Gl for RecordingGl {
+    type Buffer = usize;
+    type Program = &'static str;
+    type UniformLocation = &'static str;
+
+    fn enable_extension(&self, _name: &str) -> Result<bool, JsValue> {
+        Ok(true)
+    }
+
+    fn drawing_buffer_size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    fn enable(&self, _capability: u32) {}
+
+    fn blend_func(&self, _source: u32, _destination: u32) {}
+
+    fn clear(&self, _color: [f32; 4]) {}
+
+    fn viewport(&self, _x: i32, _y: i32, _width: i32, _height: i32) {}
+
+    fn create_buffer(&self) -> Option<usize> {
+        let buffer = self.buffers.get();
+        self.buffers.set(buffer + 1);
+        Some(buffer)
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<&usize>) {
+        let mut bound = self.bound.borrow_mut();
+        match buffer {
+            Some(buffer) => bound.insert(target, *buffer),
+            None => bound.remove(&target),
+        };
+    }
+
+    fn buffer_data_f32(&self, target: u32, data: &[f32], usage: u32) -> Result<(), JsValue> {
+        self.buffer_data(target, data.len() * 4, usage);
+        Ok(())
+    }
+
+    fn buffer_data_u32(&self, target: u32, data: &[u32], usage: u32) -> Result<(), JsValue> {
+        self.buffer_data(target, data.len() * 4, usage);
+        Ok(())
+    }
+
+    fn use_program(&self, program: &&'static str) {
+        self.record(GlCall::UseProgram(program));
+    }
+
+    fn uniform_matrix4fv(&self, location: &&'static str, value: &[f32; 16]) {
+        self.record(GlCall::Uniform(location, value.to_vec()));
+    }
+
+    fn uniform1f(&self, location: &&'static str, value: f32) {
+        self.record(GlCall::Uniform(location, vec![value]));
+    }
+
+    fn bind_attribute(&self, _buffer: &usize, _index: u32, _size: i32) {}
+
+    fn disable_vertex_attrib_array(&self, _index: u32) {}
+
+    fn draw_elements(&self, mode: u32, count: i32, _type: u32, offset: i32) {
+        self.record(GlCall::DrawElements {
+            mode,
+            count,
+            offset,
+        });
+    }
+}
@@ -0,0 +1,181 @@
+use std::f32::consts::PI;
+
+use crate::svg_path::{arc_segment_angle, PathInterpreter, Point, Subpath};
+
+// even tiny pebbles should not turn into triangles
+const MIN_ELLIPSE_SEGMENTS: usize = 8;
+
+/// https://www.w3.org/TR/SVG/shapes.html#EllipseElement
+///
+/// The segment count grows with the radius so the outline stays within `tolerance`.
+pub fn ellipse(cx: f32, cy: f32, rx: f32, ry: f32, tolerance: f32) -> Option<Subpath> {
+    if rx <= 0.0 || ry <= 0.0 {
+        return None;
+    }
+
+    let n = (2.0 * PI / arc_segment_angle(rx.max(ry), tolerance)).ceil();
+    // a tolerance of 0 can't be met with any number of segments
+    let n = if n.is_finite() {
+        (n as usize).max(MIN_ELLIPSE_SEGMENTS)
+    } else {
+        MIN_ELLIPSE_SEGMENTS
+    };
+
+    let points = (0..n)
+        .map(|i| {
+            let (sin, cos) = (2.0 * PI * i as f32 / n as f32).sin_cos();
+            (cx + rx * cos, cy + ry * sin)
+        })
+        .collect();
+
+    Some(Subpath {
+        points,
+        closed: true,
+    })
+}
+
+/// https://www.w3.org/TR/SVG/shapes.html#CircleElement
+pub fn circle(cx: f32, cy: f32, r: f32, tolerance: f32) -> Option<Subpath> {
+    ellipse(cx, cy, r, r, tolerance)
+}
+
+/// https://www.w3.org/TR/SVG/shapes.html#RectElement
+///
+/// `rx` and `ry` are the rounded corner radii, a missing one takes the value of the other.
+pub fn rect(
+    (x, y): Point,
+    width: f32,
+    height: f32,
+    rx: Option<f32>,
+    ry: Option<f32>,
+    tolerance: f32,
+) -> Option<Subpath> {
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+
+    let (rx, ry) = match (rx, ry) {
+        (Some(rx), Some(ry)) => (rx, ry),
+        (Some(r), None) | (None, Some(r)) => (r, r),
+        (None, None) => (0.0, 0.0),
+    };
+    let rx = rx.clamp(0.0, width / 2.0);
+    let ry = ry.clamp(0.0, height / 2.0);
+
+    if rx == 0.0 || ry == 0.0 {
+        return Some(Subpath {
+            points: vec![
+                (x, y),
+                (x + width, y),
+                (x + width, y + height),
+                (x, y + height),
+            ],
+            closed: true,
+        });
+    }
+
+    let mut path = PathInterpreter::new(tolerance);
+    path.move_to((x + rx, y));
+    path.line_to((x + width - rx, y));
+    path.arc_to(rx, ry, 0.0, false, true, (x + width, y + ry));
+    path.line_to((x + width, y + height - ry));
+    path.arc_to(rx, ry, 0.0, false, true, (x + width - rx, y + height));
+    path.line_to((x + rx, y + height));
+    path.arc_to(rx, ry, 0.0, false, true, (x, y + height - ry));
+    path.line_to((x, y + ry));
+    path.arc_to(rx, ry, 0.0, false, true, (x + rx, y));
+    path.close();
+
+    path.finish().pop()
+}
+
+/// https://www.w3.org/TR/SVG/shapes.html#LineElement
+pub fn line(from: Point, to: Point) -> Subpath {
+    Subpath {
+        points: vec![from, to],
+        closed: false,
+    }
+}
+
+/// https://www.w3.org/TR/SVG/shapes.html#PolygonElement and
+/// https://www.w3.org/TR/SVG/shapes.html#PolylineElement
+pub fn poly(points: Vec<Point>, closed: bool) -> Option<Subpath> {
+    if points.len() < 2 {
+        return None;
+    }
+    Some(Subpath { points, closed })
+}
+
+/// Parses the `points` attribute of polygons and polylines. An odd coordinate is dropped,
+/// like browsers do.
+pub fn parse_points(value: &str) -> Option<Vec<Point>> {
+    let numbers = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse::<f32>().ok())
+        .collect::<Option<Vec<f32>>>()?;
+
+    Some(numbers.array_chunks::<2>().map(|[x, y]| (*x, *y)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{circle, parse_points, rect};
+    use crate::svg_path::DEFAULT_CURVE_TOLERANCE;
+
+    #[test]
+    fn circle_segments_adapt_to_radius() {
+        let pebble = circle(0.0, 0.0, 0.1, 0.01).unwrap();
+        let rock = circle(0.0, 0.0, 2.0, 0.01).unwrap();
+        let lake = circle(0.0, 0.0, 1000.0, 0.01).unwrap();
+
+        assert_eq!(pebble.points.len(), 8);
+        assert!(rock.points.len() > pebble.points.len());
+        assert!(lake.points.len() > rock.points.len());
+    }
+
+    #[test]
+    fn big_circles_stay_within_tolerance() {
+        let radius = 5000.0;
+        let basin = circle(0.0, 0.0, radius, DEFAULT_CURVE_TOLERANCE).unwrap();
+
+        let points = &basin.points;
+        let error = (0..points.len())
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                let middle = ((a.0 + b.0) as f64 / 2.0, (a.1 + b.1) as f64 / 2.0);
+                radius as f64 - (middle.0 * middle.0 + middle.1 * middle.1).sqrt()
+            })
+            .fold(0.0, f64::max);
+
+        // leaves some room for f32 rounding of the points
+        assert!(
+            error <= DEFAULT_CURVE_TOLERANCE as f64 * 1.1,
+            "{} segments are {} off",
+            points.len(),
+            error
+        );
+    }
+
+    #[test]
+    fn rounded_rect_radius_is_clamped() {
+        let rounded = rect((0.0, 0.0), 10.0, 4.0, Some(100.0), None, 0.1).unwrap();
+        let xs = rounded.points.iter().map(|p| p.0);
+        let ys = rounded.points.iter().map(|p| p.1);
+
+        assert_eq!(xs.clone().fold(f32::INFINITY, f32::min), 0.0);
+        assert_eq!(xs.fold(f32::NEG_INFINITY, f32::max), 10.0);
+        assert_eq!(ys.clone().fold(f32::INFINITY, f32::min), 0.0);
+        assert_eq!(ys.fold(f32::NEG_INFINITY, f32::max), 4.0);
+        assert!(rounded.points.len() > 4);
+    }
+
+    #[test]
+    fn points_attribute() {
+        assert_eq!(
+            parse_points("0,0 1 2,3,4 5"),
+            Some(vec![(0.0, 0.0), (1.0, 2.0), (3.0, 4.0)])
+        );
+        assert_eq!(parse_points("0,0 x"), None);
+    }
+}
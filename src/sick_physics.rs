@@ -1,14 +1,18 @@
+use nphysics2d::algebra::{Force2, ForceType};
 use nphysics2d::force_generator::DefaultForceGeneratorSet;
 use nphysics2d::joint::DefaultJointConstraintSet;
-use nphysics2d::nalgebra::Vector2;
+use nphysics2d::nalgebra::{Isometry2, Point2, Vector2};
 use nphysics2d::ncollide2d::pipeline::CollisionGroups;
-use nphysics2d::ncollide2d::shape::{Cuboid, ShapeHandle};
+use nphysics2d::ncollide2d::shape::{Compound, ConvexPolygon, Cuboid, Polyline, ShapeHandle};
 use nphysics2d::object::{
-    BodyPartHandle, ColliderDesc, DefaultBodyHandle, DefaultBodySet, DefaultColliderHandle,
+    Body, BodyPartHandle, ColliderDesc, DefaultBodyHandle, DefaultBodySet, DefaultColliderHandle,
     DefaultColliderSet, Ground, RigidBodyDesc,
 };
 use nphysics2d::world::{DefaultGeometricalWorld, DefaultMechanicalWorld};
 
+use crate::level::{Collider, WaterVolume};
+use crate::svg_path::{Point, Subpath};
+use crate::triangulation::{triangulate, FillRule};
 use crate::types::Triangle;
 
 type F = f32;
@@ -17,11 +21,19 @@ type F = f32;
 const GROUND_GROUP_ID: usize = 0;
 const PLAYER_GROUP_ID: usize = 1;
 const FISHING_ROD_GROUP_ID: usize = 2;
+const WATER_GROUP_ID: usize = 3;
+
+// how much water pushes up bodies inside it, relative to gravity. Above 1 they float up and
+// bob at the surface.
+const WATER_BUOYANCY: F = 1.5;
+// how much of their velocity bodies in water lose per second
+const WATER_DRAG: F = 2.0;
 
 pub struct CollisionGroupData {
     pub ground: CollisionGroups,
     pub player: CollisionGroups,
     pub fishing_rod: CollisionGroups,
+    pub water: CollisionGroups,
 }
 
 impl CollisionGroupData {
@@ -31,7 +43,8 @@ impl CollisionGroupData {
             player: CollisionGroups::new().with_membership(&[PLAYER_GROUP_ID]),
             fishing_rod: CollisionGroups::new()
                 .with_membership(&[FISHING_ROD_GROUP_ID])
-                .with_whitelist(&[GROUND_GROUP_ID]),
+                .with_whitelist(&[GROUND_GROUP_ID, WATER_GROUP_ID]),
+            water: CollisionGroups::new().with_membership(&[WATER_GROUP_ID]),
         }
     }
 }
@@ -48,11 +61,13 @@ pub struct Physics {
     pub force_generators: DefaultForceGeneratorSet<F>,
 
     pub collision_groups: CollisionGroupData,
+    // the sensors of the water volumes
+    water: Vec<DefaultColliderHandle>,
 }
 
 impl Physics {
-    pub fn new() -> Self {
-        let mut mechanical_world = DefaultMechanicalWorld::new(Vector2::new(0.0, 9.81));
+    pub fn new((gravity_x, gravity_y): (F, F)) -> Self {
+        let mut mechanical_world = DefaultMechanicalWorld::new(Vector2::new(gravity_x, gravity_y));
         let geometrical_world = DefaultGeometricalWorld::<F>::new();
         let bodies = DefaultBodySet::new();
         let colliders = DefaultColliderSet::new();
@@ -72,10 +87,12 @@ impl Physics {
             force_generators,
 
             collision_groups: CollisionGroupData::new(),
+            water: vec![],
         }
     }
 
     pub fn step(&mut self) {
+        self.apply_water();
         self.mechanical_world.step(
             &mut self.geometrical_world,
             &mut self.bodies,
@@ -85,15 +102,77 @@ impl Physics {
         );
     }
 
-    pub fn insert_ground(&mut self, x: f32, y: f32, half_width: f32, half_height: f32) {
-        let shape = ShapeHandle::new(Cuboid::new(Vector2::new(half_width, half_height)));
-        // let body = RigidBodyDesc::new().translation(Vector2::new(x, y)).build();
+    /// Adds the static colliders of a level element. Filled parts become a compound of
+    /// triangles, open lines become polylines.
+    pub fn insert_ground(&mut self, collider: &Collider) -> Vec<DefaultColliderHandle> {
+        let mut shapes = vec![];
+        if let Some(compound) = triangle_compound(&collider.triangles) {
+            shapes.push(compound);
+        }
+        for polyline in collider.polylines.iter() {
+            let points = polyline.iter().map(|(x, y)| Point2::new(*x, *y)).collect();
+            shapes.push(ShapeHandle::new(Polyline::new(points, None)));
+        }
+
+        let body_handle = self.bodies.insert(Ground::new());
+        shapes
+            .into_iter()
+            .map(|shape| {
+                let co = ColliderDesc::new(shape)
+                    .collision_groups(self.collision_groups.ground)
+                    .build(BodyPartHandle(body_handle, 0));
+                self.colliders.insert(co)
+            })
+            .collect()
+    }
+
+    // pushes up and slows down the bodies that the water sensors found in the last step
+    fn apply_water(&mut self) {
+        let mut bodies: Vec<DefaultBodyHandle> = self
+            .water
+            .iter()
+            // colliders are registered by the first step
+            .filter(|handle| {
+                let sensor = self.colliders.get(**handle);
+                sensor.and_then(|sensor| sensor.graph_index()).is_some()
+            })
+            .filter_map(|handle| {
+                self.geometrical_world
+                    .colliders_in_proximity_of(&self.colliders, *handle)
+            })
+            .flatten()
+            .map(|(_, collider)| collider.body())
+            .collect();
+        bodies.sort();
+        bodies.dedup();
+
+        let gravity = self.mechanical_world.gravity;
+        for handle in bodies {
+            if let Some(body) = self.bodies.rigid_body_mut(handle) {
+                let mass = body.augmented_mass().linear;
+                let velocity = body.velocity().linear;
+                let force = -(gravity * WATER_BUOYANCY + velocity * WATER_DRAG) * mass;
+                body.apply_force(0, &Force2::linear(force), ForceType::Force, true);
+            }
+        }
+    }
+
+    /// Adds a water volume as a sensor. Bodies inside float up and are slowed down.
+    pub fn insert_water(&mut self, volume: &WaterVolume) -> Option<DefaultColliderHandle> {
+        let outline = Subpath {
+            points: volume.polygon.clone(),
+            closed: true,
+        };
+        let shape = triangle_compound(&triangulate(&[outline], FillRule::NonZero).triangles)?;
+
         let body_handle = self.bodies.insert(Ground::new());
         let co = ColliderDesc::new(shape)
-            .translation(Vector2::new(x, y))
-            .collision_groups(self.collision_groups.ground)
+            .sensor(true)
+            .collision_groups(self.collision_groups.water)
             .build(BodyPartHandle(body_handle, 0));
-        self.colliders.insert(co);
+        let handle = self.colliders.insert(co);
+        self.water.push(handle);
+        Some(handle)
     }
 
     pub fn insert_cuboid(
@@ -118,6 +197,24 @@ impl Physics {
     }
 }
 
+// a concave shape made of convex triangles, degenerate triangles are left out
+fn triangle_compound(triangles: &[[Point; 3]]) -> Option<ShapeHandle<F>> {
+    let parts: Vec<_> = triangles
+        .iter()
+        .filter_map(|triangle| {
+            let points = triangle.map(|(x, y)| Point2::new(x, y));
+            ConvexPolygon::try_from_points(&points)
+        })
+        .map(|part| (Isometry2::identity(), ShapeHandle::new(part)))
+        .collect();
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(ShapeHandle::new(Compound::new(parts)))
+    }
+}
+
 // fn triangulate_cuboid(cuboid: &Cuboid<F>, position: &Isometry<f32>, triangles: &mut Vec<Triangle>) {
 //     let half_width = cuboid.half_extents.x;
 //     let half_height = cuboid.half_extents.y;
@@ -162,3 +259,35 @@ impl Physics {
 //         &self.triangles
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::Physics;
+    use crate::level::WaterVolume;
+
+    #[test]
+    fn bodies_float_in_water() {
+        let mut physics = Physics::new((0.0, 10.0));
+        physics.insert_water(&WaterVolume {
+            id: None,
+            polygon: vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)],
+            surface: 0.0,
+        });
+        let group = physics.collision_groups.player;
+        let (in_water, _) = physics.insert_cuboid(5.0, 5.0, 1.0, 1.0, group);
+        let (outside, _) = physics.insert_cuboid(20.0, 5.0, 1.0, 1.0, group);
+
+        let mut heights = vec![];
+        for _ in 0..600 {
+            physics.step();
+            let body = physics.bodies.rigid_body(in_water).unwrap();
+            heights.push(body.position().translation.y);
+        }
+
+        // it comes up and bobs at the surface
+        assert!(heights[60] < 4.0, "{heights:?}");
+        assert!(heights[500..].iter().all(|y| y.abs() < 1.0), "{heights:?}");
+        let body = physics.bodies.rigid_body(outside).unwrap();
+        assert!(body.position().translation.y > 100.0);
+    }
+}
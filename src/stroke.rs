@@ -0,0 +1,696 @@
+use std::f32::consts::PI;
+
+use crate::svg_path::{arc_segment_angle, Point, Subpath};
+use crate::triangulation::{triangulate, FillRule};
+
+/// https://www.w3.org/TR/SVG/painting.html#LineJoin
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+impl LineJoin {
+    pub fn parse(value: &str) -> Option<LineJoin> {
+        match value.trim() {
+            // the SVG 2 variants are close enough to a plain miter
+            "miter" | "miter-clip" | "arcs" => Some(LineJoin::Miter),
+            "round" => Some(LineJoin::Round),
+            "bevel" => Some(LineJoin::Bevel),
+            _ => None,
+        }
+    }
+}
+
+/// https://www.w3.org/TR/SVG/painting.html#LineCaps
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+impl LineCap {
+    pub fn parse(value: &str) -> Option<LineCap> {
+        match value.trim() {
+            "butt" => Some(LineCap::Butt),
+            "round" => Some(LineCap::Round),
+            "square" => Some(LineCap::Square),
+            _ => None,
+        }
+    }
+}
+
+/// The stroke properties that decide the shape of the outline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+    /// Alternating dash and gap lengths, empty for a solid stroke.
+    pub dash_array: Vec<f32>,
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle {
+            width: 1.0,
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+            miter_limit: 4.0,
+            dash_array: vec![],
+            dash_offset: 0.0,
+        }
+    }
+}
+
+/// Parses a `stroke-dasharray` value. `none` and patterns that would not draw anything
+/// result in an empty array, an odd number of values is repeated to yield an even number.
+/// Percentages are of `percentage_of`, see [`parse_length`].
+///
+/// See https://www.w3.org/TR/SVG/painting.html#StrokeDashing
+pub fn parse_dash_array(value: &str, percentage_of: f32) -> Option<Vec<f32>> {
+    if value.trim() == "none" {
+        return Some(vec![]);
+    }
+
+    let mut dashes = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|dash| !dash.is_empty())
+        .map(|dash| parse_length(dash, percentage_of))
+        .collect::<Option<Vec<f32>>>()?;
+
+    if dashes.iter().any(|dash| *dash < 0.0) {
+        return None;
+    }
+    if dashes.iter().sum::<f32>() <= 0.0 {
+        return Some(vec![]);
+    }
+    if dashes.len() % 2 == 1 {
+        dashes.extend_from_within(..);
+    }
+    Some(dashes)
+}
+
+// user units per unit, at the 96 user units per inch browsers use
+const LENGTH_UNITS: [(&str, f32); 9] = [
+    ("px", 1.0),
+    ("in", 96.0),
+    ("cm", 96.0 / 2.54),
+    ("mm", 96.0 / 25.4),
+    ("Q", 96.0 / 101.6),
+    ("pt", 96.0 / 72.0),
+    ("pc", 16.0),
+    // levels have no text, so the font size is always the default one
+    ("em", 16.0),
+    ("ex", 8.0),
+];
+
+/// Parses a length like `2`, `0.5mm` or `1pt` into user units. Percentages are of
+/// `percentage_of`, which is the normalized diagonal of the viewport for stroke lengths.
+///
+/// See https://www.w3.org/TR/SVG/coords.html#Units
+pub fn parse_length(value: &str, percentage_of: f32) -> Option<f32> {
+    let value = value.trim();
+    let (number, factor) = match value.strip_suffix('%') {
+        Some(number) => (number, percentage_of / 100.0),
+        None => LENGTH_UNITS
+            .iter()
+            .find_map(|(unit, factor)| Some((value.strip_suffix(unit)?, *factor)))
+            .unwrap_or((value, 1.0)),
+    };
+    Some(number.trim().parse::<f32>().ok()? * factor)
+}
+
+/// Tessellates the outline of `subpaths` into triangles.
+///
+/// The outlines of all segments, joins, caps and dashes are filled as one shape with the
+/// nonzero rule, so the triangles do not overlap and a translucent stroke is blended once
+/// everywhere, even where the path crosses itself.
+pub fn stroke(subpaths: &[Subpath], style: &StrokeStyle, tolerance: f32) -> Vec<[Point; 3]> {
+    if style.width <= 0.0 {
+        return vec![];
+    }
+
+    let mut outlines = vec![];
+    for subpath in subpaths {
+        let mut points = subpath.points.clone();
+        points.dedup();
+        if subpath.closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+
+        if style.dash_array.is_empty() {
+            // a lone point has no direction, its caps are aligned with the x axis
+            let direction = (1.0, 0.0);
+            outline(
+                &points,
+                subpath.closed,
+                direction,
+                style,
+                tolerance,
+                &mut outlines,
+            );
+        } else {
+            let dashes = dash(
+                &points,
+                subpath.closed,
+                &style.dash_array,
+                style.dash_offset,
+            );
+            for mut dash in dashes {
+                dash.points.dedup();
+                outline(
+                    &dash.points,
+                    dash.closed,
+                    dash.direction,
+                    style,
+                    tolerance,
+                    &mut outlines,
+                );
+            }
+        }
+    }
+
+    let outlines: Vec<Subpath> = outlines
+        .into_iter()
+        .map(|points| Subpath {
+            points,
+            closed: true,
+        })
+        .collect();
+    triangulate(&outlines, FillRule::NonZero).triangles
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn add(a: Point, b: Point) -> Point {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn scale(a: Point, s: f32) -> Point {
+    (a.0 * s, a.1 * s)
+}
+
+fn length(a: Point) -> f32 {
+    a.0.hypot(a.1)
+}
+
+fn direction(from: Point, to: Point) -> Point {
+    let d = sub(to, from);
+    scale(d, 1.0 / length(d))
+}
+
+// the left hand normal of a unit direction, scaled to `half_width`
+fn normal(d: Point, half_width: f32) -> Point {
+    (-d.1 * half_width, d.0 * half_width)
+}
+
+// a piece of a dashed polyline
+struct Dash {
+    points: Vec<Point>,
+    // the direction of the path where the dash starts, which orients the caps of a dash
+    // without length
+    direction: Point,
+    // the dash goes all the way around a closed path
+    closed: bool,
+}
+
+/// Splits a polyline into the pieces covered by the dashes. On a closed polyline, the dash
+/// going over the start point is a single piece.
+fn dash(points: &[Point], closed: bool, dashes: &[f32], offset: f32) -> Vec<Dash> {
+    let mut points = points.to_vec();
+    if points.is_empty() {
+        return vec![];
+    }
+    if closed {
+        points.push(points[0]);
+    }
+    let pattern: f32 = dashes.iter().sum();
+
+    // find where in the pattern the polyline starts, a dash without length right at the start
+    // is kept
+    let mut index = 0;
+    let mut remaining = dashes[0];
+    let mut skip = offset.rem_euclid(pattern);
+    while skip > 0.0 && skip >= remaining {
+        skip -= remaining;
+        index = (index + 1) % dashes.len();
+        remaining = dashes[index];
+    }
+    remaining -= skip;
+
+    let starts_in_dash = index % 2 == 0;
+    let mut direction = match points[..] {
+        [a, b, ..] => self::direction(a, b),
+        _ => (1.0, 0.0),
+    };
+    let mut pieces = vec![];
+    let mut current = vec![];
+    if starts_in_dash {
+        current.push(points[0]);
+    }
+
+    for segment in points.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let segment_length = length(sub(b, a));
+        let mut travelled = 0.0;
+
+        while segment_length - travelled > remaining {
+            travelled += remaining;
+            current.push(add(a, scale(sub(b, a), travelled / segment_length)));
+            if index % 2 == 0 {
+                pieces.push(Dash {
+                    points: std::mem::take(&mut current),
+                    direction,
+                    closed: false,
+                });
+            } else {
+                direction = self::direction(a, b);
+            }
+            index = (index + 1) % dashes.len();
+            remaining = dashes[index];
+        }
+
+        remaining -= segment_length - travelled;
+        if index % 2 == 0 {
+            current.push(b);
+        }
+    }
+
+    if index % 2 == 0 {
+        pieces.push(Dash {
+            points: current,
+            direction,
+            closed: false,
+        });
+        if closed && starts_in_dash {
+            if pieces.len() == 1 {
+                // no gap anywhere
+                pieces[0].closed = true;
+                pieces[0].points.pop();
+            } else {
+                let first = pieces.remove(0);
+                let last = pieces.last_mut().unwrap();
+                last.points.extend_from_slice(&first.points[1..]);
+            }
+        }
+    }
+
+    pieces.retain(|piece| !piece.points.is_empty());
+    pieces
+}
+
+// the rings around the stroke of a polyline, its area has a nonzero winding number. `direction`
+// orients the caps if the polyline is a single point.
+fn outline(
+    points: &[Point],
+    closed: bool,
+    direction: Point,
+    style: &StrokeStyle,
+    tolerance: f32,
+    out: &mut Vec<Vec<Point>>,
+) {
+    let reversed: Vec<Point> = points.iter().rev().copied().collect();
+    match points {
+        [] => {}
+        // only round and square caps draw something for a point
+        [p] if style.cap != LineCap::Butt => {
+            let n = normal(direction, style.width / 2.0);
+            let mut ring = vec![add(*p, n)];
+            cap(*p, direction, style, tolerance, &mut ring);
+            ring.push(sub(*p, n));
+            cap(*p, scale(direction, -1.0), style, tolerance, &mut ring);
+            out.push(ring);
+        }
+        [_] => {}
+        _ if closed => {
+            out.push(side(points, true, style, tolerance));
+            out.push(side(&reversed, true, style, tolerance));
+        }
+        _ => {
+            let n = points.len();
+            let mut ring = side(points, false, style, tolerance);
+            let end = self::direction(points[n - 2], points[n - 1]);
+            cap(points[n - 1], end, style, tolerance, &mut ring);
+            ring.extend(side(&reversed, false, style, tolerance));
+            let start = self::direction(points[1], points[0]);
+            cap(points[0], start, style, tolerance, &mut ring);
+            out.push(ring);
+        }
+    }
+}
+
+// the offset of a polyline of at least two points to its left, with the joins at its corners
+fn side(points: &[Point], closed: bool, style: &StrokeStyle, tolerance: f32) -> Vec<Point> {
+    let half_width = style.width / 2.0;
+    let n = points.len();
+    let mut out = vec![];
+
+    if !closed {
+        out.push(add(
+            points[0],
+            normal(direction(points[0], points[1]), half_width),
+        ));
+    }
+    let corners = if closed { 0..n } else { 1..n - 1 };
+    for i in corners {
+        let (previous, p, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+        join(previous, p, next, style, tolerance, &mut out);
+    }
+    if !closed {
+        out.push(add(
+            points[n - 1],
+            normal(direction(points[n - 2], points[n - 1]), half_width),
+        ));
+    }
+    out
+}
+
+// the points of the left offset around the corner `p` between the segments from `previous`
+// and to `next`
+fn join(
+    previous: Point,
+    p: Point,
+    next: Point,
+    style: &StrokeStyle,
+    tolerance: f32,
+    out: &mut Vec<Point>,
+) {
+    let half_width = style.width / 2.0;
+    let (d0, d1) = (direction(previous, p), direction(p, next));
+    let (n0, n1) = (normal(d0, half_width), normal(d1, half_width));
+    let cross = d0.0 * d1.1 - d0.1 * d1.0;
+    let dot = d0.0 * d1.0 + d0.1 * d1.1;
+    if cross.abs() < 1e-6 && dot > 0.0 {
+        out.push(add(p, n0));
+        return;
+    }
+
+    if cross > 0.0 {
+        // on the inside of the turn the offsets cross each other. Where they cross is the
+        // corner, unless the segments are too short for that, then going through `p` leaves a
+        // loop that is covered by the segments anyway. Each corner may use up half of a
+        // segment, so they don't get in each other's way.
+        let cut = half_width * cross / (1.0 + dot);
+        let shortest = length(sub(p, previous)).min(length(sub(next, p)));
+        if dot > -1.0 && cut <= shortest / 2.0 {
+            out.push(add(p, scale(add(n0, n1), 1.0 / (1.0 + dot))));
+        } else {
+            out.extend([add(p, n0), p, add(p, n1)]);
+        }
+        return;
+    }
+
+    out.push(add(p, n0));
+    match style.join {
+        LineJoin::Round => arc(p, n0, -cross.atan2(dot).abs(), tolerance, out),
+        // the miter length relative to the stroke width is 1 / cos(turn / 2)
+        LineJoin::Miter if dot > -1.0 && ((1.0 + dot) / 2.0).sqrt() * style.miter_limit >= 1.0 => {
+            out.push(add(p, scale(add(n0, n1), 1.0 / (1.0 + dot))));
+        }
+        _ => {}
+    }
+    out.push(add(p, n1));
+}
+
+// the points around the end `p` of a line, from its left to its right side, without those.
+// `d` points away from the line.
+fn cap(p: Point, d: Point, style: &StrokeStyle, tolerance: f32, out: &mut Vec<Point>) {
+    let half_width = style.width / 2.0;
+    let n = normal(d, half_width);
+
+    match style.cap {
+        LineCap::Butt => {}
+        LineCap::Square => {
+            let extension = scale(d, half_width);
+            out.push(add(add(p, n), extension));
+            out.push(add(sub(p, n), extension));
+        }
+        LineCap::Round => arc(p, n, -PI, tolerance, out),
+    }
+}
+
+// the points of an arc around `center` from `start` (relative to the center), sweeping by
+// `angle`, without its ends
+fn arc(center: Point, start: Point, angle: f32, tolerance: f32, out: &mut Vec<Point>) {
+    let radius = length(start);
+    let n = (angle.abs() / arc_segment_angle(radius, tolerance))
+        .ceil()
+        .max(1.0) as usize;
+    let start_angle = start.1.atan2(start.0);
+
+    for i in 1..n {
+        let (sin, cos) = (start_angle + angle * i as f32 / n as f32).sin_cos();
+        out.push(add(center, (radius * cos, radius * sin)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_dash_array, parse_length, stroke, LineCap, LineJoin, StrokeStyle};
+    use crate::svg_path::{Point, Subpath};
+    use crate::triangulation::{contains, signed_area};
+
+    fn open(points: &[Point]) -> Vec<Subpath> {
+        vec![Subpath {
+            points: points.to_vec(),
+            closed: false,
+        }]
+    }
+
+    fn area(triangles: &[[Point; 3]]) -> f32 {
+        triangles.iter().map(|t| signed_area(t).abs() / 2.0).sum()
+    }
+
+    fn covers(triangles: &[[Point; 3]], point: Point) -> bool {
+        triangles.iter().any(|t| contains(t, point))
+    }
+
+    #[test]
+    fn caps() {
+        let line = open(&[(0.0, 0.0), (10.0, 0.0)]);
+        let style = |cap| StrokeStyle {
+            width: 2.0,
+            cap,
+            ..Default::default()
+        };
+
+        assert_eq!(area(&stroke(&line, &style(LineCap::Butt), 0.01)), 20.0);
+        assert_eq!(area(&stroke(&line, &style(LineCap::Square), 0.01)), 24.0);
+
+        let round = area(&stroke(&line, &style(LineCap::Round), 0.01));
+        assert!(
+            (round - (20.0 + std::f32::consts::PI)).abs() < 0.05,
+            "{round}"
+        );
+    }
+
+    #[test]
+    fn joins() {
+        let corner = open(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]);
+        let style = |join| StrokeStyle {
+            width: 2.0,
+            join,
+            ..Default::default()
+        };
+
+        let miter = stroke(&corner, &style(LineJoin::Miter), 0.01);
+        let round = stroke(&corner, &style(LineJoin::Round), 0.01);
+        let bevel = stroke(&corner, &style(LineJoin::Bevel), 0.01);
+
+        assert!(covers(&miter, (10.9, -0.9)));
+        assert!(!covers(&round, (10.9, -0.9)));
+        assert!(covers(&round, (10.6, -0.6)));
+        assert!(!covers(&bevel, (10.6, -0.6)));
+        assert!(covers(&bevel, (10.4, -0.4)));
+        // the inside of the corner is covered by the segments already
+        assert!(covers(&bevel, (9.1, 0.9)));
+    }
+
+    #[test]
+    fn miter_limit() {
+        let spike = open(&[(0.0, 0.0), (10.0, 0.0), (0.0, 1.0)]);
+        let style = StrokeStyle {
+            width: 1.0,
+            ..Default::default()
+        };
+
+        let limited = stroke(&spike, &style, 0.01);
+        let unlimited = stroke(
+            &spike,
+            &StrokeStyle {
+                miter_limit: 100.0,
+                ..style
+            },
+            0.01,
+        );
+
+        assert!(!covers(&limited, (11.0, -0.05)));
+        assert!(covers(&unlimited, (11.0, -0.05)));
+    }
+
+    #[test]
+    fn dashes() {
+        let line = open(&[(0.0, 0.0), (4.0, 0.0), (10.0, 0.0)]);
+        let style = StrokeStyle {
+            width: 1.0,
+            dash_array: vec![2.0, 3.0],
+            ..Default::default()
+        };
+
+        let dashed = stroke(&line, &style, 0.01);
+        assert_eq!(area(&dashed), 4.0);
+        assert!(covers(&dashed, (1.0, 0.2)));
+        assert!(!covers(&dashed, (3.0, 0.2)));
+        assert!(covers(&dashed, (6.0, 0.2)));
+
+        let shifted = stroke(
+            &line,
+            &StrokeStyle {
+                dash_offset: 1.0,
+                ..style
+            },
+            0.01,
+        );
+        assert_eq!(area(&shifted), 1.0 + 2.0 + 1.0);
+        assert!(covers(&shifted, (0.5, 0.2)));
+        assert!(covers(&shifted, (9.5, 0.2)));
+    }
+
+    #[test]
+    fn closed_dashes_join_at_the_start() {
+        let square = vec![Subpath {
+            points: vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)],
+            closed: true,
+        }];
+        // the last dash starts at (0, 3) and goes on to (3, 0)
+        let style = StrokeStyle {
+            width: 1.0,
+            dash_array: vec![6.0, 2.0],
+            dash_offset: 3.0,
+            ..Default::default()
+        };
+
+        let dashed = stroke(&square, &style, 0.01);
+        assert!(covers(&dashed, (-0.4, -0.4)));
+        assert!(covers(&dashed, (-0.4, 2.9)));
+        assert!(!covers(&dashed, (-0.4, 3.1)));
+    }
+
+    #[test]
+    fn dashes_without_length() {
+        let line = open(&[(0.0, 0.0), (10.0, 0.0)]);
+        let style = |cap| StrokeStyle {
+            width: 1.0,
+            cap,
+            dash_array: vec![0.0, 2.0],
+            ..Default::default()
+        };
+
+        let dots = stroke(&line, &style(LineCap::Round), 0.01);
+        assert!(covers(&dots, (0.0, 0.4)));
+        assert!(covers(&dots, (4.0, -0.4)));
+        assert!(!covers(&dots, (4.0, -0.6)));
+        assert!(!covers(&dots, (1.0, 0.0)));
+
+        let squares = stroke(&line, &style(LineCap::Square), 0.01);
+        assert!(covers(&squares, (2.4, 0.4)));
+        assert!(!covers(&squares, (2.6, 0.0)));
+
+        assert!(stroke(&line, &style(LineCap::Butt), 0.01).is_empty());
+    }
+
+    #[test]
+    fn closed_subpaths_have_no_caps() {
+        let square = vec![Subpath {
+            points: vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)],
+            closed: true,
+        }];
+        let style = StrokeStyle {
+            width: 1.0,
+            join: LineJoin::Bevel,
+            cap: LineCap::Square,
+            ..Default::default()
+        };
+
+        let triangles = stroke(&square, &style, 0.01);
+        // 5x5 without the 3x3 inside and the 4 beveled corners
+        assert_eq!(area(&triangles), 25.0 - 9.0 - 4.0 * 0.125);
+        assert!(!covers(&triangles, (2.0, 2.0)));
+    }
+
+    #[test]
+    fn no_overlaps() {
+        let style = StrokeStyle {
+            width: 2.0,
+            ..Default::default()
+        };
+
+        // the segments overlap inside the corner
+        let corner = open(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]);
+        assert_eq!(area(&stroke(&corner, &style, 0.01)), 22.0 + 18.0);
+
+        let cross = [
+            open(&[(0.0, 0.0), (10.0, 0.0)]),
+            open(&[(5.0, -5.0), (5.0, 5.0)]),
+        ]
+        .concat();
+        assert_eq!(area(&stroke(&cross, &style, 0.01)), 20.0 + 20.0 - 4.0);
+
+        // turns back on itself, so its round join and caps overlap with the segments
+        let zigzag = open(&[(0.0, 0.0), (10.0, 0.0), (1.0, 1.0)]);
+        let round = StrokeStyle {
+            join: LineJoin::Round,
+            cap: LineCap::Round,
+            ..style
+        };
+        let triangles = stroke(&zigzag, &round, 0.01);
+        for x in -20..=120 {
+            for y in -20..=30 {
+                // off the grid, so no point is on an edge
+                let point = (x as f32 / 10.0 + 0.0137, y as f32 / 10.0 + 0.0071);
+                let count = triangles.iter().filter(|t| contains(*t, point)).count();
+                assert!(count <= 1, "{point:?} is covered {count} times");
+            }
+        }
+        assert!(covers(&triangles, (10.9, 0.3)));
+        assert!(covers(&triangles, (5.0, 0.5)));
+    }
+
+    #[test]
+    fn dash_array() {
+        assert_eq!(parse_dash_array("none", 100.0), Some(vec![]));
+        assert_eq!(parse_dash_array("0.1, 0.4", 100.0), Some(vec![0.1, 0.4]));
+        assert_eq!(
+            parse_dash_array("1 2 3", 100.0),
+            Some(vec![1.0, 2.0, 3.0, 1.0, 2.0, 3.0])
+        );
+        assert_eq!(parse_dash_array("0 0", 100.0), Some(vec![]));
+        assert_eq!(parse_dash_array("1 -2", 100.0), None);
+        assert_eq!(parse_dash_array("1 two", 100.0), None);
+        assert_eq!(parse_dash_array("1% 3pt", 100.0), Some(vec![1.0, 4.0]));
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(parse_length(" 2 ", 100.0), Some(2.0));
+        assert_eq!(parse_length("1.5px", 100.0), Some(1.5));
+        assert_eq!(parse_length("1in", 100.0), Some(96.0));
+        assert_eq!(parse_length("2.54cm", 100.0), Some(96.0));
+        assert_eq!(parse_length("25.4mm", 100.0), Some(96.0));
+        assert_eq!(parse_length("3pt", 100.0), Some(4.0));
+        assert_eq!(parse_length("1pc", 100.0), Some(16.0));
+        assert_eq!(parse_length("50%", 10.0), Some(5.0));
+        assert_eq!(parse_length("1e1", 100.0), Some(10.0));
+        assert_eq!(parse_length("wide", 100.0), None);
+        assert_eq!(parse_length("1km", 100.0), None);
+    }
+}
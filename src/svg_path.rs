@@ -0,0 +1,610 @@
+use std::f32::consts::PI;
+
+use svg::node::element::path::{Command, Data, Position};
+
+pub type Point = (f32, f32);
+
+/// Max distance between a curve and the polyline replacing it, in SVG units.
+pub const DEFAULT_CURVE_TOLERANCE: f32 = 0.01;
+
+// upper bound so a broken tolerance can't allocate the world
+const MAX_SEGMENTS: usize = 1024;
+
+fn lerp(a: Point, b: Point, t: f32) -> Point {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+fn length((x, y): Point) -> f32 {
+    (x * x + y * y).sqrt()
+}
+
+// to the segment and not the whole line, a control point on the line but past an end point
+// still pulls the curve past it
+fn distance_to_segment(p: Point, a: Point, b: Point) -> f32 {
+    let chord = (b.0 - a.0, b.1 - a.1);
+    let chord_length_squared = chord.0 * chord.0 + chord.1 * chord.1;
+    if chord_length_squared == 0.0 {
+        return length((p.0 - a.0, p.1 - a.1));
+    }
+    let t =
+        (((p.0 - a.0) * chord.0 + (p.1 - a.1) * chord.1) / chord_length_squared).clamp(0.0, 1.0);
+    let closest = lerp(a, b, t);
+    length((p.0 - closest.0, p.1 - closest.1))
+}
+
+fn segment_count(n: f32) -> usize {
+    if n.is_finite() {
+        (n.ceil() as usize).clamp(1, MAX_SEGMENTS)
+    } else {
+        1
+    }
+}
+
+/// Reflects `control` on `current`, used by the smooth curve commands.
+pub fn reflect(control: Point, current: Point) -> Point {
+    (2.0 * current.0 - control.0, 2.0 * current.1 - control.1)
+}
+
+/// Flattens a cubic Bézier curve. The start point is not included, the end point is.
+///
+/// The segment count comes from the second derivative bound, so the chord error stays
+/// below `tolerance` without recursive subdivision.
+pub fn flatten_cubic(p0: Point, p1: Point, p2: Point, p3: Point, tolerance: f32) -> Vec<Point> {
+    if distance_to_segment(p1, p0, p3).max(distance_to_segment(p2, p0, p3)) <= tolerance {
+        return vec![p3];
+    }
+
+    let dd = length((p0.0 - 2.0 * p1.0 + p2.0, p0.1 - 2.0 * p1.1 + p2.1))
+        .max(length((p1.0 - 2.0 * p2.0 + p3.0, p1.1 - 2.0 * p2.1 + p3.1)));
+    let n = segment_count((0.75 * dd / tolerance).sqrt());
+
+    let mut points: Vec<Point> = (1..n)
+        .map(|i| {
+            let t = i as f32 / n as f32;
+            let a = lerp(p0, p1, t);
+            let b = lerp(p1, p2, t);
+            let c = lerp(p2, p3, t);
+            lerp(lerp(a, b, t), lerp(b, c, t), t)
+        })
+        .collect();
+    points.push(p3);
+    points
+}
+
+/// Flattens a quadratic Bézier curve. The start point is not included, the end point is.
+pub fn flatten_quadratic(p0: Point, p1: Point, p2: Point, tolerance: f32) -> Vec<Point> {
+    if distance_to_segment(p1, p0, p2) <= tolerance {
+        return vec![p2];
+    }
+
+    let dd = length((p0.0 - 2.0 * p1.0 + p2.0, p0.1 - 2.0 * p1.1 + p2.1));
+    let n = segment_count((0.25 * dd / tolerance).sqrt());
+
+    let mut points: Vec<Point> = (1..n)
+        .map(|i| {
+            let t = i as f32 / n as f32;
+            lerp(lerp(p0, p1, t), lerp(p1, p2, t), t)
+        })
+        .collect();
+    points.push(p2);
+    points
+}
+
+fn vector_angle(u: Point, v: Point) -> f32 {
+    let angle = (u.0 * v.1 - u.1 * v.0).atan2(u.0 * v.0 + u.1 * v.1);
+    if angle.is_nan() {
+        0.0
+    } else {
+        angle
+    }
+}
+
+/// The largest angle a chord of a circle with `radius` may span while staying within
+/// `tolerance` of the circle.
+pub fn arc_segment_angle(radius: f32, tolerance: f32) -> f32 {
+    if tolerance < radius {
+        // 2 * acos(1 - tolerance / radius), without rounding to 0 for big radii
+        4.0 * (tolerance / (2.0 * radius)).sqrt().asin()
+    } else {
+        PI / 2.0
+    }
+}
+
+/// Flattens an elliptical arc given in SVG endpoint parameterization.
+/// The start point is not included, the end point is.
+///
+/// See https://www.w3.org/TR/SVG/implnote.html#ArcConversionEndpointToCenter
+#[allow(clippy::too_many_arguments)]
+pub fn flatten_arc(
+    from: Point,
+    rx: f32,
+    ry: f32,
+    x_axis_rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: Point,
+    tolerance: f32,
+) -> Vec<Point> {
+    if from == to {
+        return vec![];
+    }
+
+    let mut rx = rx.abs();
+    let mut ry = ry.abs();
+    if rx == 0.0 || ry == 0.0 {
+        return vec![to];
+    }
+
+    let phi = x_axis_rotation.to_radians();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+
+    // step 1: compute (x1', y1')
+    let dx = (from.0 - to.0) / 2.0;
+    let dy = (from.1 - to.1) / 2.0;
+    let x1 = cos_phi * dx + sin_phi * dy;
+    let y1 = -sin_phi * dx + cos_phi * dy;
+
+    // correct out-of-range radii
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    // step 2: compute (cx', cy')
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let sign = if large_arc != sweep { 1.0 } else { -1.0 };
+    let coef = sign * (numerator / denominator).max(0.0).sqrt();
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+
+    // step 3: compute (cx, cy)
+    let cx = cos_phi * cx1 - sin_phi * cy1 + (from.0 + to.0) / 2.0;
+    let cy = sin_phi * cx1 + cos_phi * cy1 + (from.1 + to.1) / 2.0;
+
+    // step 4: compute the start angle and the sweep
+    let u = ((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let v = ((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    let theta = vector_angle((1.0, 0.0), u);
+    let mut delta = vector_angle(u, v);
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    } else if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    }
+
+    let n = segment_count(delta.abs() / arc_segment_angle(rx.max(ry), tolerance));
+
+    let mut points: Vec<Point> = (1..n)
+        .map(|i| {
+            let angle = theta + delta * i as f32 / n as f32;
+            let (sin, cos) = angle.sin_cos();
+            (
+                cx + rx * cos * cos_phi - ry * sin * sin_phi,
+                cy + rx * cos * sin_phi + ry * sin * cos_phi,
+            )
+        })
+        .collect();
+    points.push(to);
+    points
+}
+
+/// A flattened subpath. Closed subpaths don't repeat their start point at the end.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Subpath {
+    pub points: Vec<Point>,
+    pub closed: bool,
+}
+
+/// Interprets path data as described in https://www.w3.org/TR/SVG/paths.html
+///
+/// Keeps track of the subpath start point, the current point and the control point of the
+/// previous curve segment, so relative commands after `Z` and the smooth curve commands
+/// behave like they do in browsers and Inkscape.
+pub struct PathInterpreter {
+    tolerance: f32,
+    subpaths: Vec<Subpath>,
+    subpath: Option<Subpath>,
+    start: Point,
+    current: Point,
+    cubic_control: Option<Point>,
+    quadratic_control: Option<Point>,
+}
+
+impl PathInterpreter {
+    pub fn new(tolerance: f32) -> Self {
+        PathInterpreter {
+            tolerance,
+            subpaths: vec![],
+            subpath: None,
+            start: (0.0, 0.0),
+            current: (0.0, 0.0),
+            cubic_control: None,
+            quadratic_control: None,
+        }
+    }
+
+    fn absolute(&self, position: Position, (x, y): Point) -> Point {
+        match position {
+            Position::Absolute => (x, y),
+            Position::Relative => (self.current.0 + x, self.current.1 + y),
+        }
+    }
+
+    fn finish_subpath(&mut self) {
+        if let Some(mut subpath) = self.subpath.take() {
+            if subpath.closed
+                && subpath.points.len() > 1
+                && subpath.points.first() == subpath.points.last()
+            {
+                subpath.points.pop();
+            }
+            if subpath.points.len() > 1 {
+                self.subpaths.push(subpath);
+            }
+        }
+    }
+
+    fn push(&mut self, points: Vec<Point>) {
+        let start = self.current;
+        let subpath = self.subpath.get_or_insert_with(|| Subpath {
+            points: vec![start],
+            closed: false,
+        });
+        if let Some(last) = points.last() {
+            self.current = *last;
+        }
+        subpath.points.extend(points);
+    }
+
+    pub fn move_to(&mut self, to: Point) {
+        self.finish_subpath();
+        self.start = to;
+        self.current = to;
+        self.subpath = Some(Subpath {
+            points: vec![to],
+            closed: false,
+        });
+    }
+
+    pub fn line_to(&mut self, to: Point) {
+        self.push(vec![to]);
+    }
+
+    pub fn cubic_to(&mut self, control_1: Point, control_2: Point, to: Point) {
+        let points = flatten_cubic(self.current, control_1, control_2, to, self.tolerance);
+        self.push(points);
+        self.cubic_control = Some(control_2);
+    }
+
+    pub fn quadratic_to(&mut self, control: Point, to: Point) {
+        let points = flatten_quadratic(self.current, control, to, self.tolerance);
+        self.push(points);
+        self.quadratic_control = Some(control);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn arc_to(
+        &mut self,
+        rx: f32,
+        ry: f32,
+        x_axis_rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        to: Point,
+    ) {
+        let points = flatten_arc(
+            self.current,
+            rx,
+            ry,
+            x_axis_rotation,
+            large_arc,
+            sweep,
+            to,
+            self.tolerance,
+        );
+        self.push(points);
+    }
+
+    pub fn close(&mut self) {
+        if let Some(subpath) = &mut self.subpath {
+            subpath.closed = true;
+        }
+        self.finish_subpath();
+        // the next segment, if it isn't a moveto, starts a new subpath here
+        self.current = self.start;
+    }
+
+    pub fn command(&mut self, command: &Command) {
+        // the smooth variants only reflect the control point of a directly preceding curve
+        match command {
+            Command::CubicCurve(..) | Command::SmoothCubicCurve(..) => {
+                self.quadratic_control = None;
+            }
+            Command::QuadraticCurve(..) | Command::SmoothQuadraticCurve(..) => {
+                self.cubic_control = None;
+            }
+            _ => {
+                self.cubic_control = None;
+                self.quadratic_control = None;
+            }
+        }
+
+        match command {
+            Command::Move(position, parameters) => {
+                let mut point_it = parameters.array_chunks::<2>();
+
+                if let Some([x, y]) = point_it.next() {
+                    let to = self.absolute(*position, (*x, *y));
+                    self.move_to(to);
+                }
+
+                // additional pairs are implicit lineto commands
+                for [x, y] in point_it {
+                    let to = self.absolute(*position, (*x, *y));
+                    self.line_to(to);
+                }
+            }
+            Command::Line(position, parameters) => {
+                for [x, y] in parameters.array_chunks::<2>() {
+                    let to = self.absolute(*position, (*x, *y));
+                    self.line_to(to);
+                }
+            }
+            Command::HorizontalLine(position, parameters) => {
+                for x in parameters.iter() {
+                    let to = match position {
+                        Position::Absolute => (*x, self.current.1),
+                        Position::Relative => (self.current.0 + x, self.current.1),
+                    };
+                    self.line_to(to);
+                }
+            }
+            Command::VerticalLine(position, parameters) => {
+                for y in parameters.iter() {
+                    let to = match position {
+                        Position::Absolute => (self.current.0, *y),
+                        Position::Relative => (self.current.0, self.current.1 + y),
+                    };
+                    self.line_to(to);
+                }
+            }
+            Command::CubicCurve(position, parameters) => {
+                for [x1, y1, x2, y2, x, y] in parameters.array_chunks::<6>() {
+                    let control_1 = self.absolute(*position, (*x1, *y1));
+                    let control_2 = self.absolute(*position, (*x2, *y2));
+                    let to = self.absolute(*position, (*x, *y));
+                    self.cubic_to(control_1, control_2, to);
+                }
+            }
+            Command::SmoothCubicCurve(position, parameters) => {
+                for [x2, y2, x, y] in parameters.array_chunks::<4>() {
+                    let control_1 = self
+                        .cubic_control
+                        .map_or(self.current, |c| reflect(c, self.current));
+                    let control_2 = self.absolute(*position, (*x2, *y2));
+                    let to = self.absolute(*position, (*x, *y));
+                    self.cubic_to(control_1, control_2, to);
+                }
+            }
+            Command::QuadraticCurve(position, parameters) => {
+                for [x1, y1, x, y] in parameters.array_chunks::<4>() {
+                    let control = self.absolute(*position, (*x1, *y1));
+                    let to = self.absolute(*position, (*x, *y));
+                    self.quadratic_to(control, to);
+                }
+            }
+            Command::SmoothQuadraticCurve(position, parameters) => {
+                for [x, y] in parameters.array_chunks::<2>() {
+                    let control = self
+                        .quadratic_control
+                        .map_or(self.current, |c| reflect(c, self.current));
+                    let to = self.absolute(*position, (*x, *y));
+                    self.quadratic_to(control, to);
+                }
+            }
+            Command::EllipticalArc(position, parameters) => {
+                for [rx, ry, x_axis_rotation, large_arc_flag, sweep_flag, x, y] in
+                    parameters.array_chunks::<7>()
+                {
+                    let to = self.absolute(*position, (*x, *y));
+                    self.arc_to(
+                        *rx,
+                        *ry,
+                        *x_axis_rotation,
+                        *large_arc_flag != 0.0,
+                        *sweep_flag != 0.0,
+                        to,
+                    );
+                }
+            }
+            Command::Close => self.close(),
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<Subpath> {
+        self.finish_subpath();
+        self.subpaths
+    }
+}
+
+/// Flattens all subpaths of `data`. Subpaths with less than two points are dropped.
+pub fn flatten_path(data: &Data, tolerance: f32) -> Vec<Subpath> {
+    let mut interpreter = PathInterpreter::new(tolerance);
+    for command in data.iter() {
+        interpreter.command(command);
+    }
+    interpreter.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flatten_path, Point, Subpath};
+    use svg::node::element::path::Data;
+
+    fn subpaths(d: &str) -> Vec<Subpath> {
+        flatten_path(&Data::parse(d).unwrap(), 0.1)
+    }
+
+    fn closed(points: &[Point]) -> Subpath {
+        Subpath {
+            points: points.to_vec(),
+            closed: true,
+        }
+    }
+
+    fn open(points: &[Point]) -> Subpath {
+        Subpath {
+            points: points.to_vec(),
+            closed: false,
+        }
+    }
+
+    #[test]
+    fn absolute_lines() {
+        assert_eq!(
+            subpaths("M10,10 L20,10 L20,20 Z"),
+            vec![closed(&[(10.0, 10.0), (20.0, 10.0), (20.0, 20.0)])]
+        );
+    }
+
+    #[test]
+    fn implicit_lineto_after_moveto() {
+        assert_eq!(
+            subpaths("M10,10 20,10 20,20z"),
+            vec![closed(&[(10.0, 10.0), (20.0, 10.0), (20.0, 20.0)])]
+        );
+        assert_eq!(
+            subpaths("m10,10 10,0 0,10 z"),
+            vec![closed(&[(10.0, 10.0), (20.0, 10.0), (20.0, 20.0)])]
+        );
+    }
+
+    #[test]
+    fn no_origin_point() {
+        assert_eq!(
+            subpaths("M5,5 L6,5 L6,6"),
+            vec![open(&[(5.0, 5.0), (6.0, 5.0), (6.0, 6.0)])]
+        );
+    }
+
+    #[test]
+    fn drawing_after_close_restarts_at_subpath_start() {
+        assert_eq!(
+            subpaths("M10,10 l10,0 l0,10 z l-5,0 l0,-5 z"),
+            vec![
+                closed(&[(10.0, 10.0), (20.0, 10.0), (20.0, 20.0)]),
+                closed(&[(10.0, 10.0), (5.0, 10.0), (5.0, 5.0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn relative_moveto_after_close() {
+        assert_eq!(
+            subpaths("M10,10 h10 v10 z m5,5 h1 v1 z"),
+            vec![
+                closed(&[(10.0, 10.0), (20.0, 10.0), (20.0, 20.0)]),
+                closed(&[(15.0, 15.0), (16.0, 15.0), (16.0, 16.0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_close_adds_nothing() {
+        assert_eq!(
+            subpaths("M1,1 L2,1 L2,2 Z Z"),
+            vec![closed(&[(1.0, 1.0), (2.0, 1.0), (2.0, 2.0)])]
+        );
+    }
+
+    #[test]
+    fn lone_moveto_is_dropped() {
+        assert_eq!(
+            subpaths("M0,0 M10,10 L20,20"),
+            vec![open(&[(10.0, 10.0), (20.0, 20.0)])]
+        );
+    }
+
+    #[test]
+    fn explicit_closing_point_is_not_duplicated() {
+        assert_eq!(
+            subpaths("M0,0 L10,0 L10,10 L0,0 Z"),
+            vec![closed(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)])]
+        );
+    }
+
+    #[test]
+    fn smooth_cubic_without_previous_curve() {
+        assert_eq!(
+            subpaths("M0,0 S10,10 20,0"),
+            subpaths("M0,0 C0,0 10,10 20,0")
+        );
+    }
+
+    #[test]
+    fn smooth_cubic_reflects_previous_control_point() {
+        assert_eq!(
+            subpaths("M0,0 C0,10 10,10 10,0 s10,-10 10,0"),
+            subpaths("M0,0 C0,10 10,10 10,0 C10,-10 20,-10 20,0")
+        );
+    }
+
+    #[test]
+    fn smooth_quadratic_after_line_is_straight() {
+        assert_eq!(
+            subpaths("M0,0 L10,0 T20,0"),
+            vec![open(&[(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)])]
+        );
+    }
+
+    #[test]
+    fn smooth_quadratic_chain() {
+        assert_eq!(
+            subpaths("M0,0 Q5,10 10,0 T20,0 T30,0"),
+            subpaths("M0,0 Q5,10 10,0 Q15,-10 20,0 Q25,10 30,0")
+        );
+    }
+
+    #[test]
+    fn implicit_curve_repetition() {
+        assert_eq!(
+            subpaths("M0,0 c0,10 10,10 10,0 0,-10 10,-10 10,0"),
+            subpaths("M0,0 C0,10 10,10 10,0 C10,-10 20,-10 20,0")
+        );
+    }
+
+    #[test]
+    fn collinear_curves_past_their_end_points() {
+        let xs = |d| -> Vec<f32> {
+            subpaths(d)[0]
+                .points
+                .iter()
+                .map(|p| p.0)
+                .collect::<Vec<_>>()
+        };
+        let min = |xs: &[f32]| xs.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = |xs: &[f32]| xs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+        // goes back to about -1.9 and forward to about 12.1
+        let cubic = xs("M0,0 C-10,0 20,0 10,0");
+        assert!(min(&cubic) < -1.8, "{:?}", cubic);
+        assert!(max(&cubic) > 12.0, "{:?}", cubic);
+
+        // turns around at 13.33
+        let quadratic = xs("M0,0 Q20,0 10,0");
+        assert!(max(&quadratic) > 13.2, "{:?}", quadratic);
+
+        // control points between the end points are still a line
+        assert_eq!(xs("M0,0 C2,0 8,0 10,0"), vec![0.0, 10.0]);
+    }
+
+    #[test]
+    fn arc_ends_on_endpoint() {
+        let subpaths = subpaths("M0,0 a5,5 0 0 1 10,0");
+        assert_eq!(subpaths.len(), 1);
+        assert_eq!(subpaths[0].points.first(), Some(&(0.0, 0.0)));
+        assert_eq!(subpaths[0].points.last(), Some(&(10.0, 0.0)));
+    }
+}
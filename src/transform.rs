@@ -0,0 +1,213 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::svg_path::Point;
+
+static TRANSFORM_FUNCTION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\s*,?\s*([a-zA-Z]+)\s*\(([^)]*)\)").unwrap());
+
+/// An affine 2D transform, stored like the arguments of SVG's `matrix(a b c d e f)`:
+///
+/// ```text
+/// | a c e |
+/// | b d f |
+/// | 0 0 1 |
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self::matrix(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    pub fn matrix(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Transform { a, b, c, d, e, f }
+    }
+
+    pub fn translate(x: f32, y: f32) -> Self {
+        Self::matrix(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    pub fn scale(x: f32, y: f32) -> Self {
+        Self::matrix(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// Rotation by `degrees` around the origin.
+    pub fn rotate(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self::matrix(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    pub fn skew_x(degrees: f32) -> Self {
+        Self::matrix(1.0, 0.0, degrees.to_radians().tan(), 1.0, 0.0, 0.0)
+    }
+
+    pub fn skew_y(degrees: f32) -> Self {
+        Self::matrix(1.0, degrees.to_radians().tan(), 0.0, 1.0, 0.0, 0.0)
+    }
+
+    /// Returns `self * other`, i.e. `other` is applied first.
+    pub fn then(&self, other: &Transform) -> Transform {
+        Transform {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    pub fn apply(&self, (x, y): Point) -> Point {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+
+    /// The transform that undoes this one, `None` if it collapses the plane onto a line.
+    pub fn inverse(&self) -> Option<Transform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 {
+            return None;
+        }
+        Some(Transform {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+
+    /// Average scale factor, used to keep curve tolerances in world units.
+    pub fn scale_factor(&self) -> f32 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    /// Parses a `transform` attribute like `translate(10,20) rotate(45 5 5)`.
+    ///
+    /// See https://www.w3.org/TR/SVG11/coords.html#TransformAttribute
+    pub fn parse(value: &str) -> Option<Transform> {
+        let mut transform = Transform::identity();
+        let mut parsed_until = 0;
+
+        for captures in TRANSFORM_FUNCTION.captures_iter(value) {
+            let whole = captures.get(0)?;
+            if whole.start() != parsed_until {
+                return None;
+            }
+            parsed_until = whole.end();
+
+            let args = captures
+                .get(2)?
+                .as_str()
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| arg.parse::<f32>().ok())
+                .collect::<Option<Vec<f32>>>()?;
+
+            let next = match (captures.get(1)?.as_str(), args.as_slice()) {
+                ("matrix", [a, b, c, d, e, f]) => Transform::matrix(*a, *b, *c, *d, *e, *f),
+                ("translate", [x]) => Transform::translate(*x, 0.0),
+                ("translate", [x, y]) => Transform::translate(*x, *y),
+                ("scale", [s]) => Transform::scale(*s, *s),
+                ("scale", [x, y]) => Transform::scale(*x, *y),
+                ("rotate", [angle]) => Transform::rotate(*angle),
+                ("rotate", [angle, cx, cy]) => Transform::translate(*cx, *cy)
+                    .then(&Transform::rotate(*angle))
+                    .then(&Transform::translate(-cx, -cy)),
+                ("skewX", [angle]) => Transform::skew_x(*angle),
+                ("skewY", [angle]) => Transform::skew_y(*angle),
+                _ => return None,
+            };
+
+            transform = transform.then(&next);
+        }
+
+        if value[parsed_until..].trim().is_empty() {
+            Some(transform)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transform;
+    use crate::svg_path::Point;
+
+    fn assert_close(a: Point, b: Point) {
+        assert!(
+            (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    fn apply(transform: &str, p: Point) -> Point {
+        Transform::parse(transform).unwrap().apply(p)
+    }
+
+    #[test]
+    fn translate_and_scale() {
+        assert_close(apply("translate(10)", (1.0, 1.0)), (11.0, 1.0));
+        assert_close(apply("translate(10, -5)", (1.0, 1.0)), (11.0, -4.0));
+        assert_close(apply("scale(2)", (1.0, 3.0)), (2.0, 6.0));
+        assert_close(apply("scale(2 -1)", (1.0, 3.0)), (2.0, -3.0));
+    }
+
+    #[test]
+    fn rotate() {
+        assert_close(apply("rotate(90)", (1.0, 0.0)), (0.0, 1.0));
+        assert_close(apply("rotate(180 5 5)", (0.0, 0.0)), (10.0, 10.0));
+    }
+
+    #[test]
+    fn skew() {
+        assert_close(apply("skewX(45)", (0.0, 2.0)), (2.0, 2.0));
+        assert_close(apply("skewY(45)", (2.0, 0.0)), (2.0, 2.0));
+    }
+
+    #[test]
+    fn matrix() {
+        assert_close(apply("matrix(1,0,0,1,3,4)", (1.0, 1.0)), (4.0, 5.0));
+        assert_close(apply("matrix(0 1 -1 0 0 0)", (1.0, 0.0)), (0.0, 1.0));
+    }
+
+    #[test]
+    fn list_is_applied_right_to_left() {
+        assert_close(apply("translate(10,0) scale(2)", (1.0, 1.0)), (12.0, 2.0));
+        assert_close(apply("scale(2), translate(10,0)", (1.0, 1.0)), (22.0, 2.0));
+    }
+
+    #[test]
+    fn inverse() {
+        let transform = Transform::parse("translate(10,20) rotate(30) scale(2, 3)").unwrap();
+        let inverse = transform.inverse().unwrap();
+        assert_close(inverse.apply(transform.apply((1.0, 2.0))), (1.0, 2.0));
+        assert_eq!(Transform::scale(0.0, 1.0).inverse(), None);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(Transform::parse("rotate(1 2)"), None);
+        assert_eq!(Transform::parse("wobble(1)"), None);
+        assert_eq!(Transform::parse("translate(1) garbage"), None);
+    }
+}
@@ -0,0 +1,714 @@
+use crate::svg_path::{Point, Subpath};
+
+/// https://www.w3.org/TR/SVG/painting.html#FillRuleProperty
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd,
+}
+
+impl FillRule {
+    pub fn parse(value: &str) -> Option<FillRule> {
+        match value.trim() {
+            "nonzero" => Some(FillRule::NonZero),
+            "evenodd" => Some(FillRule::EvenOdd),
+            _ => None,
+        }
+    }
+
+    fn is_filled(&self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// Twice the signed area, positive for counter-clockwise rings in a y-up system.
+pub fn signed_area(points: &[Point]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += a.0 * b.1 - b.0 * a.1;
+    }
+    area
+}
+
+pub fn contains(ring: &[Point], (x, y): Point) -> bool {
+    if ring.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+    for i in 0..ring.len() {
+        let (xi, yi) = ring[i];
+        let (xj, yj) = ring[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+// points closer than this to an edge are on it
+const EPSILON: f32 = 1e-4;
+
+// points closer than this, relative to the largest coordinate of the ring, are the same point.
+// f32 coordinates are not much more precise than that.
+const SAME_POINT_EPSILON: f32 = 1e-6;
+
+// corners closer than this to the straight line between their neighbours, relative to the
+// shorter of their edges, are removed
+const STRAIGHT_EPSILON: f32 = 1e-3;
+
+/// Whether any two edges of the closed `ring` cross or touch, apart from neighbouring edges at
+/// their shared corner.
+pub fn self_intersects(ring: &[Point]) -> bool {
+    let mut points = ring.to_vec();
+    points.dedup();
+    while points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    first_intersection(&points).is_some()
+}
+
+/// Whether the segments have any point in common.
+pub fn segments_intersect(a: (Point, Point), b: (Point, Point)) -> bool {
+    intersection(a, b).is_some()
+}
+
+/// Cleans up a closed ring for triangulation: removes repeated points, corners on a straight
+/// line and spikes, then splits the ring where it crosses or touches itself. Every point keeps
+/// its winding number, so the fill is the same with either fill rule.
+pub fn simple_rings(ring: &[Point]) -> Vec<Vec<Point>> {
+    let ring = remove_redundant_points(ring);
+    if ring.len() < 3 {
+        return vec![];
+    }
+
+    match first_intersection(&ring) {
+        None => vec![ring],
+        Some((i, j, point)) => {
+            // both loops are shorter than the ring, so this ends
+            let mut first = ring[i + 1..=j].to_vec();
+            first.push(point);
+            let mut second: Vec<Point> = ring[j + 1..].iter().chain(&ring[..=i]).copied().collect();
+            second.push(point);
+
+            let mut rings = simple_rings(&first);
+            rings.extend(simple_rings(&second));
+            rings
+        }
+    }
+}
+
+// one pass over the ring, each point is checked against the last one kept and the next one.
+// Removing a point makes its neighbours meet, so the last one kept is checked again.
+fn remove_redundant_points(ring: &[Point]) -> Vec<Point> {
+    let size = ring
+        .iter()
+        .fold(0.0, |size: f32, (x, y)| size.max(x.abs()).max(y.abs()));
+    let same = SAME_POINT_EPSILON * size;
+    let redundant = |a: Point, b: Point, c: Point| {
+        let (before, after) = ((b.0 - a.0).hypot(b.1 - a.1), (c.0 - b.0).hypot(c.1 - b.1));
+        let base = (c.0 - a.0).hypot(c.1 - a.1);
+        // a spike goes back to where it came from
+        before <= same
+            || base <= same
+            || orientation(a, c, b).abs() / base <= STRAIGHT_EPSILON * before.min(after)
+    };
+
+    let mut points: Vec<Point> = Vec::with_capacity(ring.len());
+    for point in ring {
+        while let [.., a, b] = points[..] {
+            if !redundant(a, b, *point) {
+                break;
+            }
+            points.pop();
+        }
+        points.push(*point);
+    }
+
+    // the ring is closed, the end is checked against the start until neither changes
+    let mut start = 0;
+    while points.len() - start >= 3 {
+        let last = points.len() - 1;
+        if redundant(points[last - 1], points[last], points[start]) {
+            points.pop();
+        } else if redundant(points[last], points[start], points[start + 1]) {
+            start += 1;
+        } else {
+            break;
+        }
+    }
+    points.drain(..start);
+    points
+}
+
+// the first two edges that cross, or if none do, the first two that touch, apart from
+// neighbouring edges at their shared corner, and the point they have in common
+fn first_intersection(ring: &[Point]) -> Option<(usize, usize, Point)> {
+    let n = ring.len();
+    let edge = |i: usize| (ring[i], ring[(i + 1) % n]);
+    let pairs = overlapping_edges(ring);
+    pairs
+        .iter()
+        .find_map(|&(i, j)| crossing(edge(i), edge(j)).map(|point| (i, j, point)))
+        .or_else(|| {
+            pairs
+                .iter()
+                .find_map(|&(i, j)| intersection(edge(i), edge(j)).map(|point| (i, j, point)))
+        })
+}
+
+// the pairs of edges of a closed ring that are not neighbours and whose bounding boxes
+// overlap, in order. Edges are swept from left to right, so only edges that overlap along x
+// are compared.
+fn overlapping_edges(ring: &[Point]) -> Vec<(usize, usize)> {
+    let n = ring.len();
+    let bounds: Vec<(f32, f32, f32, f32)> = (0..n)
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % n]);
+            (a.0.min(b.0), a.1.min(b.1), a.0.max(b.0), a.1.max(b.1))
+        })
+        .collect();
+    let mut sorted: Vec<usize> = (0..n).collect();
+    sorted.sort_by(|a, b| bounds[*a].0.total_cmp(&bounds[*b].0));
+
+    let mut pairs = vec![];
+    for (k, &a) in sorted.iter().enumerate() {
+        let (_, top, right, bottom) = bounds[a];
+        for &b in &sorted[k + 1..] {
+            let (left, other_top, _, other_bottom) = bounds[b];
+            if left > right {
+                break;
+            }
+            let (i, j) = (a.min(b), a.max(b));
+            // the last edge is a neighbour of the first one
+            let neighbours = j == i + 1 || (i == 0 && j == n - 1);
+            if other_top <= bottom && top <= other_bottom && !neighbours {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+// the first edges of two rings that cross each other, and where
+fn first_crossing(a: &[Point], b: &[Point]) -> Option<(usize, usize, Point)> {
+    (0..a.len()).find_map(|i| {
+        let edge = (a[i], a[(i + 1) % a.len()]);
+        (0..b.len())
+            .find_map(|j| crossing(edge, (b[j], b[(j + 1) % b.len()])).map(|point| (i, j, point)))
+    })
+}
+
+// where the segments cross, if they do and neither of them just ends on the other
+fn crossing((a, b): (Point, Point), (c, d): (Point, Point)) -> Option<Point> {
+    let (d1, d2) = (orientation(c, d, a), orientation(c, d, b));
+    let (d3, d4) = (orientation(a, b, c), orientation(a, b, d));
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        let t = d1 / (d1 - d2);
+        Some((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t))
+    } else {
+        None
+    }
+}
+
+// a point the segments have in common, where they cross or where one ends on the other
+fn intersection((a, b): (Point, Point), (c, d): (Point, Point)) -> Option<Point> {
+    let (d1, d2) = (orientation(c, d, a), orientation(c, d, b));
+    let (d3, d4) = (orientation(a, b, c), orientation(a, b, d));
+    crossing((a, b), (c, d)).or_else(|| {
+        [(d1, c, d, a), (d2, c, d, b), (d3, a, b, c), (d4, a, b, d)]
+            .into_iter()
+            .find(|(side, from, to, point)| *side == 0.0 && in_bounds(*from, *to, *point))
+            .map(|(_, _, _, point)| point)
+    })
+}
+
+// positive if `c` is to the left of the line from `a` to `b`
+fn orientation(a: Point, b: Point, c: Point) -> f32 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+// whether `p` is in the bounding box of the segment from `a` to `b`
+fn in_bounds(a: Point, b: Point, p: Point) -> bool {
+    p.0 >= a.0.min(b.0) && p.0 <= a.0.max(b.0) && p.1 >= a.1.min(b.1) && p.1 <= a.1.max(b.1)
+}
+
+// whether `point` is on one of the edges of `ring`
+fn on_outline(ring: &[Point], point: Point) -> bool {
+    (0..ring.len()).any(|i| {
+        let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+        let length = (b.0 - a.0).hypot(b.1 - a.1);
+        in_bounds(a, b, point) && orientation(a, b, point).abs() <= EPSILON * length
+    })
+}
+
+// a point inside the ring, away from its edges
+fn inside_point(ring: &[Point]) -> Point {
+    let coords: Vec<f32> = ring.iter().flat_map(|(x, y)| [*x, *y]).collect();
+    let point = |index: usize| ring[index];
+    earcutr::earcut(&coords, &[], 2)
+        .array_chunks::<3>()
+        .map(|[a, b, c]| [point(*a), point(*b), point(*c)])
+        .max_by(|a, b| signed_area(a).abs().total_cmp(&signed_area(b).abs()))
+        .map_or(ring[0], |[a, b, c]| {
+            ((a.0 + b.0 + c.0) / 3.0, (a.1 + b.1 + c.1) / 3.0)
+        })
+}
+
+// Earcut fails on holes that touch their outline, so those are cut out of it. A hole that
+// touches the outline in one corner becomes part of it, one that touches it in several corners
+// splits it into the pieces between them. `holes` go in the opposite direction of `outline`.
+fn cut_touching_holes(
+    outline: Vec<Point>,
+    mut holes: Vec<Vec<Point>>,
+) -> Vec<(Vec<Point>, Vec<Vec<Point>>)> {
+    let mut outlines = vec![outline];
+    // cutting out a hole can make others touch the new outlines
+    loop {
+        let touching = holes.iter().enumerate().find_map(|(h, hole)| {
+            outlines.iter().enumerate().find_map(|(o, outline)| {
+                let shared: Vec<(usize, usize)> = outline
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, corner)| {
+                        hole.iter()
+                            .position(|point| point == corner)
+                            .map(|m| (k, m))
+                    })
+                    .collect();
+                (!shared.is_empty()).then_some((h, o, shared))
+            })
+        });
+        let (h, o, shared) = match touching {
+            Some(touching) => touching,
+            None => break,
+        };
+
+        let hole = holes.remove(h);
+        let outline = outlines.remove(o);
+        // the corners of a ring from `from` up to `to`, all of them if those are the same
+        let cyclic = |ring: &[Point], from: usize, to: usize| -> Vec<Point> {
+            let length = match (to + ring.len() - from) % ring.len() {
+                0 => ring.len(),
+                length => length,
+            };
+            (0..length).map(|i| ring[(from + i) % ring.len()]).collect()
+        };
+        if let [(k, m)] = shared[..] {
+            let mut joined = cyclic(&outline, k, k);
+            joined.extend(cyclic(&hole, m, m));
+            outlines.push(joined);
+            continue;
+        }
+        // going around the outline, the corners come up in the reverse order on the hole
+        for (a, b) in shared.iter().zip(shared.iter().cycle().skip(1)) {
+            let mut piece = cyclic(&outline, a.0, b.0);
+            piece.extend(cyclic(&hole, b.1, a.1));
+            outlines.extend(simple_rings(&piece));
+        }
+    }
+
+    let mut pieces: Vec<(Vec<Point>, Vec<Vec<Point>>)> = outlines
+        .into_iter()
+        .map(|outline| (outline, vec![]))
+        .collect();
+    for hole in holes {
+        let piece = pieces
+            .iter_mut()
+            .find(|(outline, _)| contains(outline, hole[0]));
+        if let Some((_, piece_holes)) = piece {
+            piece_holes.push(hole);
+        }
+    }
+    pieces
+}
+
+/// The triangles of a fill, all counter-clockwise.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Triangulation {
+    pub triangles: Vec<[Point; 3]>,
+    /// How far the area of the triangles is off from the area that should be filled. Earcut
+    /// still fails on some paths that cross themselves many times, it leaves out parts of them
+    /// or fills their holes.
+    pub lost_area: f32,
+}
+
+struct Ring {
+    points: Vec<Point>,
+    area: f32,
+    // rings containing this one, any order
+    ancestors: Vec<usize>,
+}
+
+/// Splits `subpaths` into outer rings with their holes according to `fill_rule` and
+/// triangulates each of them. Each subpath is cleaned up with [`simple_rings`] first.
+///
+/// Nesting is decided by testing a corner of each ring against all bigger ones.
+pub fn triangulate(subpaths: &[Subpath], fill_rule: FillRule) -> Triangulation {
+    let mut simple: Vec<Vec<Point>> = subpaths
+        .iter()
+        .flat_map(|subpath| simple_rings(&subpath.points))
+        .collect();
+
+    // rings that cross each other are joined where they cross and split again, which leaves
+    // them touching there. Every join removes a crossing, the limit only guards against
+    // rounding errors adding new ones.
+    let edges: usize = simple.iter().map(Vec::len).sum();
+    let mut joins = 0;
+    let mut i = 0;
+    while i < simple.len() && joins < edges * edges {
+        let found = (i + 1..simple.len())
+            .find_map(|j| first_crossing(&simple[i], &simple[j]).map(|found| (j, found)));
+        match found {
+            Some((j, (a, b, point))) => {
+                let other = simple.remove(j);
+                let ring = simple.remove(i);
+                let joined: Vec<Point> = ring[..=a]
+                    .iter()
+                    .chain([&point])
+                    .chain(&other[b + 1..])
+                    .chain(&other[..=b])
+                    .chain([&point])
+                    .chain(&ring[a + 1..])
+                    .copied()
+                    .collect();
+                simple.extend(simple_rings(&joined));
+                joins += 1;
+            }
+            None => i += 1,
+        }
+    }
+
+    let mut rings: Vec<Ring> = simple
+        .into_iter()
+        .map(|points| Ring {
+            area: signed_area(&points),
+            points,
+            ancestors: vec![],
+        })
+        .collect();
+
+    for i in 0..rings.len() {
+        let ancestors = (0..rings.len())
+            .filter(|j| {
+                let (ring, other) = (&rings[i], &rings[*j]);
+                // of two copies of the same ring, the later one is inside the earlier one
+                let (size, other_size) = (ring.area.abs(), other.area.abs());
+                if size > other_size || (size == other_size && *j >= i) {
+                    return false;
+                }
+                // rings that were split apart share the corners where they touch
+                let point = match ring
+                    .points
+                    .iter()
+                    .find(|point| !on_outline(&other.points, **point))
+                {
+                    Some(point) => *point,
+                    None => inside_point(&ring.points),
+                };
+                contains(&other.points, point)
+            })
+            .collect();
+        rings[i].ancestors = ancestors;
+    }
+
+    let orientation = |ring: &Ring| if ring.area > 0.0 { 1 } else { -1 };
+    let winding_outside = |ring: &Ring| -> i32 {
+        match fill_rule {
+            FillRule::NonZero => ring.ancestors.iter().map(|j| orientation(&rings[*j])).sum(),
+            FillRule::EvenOdd => ring.ancestors.len() as i32,
+        }
+    };
+    let winding_inside = |ring: &Ring| -> i32 {
+        match fill_rule {
+            FillRule::NonZero => winding_outside(ring) + orientation(ring),
+            FillRule::EvenOdd => winding_outside(ring) + 1,
+        }
+    };
+
+    let is_outer = |ring: &Ring| {
+        fill_rule.is_filled(winding_inside(ring)) && !fill_rule.is_filled(winding_outside(ring))
+    };
+    let is_hole = |ring: &Ring| {
+        !fill_rule.is_filled(winding_inside(ring)) && fill_rule.is_filled(winding_outside(ring))
+    };
+
+    let mut triangles = vec![];
+    let mut area = 0.0;
+
+    for (i, outer) in rings.iter().enumerate() {
+        if is_hole(outer) {
+            area -= outer.area.abs() / 2.0;
+        }
+        if !is_outer(outer) {
+            continue;
+        }
+        area += outer.area.abs() / 2.0;
+
+        let mut holes = vec![];
+        for hole in rings.iter().filter(|ring| is_hole(ring)) {
+            // the hole belongs to the smallest outer ring around it
+            let owner = hole
+                .ancestors
+                .iter()
+                .filter(|j| is_outer(&rings[**j]))
+                .min_by(|a, b| {
+                    rings[**a]
+                        .area
+                        .abs()
+                        .partial_cmp(&rings[**b].area.abs())
+                        .unwrap()
+                });
+            if owner == Some(&i) {
+                let mut points = hole.points.clone();
+                if (hole.area > 0.0) == (outer.area > 0.0) {
+                    points.reverse();
+                }
+                holes.push(points);
+            }
+        }
+
+        for (outline, holes) in cut_touching_holes(outer.points.clone(), holes) {
+            let mut coords: Vec<f32> = outline.iter().flat_map(|(x, y)| [*x, *y]).collect();
+            let mut hole_indices = vec![];
+            for hole in holes {
+                hole_indices.push(coords.len() / 2);
+                coords.extend(hole.iter().flat_map(|(x, y)| [*x, *y]));
+            }
+
+            let point = |index: usize| (coords[index * 2], coords[index * 2 + 1]);
+            for [a, b, c] in earcutr::earcut(&coords, &hole_indices, 2).array_chunks::<3>() {
+                let triangle = [point(*a), point(*b), point(*c)];
+                if signed_area(&triangle) < 0.0 {
+                    triangles.push([triangle[0], triangle[2], triangle[1]]);
+                } else {
+                    triangles.push(triangle);
+                }
+            }
+        }
+    }
+
+    let covered: f32 = triangles
+        .iter()
+        .map(|triangle| signed_area(triangle) / 2.0)
+        .sum();
+    Triangulation {
+        triangles,
+        lost_area: (area - covered).abs(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{contains, self_intersects, signed_area, simple_rings, triangulate, FillRule};
+    use crate::svg_path::{flatten_path, Point};
+    use svg::node::element::path::Data;
+
+    fn filled_area(d: &str, fill_rule: FillRule) -> f32 {
+        let subpaths = flatten_path(&Data::parse(d).unwrap(), 0.1);
+        triangulate(&subpaths, fill_rule)
+            .triangles
+            .iter()
+            .map(|triangle: &[Point; 3]| signed_area(triangle).abs() / 2.0)
+            .sum()
+    }
+
+    // outer square 10x10 with a clockwise and a counter-clockwise 2x2 square inside
+    const SAME_DIRECTION: &str = "M0,0 H10 V10 H0 Z M2,2 H4 V4 H2 Z";
+    const OPPOSITE_DIRECTION: &str = "M0,0 H10 V10 H0 Z M2,2 V4 H4 V2 Z";
+
+    #[test]
+    fn evenodd_cuts_holes_regardless_of_direction() {
+        assert_eq!(filled_area(SAME_DIRECTION, FillRule::EvenOdd), 96.0);
+        assert_eq!(filled_area(OPPOSITE_DIRECTION, FillRule::EvenOdd), 96.0);
+    }
+
+    #[test]
+    fn nonzero_cuts_holes_only_in_opposite_direction() {
+        assert_eq!(filled_area(SAME_DIRECTION, FillRule::NonZero), 100.0);
+        assert_eq!(filled_area(OPPOSITE_DIRECTION, FillRule::NonZero), 96.0);
+    }
+
+    #[test]
+    fn island_inside_hole() {
+        let d = "M0,0 H10 V10 H0 Z M2,2 H8 V8 H2 Z M4,4 H6 V6 H4 Z";
+        assert_eq!(filled_area(d, FillRule::EvenOdd), 100.0 - 36.0 + 4.0);
+    }
+
+    #[test]
+    fn separate_outer_rings() {
+        let d = "M0,0 H2 V2 H0 Z M5,5 H8 V8 H5 Z";
+        assert_eq!(filled_area(d, FillRule::NonZero), 4.0 + 9.0);
+        assert_eq!(filled_area(d, FillRule::EvenOdd), 4.0 + 9.0);
+    }
+
+    #[test]
+    fn self_intersections() {
+        let square = [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let bow_tie = [(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)];
+        // the corner at (1, 1) is visited twice
+        let pinched = [
+            (0.0, 0.0),
+            (1.0, 1.0),
+            (2.0, 0.0),
+            (2.0, 2.0),
+            (1.0, 1.0),
+            (0.0, 2.0),
+        ];
+        let repeated = [(0.0, 0.0), (2.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 0.0)];
+
+        assert!(!self_intersects(&square));
+        assert!(self_intersects(&bow_tie));
+        assert!(self_intersects(&pinched));
+        assert!(!self_intersects(&repeated));
+    }
+
+    #[test]
+    fn cleanup() {
+        // Inkscape repeats the first node before closing, the node at (5, 0) is on a straight
+        // line and the one at (10, 15) was dragged back to where the path came from
+        let ring = [
+            (0.0, 0.0),
+            (5.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (10.0, 15.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (0.0, 0.0),
+        ];
+        assert_eq!(
+            simple_rings(&ring),
+            vec![vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]]
+        );
+
+        let bow_tie = [(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)];
+        assert_eq!(
+            simple_rings(&bow_tie),
+            vec![
+                vec![(2.0, 2.0), (2.0, 0.0), (1.0, 1.0)],
+                vec![(0.0, 2.0), (0.0, 0.0), (1.0, 1.0)],
+            ]
+        );
+        assert_eq!(
+            simple_rings(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]),
+            vec![] as Vec<Vec<_>>
+        );
+    }
+
+    #[test]
+    fn broken_paths() {
+        let cases = [
+            // a rectangle twisted into a figure eight
+            ("M0,0 L10,10 L10,0 L0,10 Z", 50.0, 50.0),
+            // a line that curls around into the shape
+            ("M0,0 H10 V10 H2 V2 H8 V12 H0 Z", 116.0, 68.0),
+            // the union of two squares that meet in a corner, visiting it twice
+            ("M0,0 H1 V1 H2 V2 H1 V1 H0 Z", 2.0, 2.0),
+            // two paths combined into one
+            ("M0,0 H10 V10 H0 Z M5,5 H15 V15 H5 Z", 175.0, 150.0),
+            // a subpath that was pasted twice
+            ("M0,0 H10 V10 H0 Z M0,0 H10 V10 H0 Z", 100.0, 0.0),
+            // a hole touching the outline
+            ("M0,0 H10 V10 H0 Z M5,0 L7,5 L3,5 Z", 100.0, 90.0),
+            // the spike from a node dragged outside and back
+            ("M0,0 H10 V10 H5 V15 V10 H0 Z", 100.0, 100.0),
+        ];
+        for (d, nonzero, evenodd) in cases {
+            assert_eq!(filled_area(d, FillRule::NonZero), nonzero, "{d}");
+            assert_eq!(filled_area(d, FillRule::EvenOdd), evenodd, "{d}");
+        }
+    }
+
+    #[test]
+    fn exported_paths() {
+        let cases = [
+            // pixel coordinates far from the origin, the nodes between the corners are on the
+            // edges as far as f32 can tell
+            (
+                "M3012.27,2048.15 L3412.27,2048.1501 L3812.27,2048.15 L3812.27,2448.15 \
+                 L3412.27,2448.1499 L3012.27,2448.15 Z",
+                4,
+            ),
+            // a drawing in meters, with a 5 cm notch that is not noise
+            ("M0.1,0.1 H0.4 V0.4 H0.25 V0.35 H0.2 V0.4 H0.1 Z", 8),
+            // closing segments written out as curves without length, and a node that was
+            // dragged back onto the start
+            (
+                "M10,10 C10,10 20,10 20,10 C20,10 20,20 20,20 L10,20 L10,10.00001 \
+                 C10,10 10,10 10,10 Z",
+                4,
+            ),
+        ];
+        for (d, corners) in cases {
+            let subpaths = flatten_path(&Data::parse(d).unwrap(), 0.1);
+            let rings = simple_rings(&subpaths[0].points);
+            assert_eq!(rings.len(), 1, "{d}");
+            assert_eq!(rings[0].len(), corners, "{d}: {:?}", rings[0]);
+        }
+
+        // thousands of nodes along the edges, like a traced bitmap
+        let mut ring: Vec<Point> = (0..20_000).map(|i| (i as f32 * 0.5, 0.0)).collect();
+        ring.extend([(10_000.0, 0.0), (10_000.0, 10.0), (0.0, 10.0)]);
+        ring.extend((0..20_000).map(|i| (0.0, 10.0 - i as f32 * 0.0005)));
+        let rings = simple_rings(&ring);
+        assert_eq!(
+            rings,
+            vec![vec![
+                (0.0, 0.0),
+                (10_000.0, 0.0),
+                (10_000.0, 10.0),
+                (0.0, 10.0)
+            ]]
+        );
+    }
+
+    #[test]
+    fn large_rings() {
+        // a hand-traced figure eight, thousands of nodes that are all corners and one crossing
+        let n = 10_000;
+        let ring: Vec<Point> = (0..n)
+            .map(|i| {
+                let t = (i as f32 + 0.5) / n as f32 * std::f32::consts::TAU;
+                // jagged, like the pixels of a traced bitmap
+                let jag = if i % 2 == 0 { 1.01 } else { 0.99 };
+                (1000.0 * jag * t.sin(), 500.0 * jag * (2.0 * t).sin())
+            })
+            .collect();
+        let rings = simple_rings(&ring);
+        assert_eq!(rings.len(), 2);
+        // only the nodes close to the crossing are too close together to be corners
+        assert!(rings.iter().map(Vec::len).sum::<usize>() > n * 9 / 10);
+        assert!(rings.iter().all(|ring| !self_intersects(ring)));
+
+        assert!(contains(&rings[0], (500.0, 0.0)) != contains(&rings[1], (500.0, 0.0)));
+        assert!(!contains(&[], (0.0, 0.0)));
+        assert!(!contains(&[(0.0, 0.0), (1.0, 1.0)], (0.5, 0.5)));
+    }
+
+    #[test]
+    fn lost_area() {
+        let triangulated = |d: &str| {
+            let subpaths = flatten_path(&Data::parse(d).unwrap(), 0.1);
+            triangulate(&subpaths, FillRule::EvenOdd)
+        };
+        let repaired = triangulated("M0,0 H10 V10 H2 V2 H8 V12 H0 Z");
+        assert!(repaired.lost_area < 1e-4);
+        assert!(repaired
+            .triangles
+            .iter()
+            .all(|triangle| signed_area(triangle) > 0.0));
+
+        // scribbles that cross each other many times are more than earcut can handle
+        let scribbles = triangulated("M6,7 L2,8 L3,5 L4,5 L0,8 Z M4,0 L1,8 L7,0 L2,6 L6,4 Z");
+        assert!(scribbles.lost_area > 1.0);
+    }
+}
@@ -1,5 +1,6 @@
-use crate::{sick_physics::Physics, user_input::UserInput};
+use crate::{export::Snapshot, mesh::StaticMesh, sick_physics::Physics, user_input::UserInput};
 use nphysics2d::object::{DefaultBodyHandle, DefaultColliderHandle};
+use serde::{Deserialize, Serialize};
 use std::{cell::RefCell, collections::HashMap, mem, rc::Rc};
 
 pub type Color = [f32; 4];
@@ -34,9 +35,15 @@ pub fn cyan() -> Color {
     [0.0, 1.0, 1.0, 1.0]
 }
 
+// draw order, see `Entity::z_index`. Level layers are drawn at their own z-index, which is 0
+// unless the SVG sets it, so a layer with a z-index of 35 is in front of the player.
+pub const Z_FISH: i32 = 10;
+pub const Z_PLAYER: i32 = 30;
+pub const Z_HOOK: i32 = 40;
+
 pub trait Entity {
     fn id(&self) -> &String;
-    fn triangles(&self) -> &Vec<Triangle>;
+    fn triangles(&self) -> &[Triangle];
     fn update(&mut self, _time_passed: f32, _game_state: &mut GameState) {}
     fn position(&self) -> (f32, f32) {
         return (0.0, 0.0);
@@ -50,6 +57,19 @@ pub trait Entity {
     ) -> Option<(DefaultBodyHandle, DefaultColliderHandle)> {
         None
     }
+    /// Entities are drawn by increasing z-index, the ones with the same z-index by id.
+    fn z_index(&self) -> i32 {
+        0
+    }
+    /// Triangles that rarely change, drawn from buffers that stay on the GPU instead of
+    /// [`Entity::triangles`]. `None` for entities that change every frame.
+    fn static_meshes(&self) -> Option<Vec<StaticMesh>> {
+        None
+    }
+    /// Adds the entity to an SVG export of the world, see [`crate::export`].
+    fn export(&self, snapshot: &mut Snapshot) {
+        snapshot.add_entity(self);
+    }
 }
 
 pub struct EntityEntry {
@@ -142,24 +162,45 @@ pub struct GameState<'a> {
     pub physics: &'a mut Physics,
     pub entities: &'a Entities,
     pub entity_ops: &'a mut EntityOps,
+    /// The point in the middle of the screen.
+    pub camera_center: (f32, f32),
+    /// Where the mouse points in world coordinates, if it is over the canvas.
+    pub mouse: Option<(f32, f32)>,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Triangle {
     pub coords: [f32; 9],
     pub color: [f32; 4],
+    #[serde(default, skip_serializing_if = "ShaderId::is_default")]
     pub shader_id: ShaderId,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub wireframe: bool,
+    /// Colors of the three corners, interpolated across the triangle. `color` is used for all
+    /// corners if this is `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vertex_colors: Option<[Color; 3]>,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum ShaderId {
     #[default]
     Default,
-    Water,
+    /// Animated water below the `surface` height of its volume.
+    Water { surface: f32 },
+}
+
+impl ShaderId {
+    fn is_default(&self) -> bool {
+        *self == ShaderId::Default
+    }
 }
 
 impl Triangle {
+    pub fn vertex_colors(&self) -> [Color; 3] {
+        self.vertex_colors.unwrap_or([self.color; 3])
+    }
+
     pub fn new(coords: [f32; 9], color: [f32; 4]) -> Triangle {
         Triangle {
             coords,
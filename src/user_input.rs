@@ -4,12 +4,17 @@ use std::rc::Rc;
 use wasm_bindgen::JsCast;
 
 use wasm_bindgen::prelude::Closure;
+use web_sys::HtmlCanvasElement;
 
 #[derive(Default, Clone)]
 pub struct UserInput {
     pub move_left: bool,
     pub move_right: bool,
     pub throw_rod: bool,
+    /// Logs the world as SVG for bug reports, see `Game::export_svg`.
+    pub export_world: bool,
+    /// Where the mouse is over the canvas, in canvas pixels from the top left.
+    pub mouse: Option<(f32, f32)>,
 }
 
 pub struct InputHandler {
@@ -39,6 +44,7 @@ impl InputHandler {
                 s.move_left = event.key() == "a";
                 s.move_right = event.key() == "d";
                 s.throw_rod = event.key() == " ";
+                s.export_world = event.key() == "p";
             }) as Box<dyn FnMut(_)>);
             canvas
                 .add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref())
@@ -62,6 +68,35 @@ impl InputHandler {
                 .unwrap();
             closure.forget();
         }
+        {
+            let state = self.current_state.clone();
+            let element = canvas.clone().dyn_into::<HtmlCanvasElement>().unwrap();
+            let closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+                // the canvas can be scaled by CSS
+                let scale = (
+                    element.width() as f32 / element.client_width().max(1) as f32,
+                    element.height() as f32 / element.client_height().max(1) as f32,
+                );
+                state.borrow_mut().mouse = Some((
+                    event.offset_x() as f32 * scale.0,
+                    event.offset_y() as f32 * scale.1,
+                ));
+            }) as Box<dyn FnMut(_)>);
+            canvas
+                .add_event_listener_with_callback("mousemove", closure.as_ref().unchecked_ref())
+                .unwrap();
+            closure.forget();
+        }
+        {
+            let state = self.current_state.clone();
+            let closure = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+                state.borrow_mut().mouse = None;
+            }) as Box<dyn FnMut(_)>);
+            canvas
+                .add_event_listener_with_callback("mouseleave", closure.as_ref().unchecked_ref())
+                .unwrap();
+            closure.forget();
+        }
     }
 
     pub fn current_state(&self) -> UserInput {
@@ -72,5 +107,6 @@ impl InputHandler {
     pub fn after_update(&mut self) {
         let mut state = self.current_state.borrow_mut();
         state.throw_rod = false;
+        state.export_world = false;
     }
 }
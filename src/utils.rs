@@ -43,6 +43,16 @@ pub fn uint_16_array(slice: &[u16]) -> Result<js_sys::Uint16Array, JsValue> {
     Ok(array)
 }
 
+pub fn uint_32_array(slice: &[u32]) -> Result<js_sys::Uint32Array, JsValue> {
+    let memory_buffer = wasm_bindgen::memory()
+        .dyn_into::<WebAssembly::Memory>()?
+        .buffer();
+    let arr_location = slice.as_ptr() as u32 / 4;
+    let array = js_sys::Uint32Array::new(&memory_buffer)
+        .subarray(arr_location, arr_location + slice.len() as u32);
+    Ok(array)
+}
+
 pub fn compile_shader(
     context: &WebGlRenderingContext,
     shader_type: u32,